@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+use hometree_core::config::SnapshotConfig;
+use hometree_core::Schedule;
+use time::OffsetDateTime;
+
+/// Decides when the daemon should commit the index on its own.
+#[derive(Debug)]
+pub struct AutoSnapshot {
+    interval: Option<Duration>,
+    quiet: Option<Duration>,
+    schedule: Option<Schedule>,
+    last_snapshot: Instant,
+    last_staged: Option<Instant>,
+    last_schedule_minute: Option<i64>,
+    schedule_pending: bool,
+}
+
+impl AutoSnapshot {
+    /// Returns `None` when no snapshot trigger is configured.
+    pub fn from_config(
+        config: &SnapshotConfig,
+        now: Instant,
+    ) -> hometree_core::Result<Option<Self>> {
+        if !config.auto_enabled() {
+            return Ok(None);
+        }
+        let schedule = config
+            .schedule
+            .as_deref()
+            .map(str::parse::<Schedule>)
+            .transpose()?;
+        Ok(Some(Self {
+            interval: config.interval_minutes.map(minutes),
+            quiet: config.quiet_minutes.map(minutes),
+            schedule,
+            last_snapshot: now,
+            last_staged: None,
+            last_schedule_minute: None,
+            schedule_pending: false,
+        }))
+    }
+
+    /// Record that the daemon just staged changes.
+    pub fn staged(&mut self, now: Instant) {
+        self.last_staged = Some(now);
+    }
+
+    /// Whether any trigger fired. The caller still checks that the index is non-empty.
+    /// A scheduled trigger stays due until `snapshotted` is called.
+    pub fn is_due(&mut self, now: Instant, wall: OffsetDateTime) -> bool {
+        let interval_due = self
+            .interval
+            .is_some_and(|interval| now.duration_since(self.last_snapshot) >= interval);
+        let quiet_due = match (self.quiet, self.last_staged) {
+            (Some(quiet), Some(staged)) => now.duration_since(staged) >= quiet,
+            _ => false,
+        };
+        if let Some(schedule) = &self.schedule {
+            let minute = wall.unix_timestamp().div_euclid(60);
+            if self.last_schedule_minute != Some(minute) && schedule.matches(wall) {
+                self.last_schedule_minute = Some(minute);
+                self.schedule_pending = true;
+            }
+        }
+        interval_due || quiet_due || self.schedule_pending
+    }
+
+    /// Reset the timers after a snapshot attempt, whether or not it committed anything.
+    pub fn snapshotted(&mut self, now: Instant) {
+        self.last_snapshot = now;
+        self.last_staged = None;
+        self.schedule_pending = false;
+    }
+}
+
+fn minutes(value: u64) -> Duration {
+    Duration::from_secs(value.saturating_mul(60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AutoSnapshot;
+    use hometree_core::config::SnapshotConfig;
+    use std::time::{Duration, Instant};
+    use time::OffsetDateTime;
+
+    fn config() -> SnapshotConfig {
+        SnapshotConfig {
+            auto_message_template: None,
+            interval_minutes: None,
+            quiet_minutes: None,
+            schedule: None,
+            auto_push_remote: None,
+        }
+    }
+
+    #[test]
+    fn disabled_without_triggers() {
+        let auto = AutoSnapshot::from_config(&config(), Instant::now()).expect("config");
+        assert!(auto.is_none());
+    }
+
+    #[test]
+    fn quiet_period_waits_for_staged_changes() {
+        let start = Instant::now();
+        let wall = OffsetDateTime::UNIX_EPOCH;
+        let mut cfg = config();
+        cfg.quiet_minutes = Some(5);
+        let mut auto = AutoSnapshot::from_config(&cfg, start)
+            .expect("config")
+            .expect("enabled");
+        assert!(!auto.is_due(start + Duration::from_secs(3600), wall));
+        auto.staged(start);
+        assert!(!auto.is_due(start + Duration::from_secs(299), wall));
+        assert!(auto.is_due(start + Duration::from_secs(300), wall));
+        auto.snapshotted(start + Duration::from_secs(300));
+        assert!(!auto.is_due(start + Duration::from_secs(900), wall));
+    }
+
+    #[test]
+    fn interval_and_schedule_fire_once() {
+        let start = Instant::now();
+        let mut cfg = config();
+        cfg.interval_minutes = Some(10);
+        cfg.schedule = Some("0 * * * *".to_string());
+        let mut auto = AutoSnapshot::from_config(&cfg, start)
+            .expect("config")
+            .expect("enabled");
+        let off_schedule = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(60);
+        assert!(!auto.is_due(start + Duration::from_secs(60), off_schedule));
+        assert!(auto.is_due(start + Duration::from_secs(600), off_schedule));
+        auto.snapshotted(start + Duration::from_secs(600));
+
+        let on_schedule = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(3600);
+        assert!(auto.is_due(start + Duration::from_secs(601), on_schedule));
+        assert!(auto.is_due(start + Duration::from_secs(602), off_schedule));
+        auto.snapshotted(start + Duration::from_secs(603));
+        assert!(!auto.is_due(start + Duration::from_secs(604), on_schedule));
+    }
+}
@@ -1,24 +1,37 @@
 use std::collections::BTreeSet;
 use std::fs::{self, OpenOptions};
-use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::io::{BufReader, BufWriter, Write};
 use std::os::unix::fs::PermissionsExt;
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
 use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{anyhow, Context, Result};
 use fs2::FileExt;
+use hometree_cli::autosnapshot::AutoSnapshot;
 use hometree_cli::debounce::Debounce;
+use hometree_cli::fswatch::{EventResult, WatchStrategy, Watchers};
+use hometree_cli::metrics;
+use hometree_cli::systemd::{self, UnitOptions};
+use hometree_cli::throttle::{self, RateLimit, ThrottleReason};
 use hometree_cli::watch::{
-    build_allowlist, collect_watch_decisions, should_handle_event, watch_paths, WatchDecisions,
+    build_allowlist, collect_watch_decisions, decide_watch_action, glob_roots, glob_watch_targets,
+    moved_entries, queue_rename, recursive_at_root, rename_pair, should_handle_event, watch_paths,
+    DebounceWindows, WatchAction, WatchDecisions, WatchTarget,
 };
+use hometree_core::config::TimeWindow;
 use hometree_core::git::{AddMode, GitBackend, GitCliBackend};
+use hometree_core::ipc::{
+    self, Client, DaemonEvent, DaemonMetrics, DaemonStatus, EventRecord, Request, Response,
+};
 use hometree_core::{
-    active_inhibit, clear_inhibit, lock_path, write_inhibit, AgeBackend, InhibitMarker, ManagedSet,
-    Paths, SecretsBackend, SecretsManager,
+    active_inhibit, clear_inhibit, lock_path, read_secret_state, snapshot::DEFAULT_AUTO_TEMPLATE,
+    write_inhibit, write_secret_state, AgeBackend, Effects, HometreeError, InhibitMarker,
+    ManagedSet, Paths, Plaintext, SecretsBackend, SecretsManager,
 };
-use notify::{RecursiveMode, Watcher};
+use notify::RecursiveMode;
 use serde::{Deserialize, Serialize};
 use signal_hook::consts::signal::SIGHUP;
 use signal_hook::iterator::Signals;
@@ -28,9 +41,17 @@ use tracing::{debug, error, info, warn};
 
 use crate::{load_config, load_paths, DaemonCommand, Overrides};
 
-const SOCKET_FILENAME: &str = "daemon.sock";
-const IPC_MAX_LINE: usize = 64 * 1024;
 const INHIBIT_TTL_DEFAULT: Duration = Duration::from_secs(300);
+const MAX_SUBSCRIBERS: usize = 16;
+/// Connections served at once, event subscriptions included.
+const MAX_IPC_CONNECTIONS: usize = MAX_SUBSCRIBERS + 16;
+/// How long a connection may sit idle between requests before it is closed.
+#[cfg(not(test))]
+const IPC_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+#[cfg(test)]
+const IPC_IDLE_TIMEOUT: Duration = Duration::from_millis(500);
+const SUBSCRIBER_BUFFER: usize = 256;
+const SUBSCRIBER_PROBE_INTERVAL: Duration = Duration::from_secs(1);
 
 pub fn run_daemon_command(
     overrides: &Overrides,
@@ -54,28 +75,60 @@ pub fn run_daemon_command(
             run_daemon_foreground(overrides, foreground || cmd_fg)
         }
         DaemonCommand::Foreground => run_daemon_foreground(overrides, true),
-        DaemonCommand::InstallSystemd => {
-            let paths = load_paths(overrides)?;
-            install_systemd_unit(&paths)
+        DaemonCommand::InstallSystemd { socket, no_harden } => {
+            install_systemd_unit(overrides, socket, !no_harden)
         }
         DaemonCommand::UninstallSystemd => {
             let paths = load_paths(overrides)?;
             uninstall_systemd_unit(&paths)
         }
-        DaemonCommand::Start => systemctl_user(&["enable", "--now", "hometree.service"]),
-        DaemonCommand::Stop => systemctl_user(&["disable", "--now", "hometree.service"]),
-        DaemonCommand::Restart => systemctl_user(&["restart", "hometree.service"]),
-        DaemonCommand::Status => daemon_status_cmd(overrides),
-        DaemonCommand::Reload => daemon_simple_cmd(overrides, "reload", None, None),
+        DaemonCommand::Start => {
+            let paths = load_paths(overrides)?;
+            let unit = if systemd_unit_dir(&paths).join(systemd::SOCKET_UNIT).exists() {
+                systemd::SOCKET_UNIT
+            } else {
+                systemd::SERVICE_UNIT
+            };
+            systemctl_user(&["enable", "--now", unit])
+        }
+        DaemonCommand::Stop => {
+            let paths = load_paths(overrides)?;
+            if systemd_unit_dir(&paths).join(systemd::SOCKET_UNIT).exists() {
+                // Stop the socket first so a client cannot start the service again.
+                systemctl_user(&["disable", "--now", systemd::SOCKET_UNIT])?;
+                systemctl_user(&["stop", systemd::SERVICE_UNIT])
+            } else {
+                systemctl_user(&["disable", "--now", systemd::SERVICE_UNIT])
+            }
+        }
+        DaemonCommand::Restart => systemctl_user(&["restart", systemd::SERVICE_UNIT]),
+        DaemonCommand::Status { json } => daemon_status_cmd(overrides, json),
+        DaemonCommand::Metrics => daemon_metrics_cmd(overrides),
+        DaemonCommand::Reload => daemon_simple_cmd(overrides, Request::Reload),
         DaemonCommand::Pause { ttl_ms, reason } => daemon_simple_cmd(
             overrides,
-            "pause",
-            Some(Duration::from_millis(ttl_ms)),
-            Some(reason),
+            Request::Pause {
+                ttl_ms: Some(ttl_ms),
+                reason: Some(reason),
+            },
         ),
-        DaemonCommand::Resume => daemon_simple_cmd(overrides, "resume", None, None),
-        DaemonCommand::Flush => daemon_simple_cmd(overrides, "flush", None, None),
+        DaemonCommand::Resume => daemon_simple_cmd(overrides, Request::Resume),
+        DaemonCommand::Flush => daemon_simple_cmd(overrides, Request::Flush),
+        DaemonCommand::Events => daemon_events_cmd(overrides),
+    }
+}
+
+fn daemon_events_cmd(overrides: &Overrides) -> Result<()> {
+    let paths = load_paths(overrides)?;
+    let mut events = connect(&paths)?.subscribe().context("subscribe")?;
+    let stdout = std::io::stdout();
+    while let Some(line) = events.next_line()? {
+        let mut out = stdout.lock();
+        out.write_all(line.as_bytes())?;
+        out.write_all(b"\n")?;
+        out.flush()?;
     }
+    Ok(())
 }
 
 pub struct DaemonInhibitGuard {
@@ -104,13 +157,18 @@ impl Drop for DaemonInhibitGuard {
     }
 }
 
-fn daemon_status_cmd(overrides: &Overrides) -> Result<()> {
+fn daemon_status_cmd(overrides: &Overrides, json: bool) -> Result<()> {
     let paths = load_paths(overrides)?;
     match ipc_status(&paths) {
+        Ok(status) if json => {
+            println!("{}", serde_json::to_string_pretty(&status)?);
+            Ok(())
+        }
         Ok(status) => {
             print_status(&status);
             Ok(())
         }
+        Err(err) if json => Err(anyhow!("daemon not reachable: {err}")),
         Err(err) => {
             eprintln!("daemon not reachable: {err}");
             eprintln!("hint: run `hometree daemon start`");
@@ -120,48 +178,71 @@ fn daemon_status_cmd(overrides: &Overrides) -> Result<()> {
     }
 }
 
-fn daemon_simple_cmd(
-    overrides: &Overrides,
-    cmd: &str,
-    ttl: Option<Duration>,
-    reason: Option<String>,
-) -> Result<()> {
+fn daemon_metrics_cmd(overrides: &Overrides) -> Result<()> {
     let paths = load_paths(overrides)?;
-    ipc_command(&paths, cmd, ttl, reason)
+    let status = ipc_status(&paths).context("daemon not reachable")?;
+    print!("{}", metrics::render(&status));
+    Ok(())
+}
+
+fn daemon_simple_cmd(overrides: &Overrides, request: Request) -> Result<()> {
+    let paths = load_paths(overrides)?;
+    connect(&paths)?.command(&request)?;
+    Ok(())
 }
 
 fn run_daemon_foreground(overrides: &Overrides, _foreground: bool) -> Result<()> {
+    // Before any thread starts: this clears the activation environment.
+    let activated = systemd::activated_listener().context("socket activation")?;
     let mut ctx = DaemonContext::load(overrides)?;
     let runtime_dir = ensure_runtime_dir(&ctx.paths)?;
-    let socket_path = runtime_dir.join(SOCKET_FILENAME);
-
-    if socket_in_use(&socket_path)? {
-        return Err(anyhow!("daemon already running"));
-    }
-    if socket_path.exists() {
-        let _ = fs::remove_file(&socket_path);
-    }
+    let socket_path = runtime_dir.join(ipc::SOCKET_FILENAME);
+
+    // A socket passed by systemd belongs to the socket unit; never remove it.
+    let owns_socket = activated.is_none();
+    let listener = match activated {
+        Some(listener) => {
+            info!("using socket passed by systemd");
+            listener
+        }
+        None => bind_socket(&socket_path)?,
+    };
 
+    let mut glob_targets =
+        glob_watch_targets(ctx.paths.home_dir(), &ctx.glob_roots, &ctx.watch_roots);
+    let (mut watcher, mut rx) = setup_watcher(&ctx, &glob_targets)?;
     let shared = Arc::new(Mutex::new(DaemonShared::new(
         ctx.paths.config_file(),
-        ctx.watch_roots.clone(),
+        status_watch_roots(&ctx, &glob_targets, &watcher),
     )));
 
     let (control_tx, control_rx) = mpsc::channel();
-    start_ipc_server(&socket_path, control_tx.clone(), shared.clone())?;
+    start_ipc_server(listener, control_tx.clone(), shared.clone());
     install_sighup_handler(control_tx.clone())?;
 
-    let (mut _watcher, mut rx) = setup_watcher(&ctx.paths, &ctx.watch_roots)?;
-    let mut debouncer = Debounce::new(ctx.debounce);
-    let mut secrets_debouncer = Debounce::new(ctx.debounce);
+    let mut debouncer = Debounce::new(ctx.debounce.default_window());
+    let mut secrets_debouncer = Debounce::new(ctx.debounce.default_window());
     let mut auto_add_queue: BTreeSet<PathBuf> = BTreeSet::new();
+    let mut renames: Vec<(PathBuf, PathBuf)> = Vec::new();
     let mut backoff = Backoff::new();
     let mut pause_until: Option<Instant> = None;
     let mut pause_reason: Option<String> = None;
     let mut reload_response: Option<mpsc::Sender<Result<()>>> = None;
     let mut reload_requested = false;
     let mut force_flush = false;
-    let mut last_inhibit_check = Instant::now();
+    let mut last_inhibit_check: Option<Instant> = None;
+    let mut auto_snapshot = AutoSnapshot::from_config(&ctx.config.snapshot, Instant::now())?;
+    let mut push_backoff = Backoff::with_limits(PUSH_RETRY_INITIAL, PUSH_RETRY_MAX);
+    let mut push_pending = false;
+    let mut push_job: Option<mpsc::Receiver<PushResult>> = None;
+    let mut last_push_check: Option<Instant> = None;
+    let mut last_metrics_write: Option<Instant> = None;
+    let mut rate_limit = ctx.rate_limit();
+    let mut on_battery = false;
+    let mut last_battery_check: Option<Instant> = None;
+    let mut held: Option<(ThrottleReason, Option<SystemTime>)> = None;
+    let mut rate_held: Option<(ThrottleReason, Option<SystemTime>)> = None;
+    update_push_remote(&shared, ctx.config.snapshot.auto_push_remote.clone());
 
     loop {
         while let Ok(msg) = control_rx.try_recv() {
@@ -176,6 +257,7 @@ fn run_daemon_foreground(overrides: &Overrides, _foreground: bool) -> Result<()>
                     debouncer.clear();
                     secrets_debouncer.clear();
                     auto_add_queue.clear();
+                    renames.clear();
                     update_inhibit(&shared, true, Some(reason));
                     respond_if_needed(respond, Ok(()));
                 }
@@ -195,7 +277,9 @@ fn run_daemon_foreground(overrides: &Overrides, _foreground: bool) -> Result<()>
                 }
                 ControlMessage::Shutdown { respond } => {
                     respond_if_needed(respond, Ok(()));
-                    let _ = fs::remove_file(&socket_path);
+                    if owns_socket {
+                        let _ = fs::remove_file(&socket_path);
+                    }
                     return Ok(());
                 }
             }
@@ -211,11 +295,24 @@ fn run_daemon_foreground(overrides: &Overrides, _foreground: bool) -> Result<()>
             let reload_result = match DaemonContext::load(overrides) {
                 Ok(new_ctx) => {
                     ctx = new_ctx;
-                    match setup_watcher(&ctx.paths, &ctx.watch_roots) {
+                    rate_limit = ctx.rate_limit();
+                    last_battery_check = None;
+                    last_inhibit_check = None;
+                    auto_snapshot =
+                        AutoSnapshot::from_config(&ctx.config.snapshot, Instant::now())?;
+                    update_push_remote(&shared, ctx.config.snapshot.auto_push_remote.clone());
+                    push_backoff.reset();
+                    last_push_check = None;
+                    glob_targets =
+                        glob_watch_targets(ctx.paths.home_dir(), &ctx.glob_roots, &ctx.watch_roots);
+                    match setup_watcher(&ctx, &glob_targets) {
                         Ok((new_watcher, new_rx)) => {
-                            _watcher = new_watcher;
+                            watcher = new_watcher;
                             rx = new_rx;
-                            update_watch_roots(&shared, ctx.watch_roots.clone());
+                            update_watch_roots(
+                                &shared,
+                                status_watch_roots(&ctx, &glob_targets, &watcher),
+                            );
                             Ok(())
                         }
                         Err(err) => Err(err),
@@ -232,10 +329,43 @@ fn run_daemon_foreground(overrides: &Overrides, _foreground: bool) -> Result<()>
 
         match rx.recv_timeout(Duration::from_millis(100)) {
             Ok(Ok(event)) => {
+                count_event(&shared);
                 if should_handle_event(&event.kind) {
+                    if touches_recipients(&ctx, &event.paths) {
+                        if let Err(err) = refresh_recipients(&mut ctx, &mut secrets_debouncer) {
+                            record_error(&shared, &ctx.paths, &err);
+                        }
+                    }
+                    if let Some((from, to)) = rename_pair(&event) {
+                        let home = ctx.paths.home_dir();
+                        if let (Some(from), Some(to)) =
+                            (normalize_rel_path(home, from), normalize_rel_path(home, to))
+                        {
+                            queue_rename(&mut renames, from, to);
+                        }
+                    }
+                    let mut event_paths = event.paths;
+                    if !ctx.glob_roots.is_empty() && changes_directories(&event.kind) {
+                        let added = refresh_glob_watches(
+                            ctx.paths.home_dir(),
+                            &ctx.glob_roots,
+                            &ctx.watch_roots,
+                            &mut watcher,
+                            &mut glob_targets,
+                        );
+                        if !added.is_empty() {
+                            update_watch_roots(
+                                &shared,
+                                status_watch_roots(&ctx, &glob_targets, &watcher),
+                            );
+                            // Files may have been created before the new watch was in place.
+                            event_paths.extend(scan_watch_targets(&ctx.paths, &added));
+                        }
+                    }
                     handle_event(
                         &ctx,
-                        &event.paths,
+                        &shared,
+                        &event_paths,
                         &mut debouncer,
                         &mut secrets_debouncer,
                         &mut auto_add_queue,
@@ -255,10 +385,18 @@ fn run_daemon_foreground(overrides: &Overrides, _foreground: bool) -> Result<()>
             debouncer.len(),
             secrets_debouncer.len(),
             auto_add_queue.len(),
+            renames.len(),
         );
 
+        if let Some(path) = &ctx.config.watch.metrics_textfile {
+            if last_metrics_write.is_none_or(|t| t.elapsed() >= METRICS_WRITE_INTERVAL) {
+                write_metrics(&shared, path);
+                last_metrics_write = Some(Instant::now());
+            }
+        }
+
         let now = Instant::now();
-        if now.duration_since(last_inhibit_check) >= Duration::from_secs(1) {
+        if last_inhibit_check.is_none_or(|t| now.duration_since(t) >= Duration::from_secs(1)) {
             if let Ok(marker) = active_inhibit(&ctx.paths) {
                 if let Some(marker) = marker {
                     update_inhibit(&shared, true, Some(marker.reason.clone()));
@@ -266,7 +404,119 @@ fn run_daemon_foreground(overrides: &Overrides, _foreground: bool) -> Result<()>
                     update_inhibit(&shared, false, None);
                 }
             }
-            last_inhibit_check = now;
+            if ctx.config.watch.pause_on_battery {
+                if last_battery_check
+                    .is_none_or(|t| now.duration_since(t) >= BATTERY_CHECK_INTERVAL)
+                {
+                    on_battery = throttle::on_battery();
+                    last_battery_check = Some(now);
+                }
+            } else {
+                on_battery = false;
+            }
+            held = if let Some(until) =
+                throttle::quiet_until(&ctx.quiet_hours, hometree_core::snapshot::local_now())
+            {
+                Some((ThrottleReason::QuietHours, Some(SystemTime::from(until))))
+            } else if on_battery {
+                Some((ThrottleReason::OnBattery, None))
+            } else {
+                None
+            };
+            if rate_limit
+                .as_mut()
+                .is_none_or(|limit| limit.blocked_until(now).is_none())
+            {
+                rate_held = None;
+            }
+            update_throttle(&shared, held.or(rate_held));
+            last_inhibit_check = Some(now);
+        }
+
+        if let Some(auto) = auto_snapshot.as_mut() {
+            if auto.is_due(now, hometree_core::snapshot::local_now()) {
+                let inhibited = pause_until.is_some()
+                    || matches!(active_inhibit(&ctx.paths), Ok(Some(_)) | Err(_));
+                if !debouncer.is_empty() || !secrets_debouncer.is_empty() {
+                    // Stage pending edits first so they land in this snapshot.
+                    force_flush = true;
+                } else if !inhibited {
+                    match run_auto_snapshot(&ctx) {
+                        Ok(SnapshotOutcome::Committed(message)) => {
+                            info!(message = %message, "auto snapshot committed");
+                            update_snapshot_time(&shared);
+                            publish(&shared, DaemonEvent::Snapshot { message });
+                            auto.snapshotted(now);
+                            if ctx.config.snapshot.auto_push_remote.is_some() {
+                                push_pending = true;
+                                push_backoff.reset();
+                            }
+                        }
+                        Ok(SnapshotOutcome::NothingStaged) => auto.snapshotted(now),
+                        Ok(SnapshotOutcome::LockBusy) => {}
+                        Err(err) => {
+                            record_error(&shared, &ctx.paths, &err);
+                            auto.snapshotted(now);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(remote) = ctx.config.snapshot.auto_push_remote.clone() {
+            // Also picks up commits made by `hometree snapshot` while offline.
+            if !push_pending
+                && last_push_check.is_none_or(|t| now.duration_since(t) >= PUSH_CHECK_INTERVAL)
+            {
+                last_push_check = Some(now);
+                match unpushed_count(&ctx, &remote) {
+                    Ok(count) => {
+                        push_pending = count > 0;
+                        update_push_result(&shared, Some(count), None);
+                    }
+                    Err(err) => warn!("failed to count unpushed commits: {err:#}"),
+                }
+            }
+            if let Some(job) = &push_job {
+                let result = match job.try_recv() {
+                    Ok(result) => Some(result),
+                    Err(mpsc::TryRecvError::Empty) => None,
+                    Err(mpsc::TryRecvError::Disconnected) => Some(Err(PushFailure {
+                        error: "push worker exited without a result".to_string(),
+                        unpushed: None,
+                    })),
+                };
+                if let Some(result) = result {
+                    push_job = None;
+                    match result {
+                        Ok(()) => {
+                            info!(remote = %remote, "pushed snapshots");
+                            publish(
+                                &shared,
+                                DaemonEvent::Pushed {
+                                    remote: remote.clone(),
+                                },
+                            );
+                            push_pending = false;
+                            push_backoff.reset();
+                            last_push_check = Some(now);
+                            update_push_result(&shared, Some(0), None);
+                        }
+                        Err(failure) => {
+                            warn!("{}", failure.error);
+                            push_backoff.fail(now);
+                            update_push_result(&shared, failure.unpushed, Some(failure.error));
+                        }
+                    }
+                }
+            }
+            if push_job.is_none()
+                && push_pending
+                && push_backoff.ready(now)
+                && pause_until.is_none()
+            {
+                push_job = Some(spawn_push(&ctx, remote));
+            }
         }
 
         let flush_due = force_flush
@@ -280,7 +530,6 @@ fn run_daemon_foreground(overrides: &Overrides, _foreground: bool) -> Result<()>
             continue;
         }
 
-        force_flush = false;
         let inhibit_marker = active_inhibit(&ctx.paths)?;
         if pause_until.is_some() || inhibit_marker.is_some() {
             if let Some(marker) = inhibit_marker {
@@ -291,25 +540,69 @@ fn run_daemon_foreground(overrides: &Overrides, _foreground: bool) -> Result<()>
             debouncer.clear();
             secrets_debouncer.clear();
             auto_add_queue.clear();
+            renames.clear();
+            force_flush = false;
             continue;
         }
 
+        // Throttling only holds changes back; unlike a pause it never drops them.
+        if held.is_some() {
+            continue;
+        }
+        if let Some(limit) = rate_limit.as_mut() {
+            if let Some(until) = limit.blocked_until(now) {
+                rate_held = Some((
+                    ThrottleReason::RateLimited,
+                    Some(SystemTime::now() + (until - now)),
+                ));
+                update_throttle(&shared, rate_held);
+                continue;
+            }
+            if rate_held.take().is_some() {
+                update_throttle(&shared, None);
+            }
+            limit.record(now);
+        }
+        let drain_all = std::mem::take(&mut force_flush);
+        let flush_started = Instant::now();
         let flush_result = flush_changes(
             &ctx,
             &mut debouncer,
             &mut secrets_debouncer,
             &mut auto_add_queue,
+            &mut renames,
+            drain_all,
         );
         match flush_result {
-            Ok(()) => {
+            Ok(staged) => {
                 backoff.reset();
-                update_flush_time(&shared);
+                update_flush_time(&shared, staged.len(), flush_started.elapsed());
+                if !staged.is_empty() {
+                    if let Some(auto) = auto_snapshot.as_mut() {
+                        auto.staged(now);
+                    }
+                    publish(
+                        &shared,
+                        DaemonEvent::Staged {
+                            paths: staged.iter().map(|p| p.display().to_string()).collect(),
+                        },
+                    );
+                }
+                publish(
+                    &shared,
+                    DaemonEvent::Flushed {
+                        staged: staged.len(),
+                    },
+                );
             }
             Err(err) => {
                 record_error(&shared, &ctx.paths, &err);
                 backoff.fail(now);
+                update_flush_failure(&shared, flush_started.elapsed(), backoff.current);
             }
         }
+        // Publish flush outcomes right away instead of on the next tick.
+        last_metrics_write = None;
     }
 
     Ok(())
@@ -317,6 +610,7 @@ fn run_daemon_foreground(overrides: &Overrides, _foreground: bool) -> Result<()>
 
 fn handle_event(
     ctx: &DaemonContext,
+    shared: &Arc<Mutex<DaemonShared>>,
     paths: &[PathBuf],
     debouncer: &mut Debounce<PathBuf>,
     secrets_debouncer: &mut Debounce<PathBuf>,
@@ -335,12 +629,15 @@ fn handle_event(
             ctx.auto_add_enabled,
             std::iter::once(rel),
         );
-        log_auto_add_skips(&decisions, ctx.auto_add_enabled);
+        log_auto_add_skips(shared, &decisions, ctx.auto_add_enabled);
+        let now = Instant::now();
         for rel_path in decisions.managed_stage {
-            debouncer.push(rel_path, Instant::now());
+            let window = ctx.debounce_for(&rel_path);
+            debouncer.push_for(rel_path, now, window);
         }
         for rel_path in decisions.secret_plaintext {
-            secrets_debouncer.push(rel_path, Instant::now());
+            let window = ctx.debounce_for(&rel_path);
+            secrets_debouncer.push_for(rel_path, now, window);
         }
         if !decisions.auto_add.is_empty() {
             for rel_path in decisions.auto_add {
@@ -351,7 +648,55 @@ fn handle_event(
     }
 }
 
-fn log_auto_add_skips(decisions: &WatchDecisions, auto_add_enabled: bool) {
+fn touches_recipients(ctx: &DaemonContext, paths: &[PathBuf]) -> bool {
+    if ctx.secrets_backend.is_none() {
+        return false;
+    }
+    let work_tree = &ctx.config.repo.work_tree;
+    let secrets = &ctx.config.secrets;
+    let dir = secrets
+        .recipients_dir
+        .as_ref()
+        .map(|dir| work_tree.join(dir));
+    paths.iter().any(|abs| {
+        dir.as_ref().is_some_and(|dir| abs.starts_with(dir))
+            || secrets.recipients_files.iter().any(|file| {
+                if file.is_absolute() {
+                    abs == file
+                } else {
+                    *abs == work_tree.join(file)
+                }
+            })
+    })
+}
+
+/// Reload recipients after a recipients file changed and queue every secret
+/// whose sidecar was encrypted to a different recipient set.
+fn refresh_recipients(
+    ctx: &mut DaemonContext,
+    secrets_debouncer: &mut Debounce<PathBuf>,
+) -> Result<()> {
+    let backend = AgeBackend::from_config(&ctx.config.secrets, &ctx.config.repo.work_tree)
+        .context("reload secret recipients")?;
+    let recipients = backend.recipient_keys();
+    let state = read_secret_state(&ctx.paths).context("read secret state")?;
+    let now = Instant::now();
+    for rule in ctx.secrets.rules() {
+        let plaintext_rel = ctx.secrets.plaintext_path(rule);
+        if state.needs_rekey(&plaintext_rel, &recipients) {
+            info!(path = %plaintext_rel.display(), "recipients changed; re-encrypting");
+            secrets_debouncer.push(plaintext_rel, now);
+        }
+    }
+    ctx.secrets_backend = Some(backend);
+    Ok(())
+}
+
+fn log_auto_add_skips(
+    shared: &Arc<Mutex<DaemonShared>>,
+    decisions: &WatchDecisions,
+    auto_add_enabled: bool,
+) {
     if !auto_add_enabled {
         return;
     }
@@ -359,11 +704,21 @@ fn log_auto_add_skips(decisions: &WatchDecisions, auto_add_enabled: bool) {
         if meta.auto_add {
             continue;
         }
-        if !meta.is_allowed {
-            debug!(path = %meta.path.display(), "skipped auto-add: path is ignored or denylisted");
+        let reason = if !meta.is_allowed {
+            "path is ignored or denylisted"
         } else if !meta.matches_allowlist {
-            debug!(path = %meta.path.display(), "skipped auto-add: path does not match allowlist");
-        }
+            "path does not match allowlist"
+        } else {
+            continue;
+        };
+        debug!(path = %meta.path.display(), "skipped auto-add: {reason}");
+        publish(
+            shared,
+            DaemonEvent::AutoAddSkipped {
+                path: meta.path.display().to_string(),
+                reason: reason.to_string(),
+            },
+        );
     }
 }
 
@@ -372,15 +727,25 @@ fn flush_changes(
     debouncer: &mut Debounce<PathBuf>,
     secrets_debouncer: &mut Debounce<PathBuf>,
     auto_add_queue: &mut BTreeSet<PathBuf>,
-) -> Result<()> {
+    renames: &mut Vec<(PathBuf, PathBuf)>,
+    drain_all: bool,
+) -> Result<Vec<PathBuf>> {
     let now = Instant::now();
-    let managed_paths = debouncer.drain();
-    let secret_paths = secrets_debouncer.drain();
+    let (managed_paths, secret_paths) = if drain_all {
+        (debouncer.drain(), secrets_debouncer.drain())
+    } else {
+        (debouncer.drain_due(now), secrets_debouncer.drain_due(now))
+    };
     let auto_add_paths: Vec<PathBuf> = auto_add_queue.iter().cloned().collect();
     auto_add_queue.clear();
-
-    if managed_paths.is_empty() && secret_paths.is_empty() && auto_add_paths.is_empty() {
-        return Ok(());
+    let rename_pairs = std::mem::take(renames);
+
+    if managed_paths.is_empty()
+        && secret_paths.is_empty()
+        && auto_add_paths.is_empty()
+        && rename_pairs.is_empty()
+    {
+        return Ok(Vec::new());
     }
 
     let result = (|| {
@@ -393,9 +758,19 @@ fn flush_changes(
         let git_dir = &ctx.config.repo.git_dir;
         let work_tree = &ctx.config.repo.work_tree;
 
-        if !auto_add_paths.is_empty() {
-            git.add(git_dir, work_tree, &auto_add_paths, AddMode::Paths)
+        // Both sides of a move go into one `git add` so the index never holds half of it.
+        let moved = rename_paths(ctx, &git, &rename_pairs)?;
+        if !moved.is_empty() {
+            git.add(git_dir, work_tree, &moved, AddMode::Paths)
+                .context("stage renames")?;
+        }
+        let mut staged = moved;
+
+        let auto_add = stageable_paths(ctx, &git, &auto_add_paths)?;
+        if !auto_add.is_empty() {
+            git.add(git_dir, work_tree, &auto_add, AddMode::Paths)
                 .context("auto-add")?;
+            staged.extend(auto_add);
         }
 
         if !secret_paths.is_empty() {
@@ -404,9 +779,10 @@ fn flush_changes(
                 .as_ref()
                 .ok_or_else(|| anyhow!("secrets enabled but backend unavailable"))?;
             let mut ciphertext_paths = Vec::new();
+            let mut state = read_secret_state(&ctx.paths).context("read secret state")?;
             for rel in &secret_paths {
                 let plaintext_abs = ctx.paths.home_dir().join(rel);
-                let plaintext = match std::fs::read(&plaintext_abs) {
+                let plaintext = match Plaintext::read_file(&plaintext_abs) {
                     Ok(contents) => contents,
                     Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
                     Err(err) => {
@@ -414,7 +790,7 @@ fn flush_changes(
                         continue;
                     }
                 };
-                let ciphertext = backend.encrypt(&plaintext)?;
+                let ciphertext = backend.encrypt(plaintext.as_bytes())?;
                 let rule = match ctx
                     .config
                     .secrets
@@ -440,27 +816,37 @@ fn flush_changes(
                 if let Some(parent) = ciphertext_abs.parent() {
                     std::fs::create_dir_all(parent)?;
                 }
-                std::fs::write(&ciphertext_abs, ciphertext)?;
+                std::fs::write(&ciphertext_abs, &ciphertext)?;
+                state.record_encryption(
+                    rel,
+                    &ciphertext_rel,
+                    &ciphertext,
+                    &backend.recipient_keys(),
+                )?;
                 ciphertext_paths.push(ciphertext_rel);
             }
             if !ciphertext_paths.is_empty() {
+                write_secret_state(&ctx.paths, &state).context("write secret state")?;
                 git.add(git_dir, work_tree, &ciphertext_paths, AddMode::Paths)
                     .context("stage secret sidecars")?;
+                staged.extend(ciphertext_paths);
             }
         }
 
-        if !managed_paths.is_empty() {
+        let managed = stageable_paths(ctx, &git, &managed_paths)?;
+        if !managed.is_empty() {
             let mode = if ctx.config.watch.auto_stage_tracked_only {
                 AddMode::TrackedOnly
             } else {
                 AddMode::Paths
             };
-            git.add(git_dir, work_tree, &managed_paths, mode)
+            git.add(git_dir, work_tree, &managed, mode)
                 .context("stage managed paths")?;
+            staged.extend(managed);
         }
 
         drop(lock_file);
-        Ok(())
+        Ok(staged)
     })();
 
     if result.is_err() {
@@ -473,10 +859,111 @@ fn flush_changes(
             &auto_add_paths,
             now,
         );
+        renames.extend(rename_pairs);
     }
     result
 }
 
+/// Paths on either side of the queued moves. The destination is staged only when
+/// it is managed and the source is gone: backup-then-write saves move the original
+/// aside and recreate it, which is an edit rather than a move.
+fn rename_paths(
+    ctx: &DaemonContext,
+    git: &GitCliBackend,
+    renames: &[(PathBuf, PathBuf)],
+) -> Result<Vec<PathBuf>> {
+    let home = ctx.paths.home_dir();
+    let mut paths = BTreeSet::new();
+    for (from, to) in renames {
+        if home.join(from).symlink_metadata().is_ok() {
+            continue;
+        }
+        let tracked = git.tracked_paths(
+            &ctx.config.repo.git_dir,
+            &ctx.config.repo.work_tree,
+            std::slice::from_ref(from),
+        )?;
+        for (old, new) in moved_entries(from, to, &tracked) {
+            let managed = matches!(
+                decide_watch_action(&ctx.managed, &ctx.secrets, &ctx.allowlist, false, &new),
+                WatchAction::Managed {
+                    is_allowed: true,
+                    ..
+                }
+            );
+            let is_file = home
+                .join(&new)
+                .symlink_metadata()
+                .is_ok_and(|meta| !meta.is_dir());
+            if managed && is_file {
+                paths.insert(new);
+            }
+            paths.insert(old);
+        }
+    }
+    Ok(paths.into_iter().collect())
+}
+
+/// Drop paths that neither exist nor are tracked, such as editor temp files that
+/// came and went within one debounce window; `git add` rejects them.
+fn stageable_paths(
+    ctx: &DaemonContext,
+    git: &GitCliBackend,
+    paths: &[PathBuf],
+) -> Result<Vec<PathBuf>> {
+    let home = ctx.paths.home_dir();
+    let (mut stageable, missing): (Vec<PathBuf>, Vec<PathBuf>) = paths
+        .iter()
+        .cloned()
+        .partition(|path| home.join(path).symlink_metadata().is_ok());
+    let tracked = git.tracked_paths(
+        &ctx.config.repo.git_dir,
+        &ctx.config.repo.work_tree,
+        &missing,
+    )?;
+    stageable.extend(missing.into_iter().filter(|path| {
+        tracked
+            .iter()
+            .any(|entry| Path::new(entry).starts_with(path))
+    }));
+    Ok(stageable)
+}
+
+enum SnapshotOutcome {
+    Committed(String),
+    NothingStaged,
+    LockBusy,
+}
+
+/// Commit whatever is staged, applying the same secret guard as `hometree snapshot`.
+fn run_auto_snapshot(ctx: &DaemonContext) -> Result<SnapshotOutcome> {
+    let Some(lock_file) = try_acquire_lock(&ctx.paths)? else {
+        return Ok(SnapshotOutcome::LockBusy);
+    };
+    let git = GitCliBackend::new();
+    let git_dir = &ctx.config.repo.git_dir;
+    let work_tree = &ctx.config.repo.work_tree;
+    let staged = git
+        .staged_paths(git_dir, work_tree)
+        .context("list staged paths")?;
+    if staged.is_empty() {
+        return Ok(SnapshotOutcome::NothingStaged);
+    }
+    crate::guard_snapshot_secrets(&ctx.config, &git)?;
+    crate::stage_metadata_manifest(&ctx.paths, &ctx.config, &git, &Effects::default())?;
+    let template = ctx
+        .config
+        .snapshot
+        .auto_message_template
+        .as_deref()
+        .unwrap_or(DEFAULT_AUTO_TEMPLATE);
+    let message = crate::auto_snapshot_message(template, &staged);
+    git.commit(git_dir, work_tree, &message)
+        .context("git commit")?;
+    drop(lock_file);
+    Ok(SnapshotOutcome::Committed(message))
+}
+
 fn requeue(
     debouncer: &mut Debounce<PathBuf>,
     secrets_debouncer: &mut Debounce<PathBuf>,
@@ -505,7 +992,7 @@ fn normalize_rel_path(home_dir: &Path, abs: &Path) -> Option<PathBuf> {
     Some(rel)
 }
 
-fn try_acquire_lock(paths: &Paths) -> Result<Option<std::fs::File>> {
+pub(crate) fn try_acquire_lock(paths: &Paths) -> Result<Option<std::fs::File>> {
     fs::create_dir_all(paths.state_dir())?;
     let path = lock_path(paths);
     let file = OpenOptions::new().create(true).append(true).open(path)?;
@@ -526,40 +1013,92 @@ fn ensure_runtime_dir(paths: &Paths) -> Result<PathBuf> {
     Ok(runtime)
 }
 
-fn socket_in_use(socket_path: &Path) -> Result<bool> {
+/// What is behind the daemon socket path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SocketState {
+    Absent,
+    /// The socket file is left over and nothing accepts connections.
+    Stale,
+    /// A process accepts connections but does not answer in time.
+    NotResponding,
+    Responding,
+}
+
+pub(crate) fn socket_state(socket_path: &Path) -> SocketState {
     if !socket_path.exists() {
-        return Ok(false);
+        return SocketState::Absent;
     }
-    match UnixStream::connect(socket_path) {
-        Ok(mut stream) => {
-            let req = IpcRequest::ping();
-            if write_request(&mut stream, &req).is_err() {
-                return Ok(false);
-            }
-            Ok(true)
+    // `connect` itself blocks once a wedged daemon's accept backlog is full,
+    // so the probe runs on its own thread against a deadline.
+    let (tx, rx) = mpsc::channel();
+    let path = socket_path.to_path_buf();
+    std::thread::spawn(move || {
+        let _ =
+            tx.send(Client::connect(&path).and_then(|mut client| client.command(&Request::Ping)));
+    });
+    match rx.recv_timeout(ipc::CLIENT_TIMEOUT * 3) {
+        Ok(Ok(())) => SocketState::Responding,
+        Ok(Err(HometreeError::Ipc(msg))) if msg == ipc::NOT_RESPONDING => {
+            SocketState::NotResponding
         }
-        Err(_) => Ok(false),
+        Ok(Err(HometreeError::Io(_))) => SocketState::Stale,
+        // Anything that answers, even with an error, is a live daemon.
+        Ok(Err(_)) => SocketState::Responding,
+        Err(_) => SocketState::NotResponding,
     }
 }
 
-fn start_ipc_server(
-    socket_path: &Path,
-    control_tx: mpsc::Sender<ControlMessage>,
-    shared: Arc<Mutex<DaemonShared>>,
-) -> Result<()> {
+fn bind_socket(socket_path: &Path) -> Result<UnixListener> {
+    match socket_state(socket_path) {
+        SocketState::Responding => return Err(anyhow!("daemon already running")),
+        SocketState::NotResponding => {
+            return Err(anyhow!(
+                "a daemon is listening on {} but not responding",
+                socket_path.display()
+            ))
+        }
+        SocketState::Absent | SocketState::Stale => {}
+    }
+    if socket_path.exists() {
+        let _ = fs::remove_file(socket_path);
+    }
     if let Some(parent) = socket_path.parent() {
         fs::create_dir_all(parent)?;
     }
     let listener = UnixListener::bind(socket_path).context("bind daemon socket")?;
     fs::set_permissions(socket_path, fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
 
+fn start_ipc_server(
+    listener: UnixListener,
+    control_tx: mpsc::Sender<ControlMessage>,
+    shared: Arc<Mutex<DaemonShared>>,
+) {
+    let active = Arc::new(AtomicUsize::new(0));
     std::thread::spawn(move || {
         for stream in listener.incoming() {
             match stream {
-                Ok(stream) => {
-                    if let Err(err) = handle_ipc_connection(stream, &control_tx, &shared) {
-                        error!("ipc error: {err}");
-                    }
+                Ok(mut stream) => {
+                    let Some(slot) = ConnectionSlot::acquire(&active) else {
+                        warn!("ipc: too many connections; refusing one");
+                        let _ = ipc::write_message(
+                            &mut stream,
+                            &Response::<serde_json::Value>::err("too many connections"),
+                        );
+                        continue;
+                    };
+                    let control_tx = control_tx.clone();
+                    let shared = shared.clone();
+                    // One thread per connection, at most MAX_IPC_CONNECTIONS:
+                    // clients may keep a connection open for several requests
+                    // or an event subscription.
+                    std::thread::spawn(move || {
+                        let _slot = slot;
+                        if let Err(err) = handle_ipc_connection(stream, &control_tx, &shared) {
+                            error!("ipc error: {err}");
+                        }
+                    });
                 }
                 Err(err) => {
                     error!("ipc accept error: {err}");
@@ -567,8 +1106,26 @@ fn start_ipc_server(
             }
         }
     });
+}
 
-    Ok(())
+/// A claim on one of the `MAX_IPC_CONNECTIONS` handler threads, released on drop.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn acquire(active: &Arc<AtomicUsize>) -> Option<Self> {
+        active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < MAX_IPC_CONNECTIONS).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Self(active.clone()))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
 }
 
 fn handle_ipc_connection(
@@ -577,28 +1134,64 @@ fn handle_ipc_connection(
     shared: &Arc<Mutex<DaemonShared>>,
 ) -> Result<()> {
     let mut reader = BufReader::new(stream.try_clone()?);
-    let mut line = String::new();
-    let bytes = reader.read_line(&mut line)?;
-    if bytes == 0 {
-        return Ok(());
-    }
-    if line.len() > IPC_MAX_LINE {
-        return Err(anyhow!("request too large"));
+    let mut writer = BufWriter::new(stream.try_clone()?);
+    let mut version = None;
+    // Frees the connection slot when a client goes quiet between requests;
+    // `subscribe` sets its own timeout.
+    stream.set_read_timeout(Some(IPC_IDLE_TIMEOUT))?;
+    loop {
+        let line = match ipc::read_line(&mut reader) {
+            Ok(Some(line)) => line,
+            Ok(None) => return Ok(()),
+            Err(HometreeError::Io(err))
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(())
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let response = match Request::parse(line.trim_end()) {
+            Ok(Request::Hello { version: offered }) if version.is_none() => {
+                match ipc::negotiate(offered) {
+                    Ok(hello) => {
+                        version = Some(hello.version);
+                        Response::ok(serde_json::to_value(hello)?)
+                    }
+                    Err(err) => Response::err(err),
+                }
+            }
+            Ok(Request::Hello { .. }) => Response::err("handshake already done"),
+            Ok(Request::Subscribe) => return subscribe(stream, writer, shared),
+            Ok(request) => handle_ipc_request(request, control_tx, shared)?,
+            Err(err) => Response::err(err),
+        };
+        ipc::write_message(&mut writer, &response)?;
+        // Connections without a handshake speak v1: one request each.
+        if version.is_none() {
+            return Ok(());
+        }
     }
-    let req: IpcRequest = serde_json::from_str(line.trim_end())?;
-    let response: IpcResponse<serde_json::Value> = match req.cmd.as_str() {
-        "ping" => IpcResponse::ok(serde_json::Value::Null),
-        "status" => {
+}
+
+fn handle_ipc_request(
+    request: Request,
+    control_tx: &mpsc::Sender<ControlMessage>,
+    shared: &Arc<Mutex<DaemonShared>>,
+) -> Result<Response> {
+    Ok(match request {
+        Request::Ping => Response::ok(serde_json::Value::Null),
+        Request::Status => {
             let status = shared.lock().map(|s| s.status()).unwrap_or_default();
-            let value = serde_json::to_value(status)?;
-            IpcResponse::ok(value)
+            Response::ok(serde_json::to_value(status)?)
         }
-        "pause" => {
-            let ttl = req
-                .ttl_ms
+        Request::Pause { ttl_ms, reason } => {
+            let ttl = ttl_ms
                 .map(Duration::from_millis)
                 .unwrap_or(INHIBIT_TTL_DEFAULT);
-            let reason = req.reason.unwrap_or_else(|| "manual".to_string());
+            let reason = reason.unwrap_or_else(|| "manual".to_string());
             let (tx, rx) = mpsc::channel();
             let msg = ControlMessage::Pause {
                 ttl,
@@ -607,41 +1200,95 @@ fn handle_ipc_connection(
             };
             let send_res = control_tx.send(msg).map_err(|e| anyhow!(e.to_string()));
             match send_res.and_then(|_| rx.recv().unwrap_or_else(|e| Err(anyhow!(e)))) {
-                Ok(_) => IpcResponse::ok(serde_json::Value::Null),
-                Err(err) => IpcResponse::err(err.to_string()),
+                Ok(_) => Response::ok(serde_json::Value::Null),
+                Err(err) => Response::err(err.to_string()),
             }
         }
-        "resume" => ipc_control_simple(
+        Request::Resume => ipc_control_simple(
             control_tx,
             ControlMessage::Resume { respond: None },
             "resume",
         ),
-        "flush" => ipc_control_simple(control_tx, ControlMessage::Flush { respond: None }, "flush"),
-        "reload" => ipc_control_simple(
+        Request::Flush => {
+            ipc_control_simple(control_tx, ControlMessage::Flush { respond: None }, "flush")
+        }
+        Request::Reload => ipc_control_simple(
             control_tx,
             ControlMessage::Reload { respond: None },
             "reload",
         ),
-        "shutdown" => ipc_control_simple(
+        Request::Shutdown => ipc_control_simple(
             control_tx,
             ControlMessage::Shutdown { respond: None },
             "shutdown",
         ),
-        other => IpcResponse::err(format!("unknown command: {other}")),
+        Request::Hello { .. } | Request::Subscribe => {
+            Response::err("request not valid here".to_string())
+        }
+    })
+}
+
+/// Acknowledge the request, then stream events until the client goes away.
+fn subscribe(
+    stream: UnixStream,
+    mut writer: BufWriter<UnixStream>,
+    shared: &Arc<Mutex<DaemonShared>>,
+) -> Result<()> {
+    let (tx, rx) = mpsc::sync_channel(SUBSCRIBER_BUFFER);
+    let registered = match shared.lock() {
+        Ok(mut shared) if shared.subscribers.len() < MAX_SUBSCRIBERS => {
+            shared.subscribers.push(tx);
+            true
+        }
+        _ => false,
     };
-    let mut writer = BufWriter::new(stream);
-    let json = serde_json::to_string(&response)?;
-    writer.write_all(json.as_bytes())?;
-    writer.write_all(b"\n")?;
-    writer.flush()?;
+    let response: Response = if registered {
+        Response::ok(serde_json::Value::Null)
+    } else {
+        Response::err("too many subscribers")
+    };
+    ipc::write_message(&mut writer, &response)?;
+    if !registered {
+        return Ok(());
+    }
+    // Reads only detect the client hanging up; the timeout keeps them short.
+    stream.set_read_timeout(Some(Duration::from_millis(10)))?;
+    stream_events(stream, writer, rx);
     Ok(())
 }
 
+fn stream_events(
+    mut probe: UnixStream,
+    mut writer: BufWriter<UnixStream>,
+    rx: mpsc::Receiver<String>,
+) {
+    loop {
+        match rx.recv_timeout(SUBSCRIBER_PROBE_INTERVAL) {
+            Ok(line) => {
+                let written = writer
+                    .write_all(line.as_bytes())
+                    .and_then(|()| writer.write_all(b"\n"))
+                    .and_then(|()| writer.flush());
+                if written.is_err() {
+                    break;
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let mut buf = [0u8; 64];
+                if matches!(std::io::Read::read(&mut probe, &mut buf), Ok(0)) {
+                    break;
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
 fn ipc_control_simple(
     control_tx: &mpsc::Sender<ControlMessage>,
     msg: ControlMessage,
     name: &str,
-) -> IpcResponse<serde_json::Value> {
+) -> Response {
     let (tx, rx) = mpsc::channel();
     let msg = match msg {
         ControlMessage::Resume { .. } => ControlMessage::Resume { respond: Some(tx) },
@@ -651,11 +1298,11 @@ fn ipc_control_simple(
         other => other,
     };
     if let Err(err) = control_tx.send(msg) {
-        return IpcResponse::err(format!("{name} send failed: {err}"));
+        return Response::err(format!("{name} send failed: {err}"));
     }
     match rx.recv().unwrap_or_else(|e| Err(anyhow!(e))) {
-        Ok(_) => IpcResponse::ok(serde_json::Value::Null),
-        Err(err) => IpcResponse::err(err.to_string()),
+        Ok(_) => Response::ok(serde_json::Value::Null),
+        Err(err) => Response::err(err.to_string()),
     }
 }
 
@@ -670,47 +1317,281 @@ fn install_sighup_handler(control_tx: mpsc::Sender<ControlMessage>) -> Result<()
 }
 
 fn setup_watcher(
-    paths: &Paths,
-    watch_roots: &[PathBuf],
-) -> Result<(
-    notify::RecommendedWatcher,
-    mpsc::Receiver<notify::Result<notify::Event>>,
-)> {
-    let (tx, rx) = mpsc::channel();
-    let mut watcher = notify::recommended_watcher(tx)?;
-    for path in watch_roots {
-        let abs = paths.home_dir().join(path);
-        watcher.watch(&abs, RecursiveMode::Recursive)?;
+    ctx: &DaemonContext,
+    glob_targets: &BTreeSet<WatchTarget>,
+) -> Result<(Watchers, mpsc::Receiver<EventResult>)> {
+    let home = ctx.paths.home_dir();
+    let (mut watcher, rx) = Watchers::new(ctx.poll_interval);
+    for path in &ctx.watch_roots {
+        watcher.watch(&home.join(path), RecursiveMode::Recursive)?;
+    }
+    for target in glob_targets {
+        watcher.watch(&home.join(&target.path), recursive_mode(target))?;
     }
     Ok((watcher, rx))
 }
 
+fn recursive_mode(target: &WatchTarget) -> RecursiveMode {
+    if target.recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    }
+}
+
+fn changes_directories(kind: &notify::EventKind) -> bool {
+    use notify::event::ModifyKind;
+    use notify::EventKind;
+    matches!(
+        kind,
+        EventKind::Create(_)
+            | EventKind::Remove(_)
+            | EventKind::Modify(ModifyKind::Name(_))
+            | EventKind::Any
+    )
+}
+
+/// Recompute glob watch targets and apply the difference to `watcher`.
+/// Returns the targets that were newly added.
+fn refresh_glob_watches(
+    home: &Path,
+    glob_roots: &[String],
+    watch_roots: &[PathBuf],
+    watcher: &mut Watchers,
+    current: &mut BTreeSet<WatchTarget>,
+) -> Vec<WatchTarget> {
+    let next = glob_watch_targets(home, glob_roots, watch_roots);
+    for stale in current.difference(&next) {
+        // Watches are keyed by path; a root's own watch must survive.
+        if watch_roots.contains(&stale.path) {
+            continue;
+        }
+        watcher.unwatch(&home.join(&stale.path));
+    }
+    let mut added = Vec::new();
+    for target in next.difference(current) {
+        match watcher.watch(&home.join(&target.path), recursive_mode(target)) {
+            Ok(strategy) => {
+                debug!(path = %target.path.display(), %strategy, "watching glob directory");
+                added.push(target.clone());
+            }
+            Err(err) => warn!(path = %target.path.display(), "failed to watch: {err}"),
+        }
+    }
+    *current = next;
+    added
+}
+
+fn scan_watch_targets(paths: &Paths, targets: &[WatchTarget]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for target in targets {
+        let dir = paths.home_dir().join(&target.path);
+        let depth = if target.recursive { usize::MAX } else { 1 };
+        for entry in walkdir::WalkDir::new(&dir)
+            .max_depth(depth)
+            .follow_links(false)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            if entry.file_type().is_file() {
+                files.push(entry.into_path());
+            }
+        }
+    }
+    files
+}
+
+fn status_watch_roots(
+    ctx: &DaemonContext,
+    glob_targets: &BTreeSet<WatchTarget>,
+    watcher: &Watchers,
+) -> Vec<(PathBuf, WatchStrategy)> {
+    let home = ctx.paths.home_dir();
+    ctx.watch_roots
+        .iter()
+        .chain(glob_targets.iter().map(|target| &target.path))
+        .filter_map(|root| {
+            let strategy = watcher.strategy(&home.join(root))?;
+            Some((root.clone(), strategy))
+        })
+        .collect()
+}
+
 fn update_queue_size(
     shared: &Arc<Mutex<DaemonShared>>,
     managed: usize,
     secrets: usize,
     auto_add: usize,
+    renames: usize,
 ) {
     if let Ok(mut shared) = shared.lock() {
-        shared.queue_size = managed + secrets + auto_add;
+        shared.queue_size = managed + secrets + auto_add + renames;
+        shared.metrics.queue_managed = managed;
+        shared.metrics.queue_secrets = secrets;
+        shared.metrics.queue_auto_add = auto_add;
+        shared.metrics.queue_renames = renames;
     }
 }
 
-fn update_flush_time(shared: &Arc<Mutex<DaemonShared>>) {
+fn count_event(shared: &Arc<Mutex<DaemonShared>>) {
+    if let Ok(mut shared) = shared.lock() {
+        shared.metrics.events_received = shared.metrics.events_received.saturating_add(1);
+    }
+}
+
+fn update_flush_time(shared: &Arc<Mutex<DaemonShared>>, staged: usize, elapsed: Duration) {
     if let Ok(mut shared) = shared.lock() {
         shared.last_flush_at = Some(SystemTime::now());
         shared.total_flushes = shared.total_flushes.saturating_add(1);
+        shared.failing_since = None;
+        let metrics = &mut shared.metrics;
+        metrics.paths_staged = metrics.paths_staged.saturating_add(staged as u64);
+        metrics.consecutive_flush_failures = 0;
+        metrics.retry_backoff_ms = 0;
+        metrics.last_flush_duration_ms = Some(duration_ms(elapsed));
+    }
+}
+
+fn update_flush_failure(shared: &Arc<Mutex<DaemonShared>>, elapsed: Duration, retry: Duration) {
+    if let Ok(mut shared) = shared.lock() {
+        shared.failing_since.get_or_insert_with(SystemTime::now);
+        let metrics = &mut shared.metrics;
+        metrics.flush_failures = metrics.flush_failures.saturating_add(1);
+        metrics.consecutive_flush_failures = metrics.consecutive_flush_failures.saturating_add(1);
+        metrics.retry_backoff_ms = duration_ms(retry);
+        metrics.last_flush_duration_ms = Some(duration_ms(elapsed));
+    }
+}
+
+fn write_metrics(shared: &Arc<Mutex<DaemonShared>>, path: &Path) {
+    let Ok(status) = shared.lock().map(|s| s.status()) else {
+        return;
+    };
+    if let Err(err) = metrics::write_textfile(path, &status) {
+        warn!(path = %path.display(), "failed to write metrics: {err}");
+    }
+}
+
+fn unpushed_count(ctx: &DaemonContext, remote: &str) -> Result<usize> {
+    let git = GitCliBackend::new();
+    Ok(git.unpushed_count(&ctx.config.repo.git_dir, &ctx.config.repo.work_tree, remote)?)
+}
+
+/// Outcome of a push run by [`spawn_push`]; the count is `None` when it could
+/// not be determined after a failed push.
+type PushResult = std::result::Result<(), PushFailure>;
+
+struct PushFailure {
+    error: String,
+    unpushed: Option<usize>,
+}
+
+/// Push on a worker thread so a slow or hung remote does not stall the event
+/// loop; the caller keeps the receiver and starts at most one push at a time.
+fn spawn_push(ctx: &DaemonContext, remote: String) -> mpsc::Receiver<PushResult> {
+    let (tx, rx) = mpsc::channel();
+    let config = ctx.config.clone();
+    std::thread::spawn(move || {
+        let git = GitCliBackend::new();
+        let result = crate::push_snapshot(&config, &git, &remote).map_err(|err| {
+            match git.unpushed_count(&config.repo.git_dir, &config.repo.work_tree, &remote) {
+                Ok(count) => PushFailure {
+                    error: format!("{err:#}"),
+                    unpushed: Some(count),
+                },
+                Err(count_err) => PushFailure {
+                    error: format!("{err:#}; unpushed commit count unknown ({count_err:#})"),
+                    unpushed: None,
+                },
+            }
+        });
+        let _ = tx.send(result);
+    });
+    rx
+}
+
+fn update_push_remote(shared: &Arc<Mutex<DaemonShared>>, remote: Option<String>) {
+    if let Ok(mut shared) = shared.lock() {
+        shared.push_remote = remote;
+    }
+}
+
+/// An unknown count (`None`) keeps the last known one; the error says why.
+fn update_push_result(
+    shared: &Arc<Mutex<DaemonShared>>,
+    unpushed: Option<usize>,
+    error: Option<String>,
+) {
+    if let Ok(mut shared) = shared.lock() {
+        if let Some(unpushed) = unpushed {
+            shared.unpushed_commits = unpushed;
+        }
+        if let Some(error) = error {
+            shared.last_push_attempt_at = Some(SystemTime::now());
+            shared.last_push_error = Some(error);
+        } else if unpushed == Some(0) {
+            shared.last_push_error = None;
+        }
+    }
+}
+
+fn update_snapshot_time(shared: &Arc<Mutex<DaemonShared>>) {
+    if let Ok(mut shared) = shared.lock() {
+        shared.last_snapshot_at = Some(SystemTime::now());
+        shared.total_snapshots = shared.total_snapshots.saturating_add(1);
     }
 }
 
 fn update_inhibit(shared: &Arc<Mutex<DaemonShared>>, inhibited: bool, reason: Option<String>) {
     if let Ok(mut shared) = shared.lock() {
+        let changed = shared.inhibited != inhibited || shared.inhibit_reason != reason;
+        let now = Instant::now();
+        match (shared.inhibited_since, inhibited) {
+            (None, true) => shared.inhibited_since = Some(now),
+            (Some(since), false) => {
+                shared.inhibited_total += now.duration_since(since);
+                shared.inhibited_since = None;
+            }
+            _ => {}
+        }
         shared.inhibited = inhibited;
-        shared.inhibit_reason = reason;
+        shared.inhibit_reason = reason.clone();
+        if changed {
+            shared.publish(DaemonEvent::Inhibit { inhibited, reason });
+        }
+    }
+}
+
+fn update_throttle(
+    shared: &Arc<Mutex<DaemonShared>>,
+    throttle: Option<(ThrottleReason, Option<SystemTime>)>,
+) {
+    if let Ok(mut shared) = shared.lock() {
+        let reason = throttle.map(|(reason, _)| reason);
+        let until = throttle.and_then(|(_, until)| until);
+        if shared.throttle == reason {
+            shared.throttled_until = until;
+            return;
+        }
+        shared.throttle = reason;
+        shared.throttled_until = until;
+        if let Some(reason) = reason {
+            info!(reason = %reason, "holding changes back");
+        }
+        shared.publish(DaemonEvent::Throttle {
+            reason: reason.map(|reason| reason.to_string()),
+            until: until.map(format_time),
+        });
     }
 }
 
-fn update_watch_roots(shared: &Arc<Mutex<DaemonShared>>, roots: Vec<PathBuf>) {
+fn publish(shared: &Arc<Mutex<DaemonShared>>, event: DaemonEvent) {
+    if let Ok(mut shared) = shared.lock() {
+        shared.publish(event);
+    }
+}
+
+fn update_watch_roots(shared: &Arc<Mutex<DaemonShared>>, roots: Vec<(PathBuf, WatchStrategy)>) {
     if let Ok(mut shared) = shared.lock() {
         shared.watch_roots = roots;
     }
@@ -722,6 +1603,9 @@ fn record_error(shared: &Arc<Mutex<DaemonShared>>, paths: &Paths, err: &dyn std:
         shared.last_error = Some(err.to_string());
         shared.last_error_at = Some(now);
         let _ = write_last_error(paths, &shared.last_error, shared.last_error_at);
+        shared.publish(DaemonEvent::Error {
+            message: err.to_string(),
+        });
     }
 }
 
@@ -747,83 +1631,26 @@ fn respond_if_needed(respond: Option<mpsc::Sender<Result<()>>>, result: Result<(
     }
 }
 
-fn ipc_command(
-    paths: &Paths,
-    cmd: &str,
-    ttl: Option<Duration>,
-    reason: Option<String>,
-) -> Result<()> {
-    let req = IpcRequest {
-        cmd: cmd.to_string(),
-        ttl_ms: ttl.map(|d| d.as_millis() as u64),
-        reason,
-    };
-    let resp: IpcResponse<serde_json::Value> = ipc_request(paths, &req)?;
-    if resp.ok {
-        Ok(())
-    } else {
-        Err(anyhow!(resp
-            .error
-            .unwrap_or_else(|| "unknown error".to_string())))
-    }
+fn connect(paths: &Paths) -> Result<Client> {
+    let socket = ipc::socket_path(paths).ok_or_else(|| anyhow!("XDG_RUNTIME_DIR is not set"))?;
+    Client::connect(&socket).context("connect daemon socket")
 }
 
 fn ipc_pause(paths: &Paths, ttl: Duration, reason: &str) -> Result<()> {
-    ipc_command(paths, "pause", Some(ttl), Some(reason.to_string()))
+    connect(paths)?.command(&Request::Pause {
+        ttl_ms: Some(ttl.as_millis() as u64),
+        reason: Some(reason.to_string()),
+    })?;
+    Ok(())
 }
 
 fn ipc_resume(paths: &Paths) -> Result<()> {
-    ipc_command(paths, "resume", None, None)
-}
-
-fn ipc_status(paths: &Paths) -> Result<DaemonStatus> {
-    let req = IpcRequest::status();
-    let resp: IpcResponse<serde_json::Value> = ipc_request(paths, &req)?;
-    if resp.ok {
-        let value = resp.result.ok_or_else(|| anyhow!("missing status"))?;
-        let status: DaemonStatus = serde_json::from_value(value)?;
-        Ok(status)
-    } else {
-        Err(anyhow!(resp
-            .error
-            .unwrap_or_else(|| "unknown error".to_string())))
-    }
-}
-
-fn ipc_request<T: for<'de> Deserialize<'de>, R: Serialize>(
-    paths: &Paths,
-    req: &R,
-) -> Result<IpcResponse<T>> {
-    let socket = daemon_socket_path(paths)?;
-    let mut stream = UnixStream::connect(socket).context("connect daemon socket")?;
-    write_request(&mut stream, req)?;
-    let mut reader = BufReader::new(stream);
-    let mut line = String::new();
-    reader.read_line(&mut line)?;
-    if line.len() > IPC_MAX_LINE {
-        return Err(anyhow!("response too large"));
-    }
-    let resp: IpcResponse<T> = serde_json::from_str(line.trim_end())?;
-    Ok(resp)
-}
-
-fn write_request<R: Serialize>(stream: &mut UnixStream, req: &R) -> Result<()> {
-    let mut writer = BufWriter::new(stream);
-    let json = serde_json::to_string(req)?;
-    if json.len() > IPC_MAX_LINE {
-        return Err(anyhow!("request too large"));
-    }
-    writer.write_all(json.as_bytes())?;
-    writer.write_all(b"\n")?;
-    writer.flush()?;
+    connect(paths)?.command(&Request::Resume)?;
     Ok(())
 }
 
-fn daemon_socket_path(paths: &Paths) -> Result<PathBuf> {
-    let runtime = paths
-        .runtime_dir()
-        .ok_or_else(|| anyhow!("XDG_RUNTIME_DIR is not set"))?;
-    Ok(runtime.join(SOCKET_FILENAME))
+fn ipc_status(paths: &Paths) -> Result<DaemonStatus> {
+    Ok(connect(paths)?.status()?)
 }
 
 fn print_status(status: &DaemonStatus) {
@@ -831,7 +1658,10 @@ fn print_status(status: &DaemonStatus) {
     println!("config_path: {}", status.config_path);
     println!("watch_roots:");
     for root in &status.watch_roots {
-        println!("  - {root}");
+        match status.watch_strategies.get(root) {
+            Some(strategy) => println!("  - {root} ({strategy})"),
+            None => println!("  - {root}"),
+        }
     }
     println!("queue_size: {}", status.queue_size);
     println!("inhibited: {}", status.inhibited);
@@ -848,37 +1678,132 @@ fn print_status(status: &DaemonStatus) {
         println!("last_error_at: {ts}");
     }
     println!("total_flushes: {}", status.total_flushes);
+    if let Some(reason) = &status.throttle {
+        match &status.throttled_until {
+            Some(until) => println!("throttle: {reason} until {until}"),
+            None => println!("throttle: {reason}"),
+        }
+    }
+    let metrics = &status.metrics;
+    if metrics.consecutive_flush_failures > 0 {
+        let since = metrics.failing_since.as_deref().unwrap_or("unknown");
+        println!(
+            "failing: {} consecutive flush failure(s) since {since}",
+            metrics.consecutive_flush_failures
+        );
+    }
+    if let Some(last_snapshot) = &status.last_snapshot_at {
+        println!("last_snapshot_at: {last_snapshot}");
+    }
+    println!("total_snapshots: {}", status.total_snapshots);
+    if let Some(remote) = &status.push_remote {
+        println!("push: {}", push_summary(remote, status));
+    }
 }
 
-fn install_systemd_unit(paths: &Paths) -> Result<()> {
-    let config_dir = paths.config_home_dir();
-    let unit_dir = config_dir.join("systemd").join("user");
-    fs::create_dir_all(&unit_dir).context("create systemd user dir")?;
+fn push_summary(remote: &str, status: &DaemonStatus) -> String {
+    let mut summary = match (status.unpushed_commits, &status.last_push_error) {
+        (0, None) => return format!("up to date with '{remote}'"),
+        (0, Some(_)) => format!("push to '{remote}' failing"),
+        (1, _) => format!("1 commit unpushed to '{remote}'"),
+        (count, _) => format!("{count} commits unpushed to '{remote}'"),
+    };
+    if let Some(err) = &status.last_push_error {
+        summary.push_str(&format!(", last attempt failed: {err}"));
+    }
+    if let Some(ts) = &status.last_push_attempt_at {
+        summary.push_str(&format!(" (at {ts})"));
+    }
+    summary
+}
+
+pub(crate) fn systemd_unit_dir(paths: &Paths) -> PathBuf {
+    paths.config_home_dir().join("systemd").join("user")
+}
 
-    let unit_path = unit_dir.join("hometree.service");
-    let unit = format!(
-        "[Unit]\nDescription=hometree daemon\n\n[Service]\nType=simple\nExecStart={exe} daemon run\nRestart=on-failure\nRestartSec=2\nRuntimeDirectory=hometree\nRuntimeDirectoryMode=0700\nExecReload=/bin/kill -HUP $MAINPID\n\n[Install]\nWantedBy=default.target\n",
-        exe = std::env::current_exe()?.display()
-    );
-    fs::write(&unit_path, unit).context("write systemd unit")?;
+fn install_systemd_unit(overrides: &Overrides, socket: bool, harden: bool) -> Result<()> {
+    let paths = load_paths(overrides)?;
+    let unit_dir = systemd_unit_dir(&paths);
+    fs::create_dir_all(&unit_dir).context("create systemd user dir")?;
 
+    let socket_path = if socket {
+        Some(ipc::socket_path(&paths).ok_or_else(|| {
+            anyhow!("socket activation needs HOMETREE_RUNTIME_DIR or XDG_RUNTIME_DIR")
+        })?)
+    } else {
+        None
+    };
+    let mut writable = vec![
+        paths.home_dir().to_path_buf(),
+        paths.config_dir().to_path_buf(),
+        paths.data_dir().to_path_buf(),
+        paths.state_dir().to_path_buf(),
+    ];
+    writable.extend(paths.runtime_dir().map(Path::to_path_buf));
+    if let Ok((_, config)) = load_config(overrides) {
+        writable.push(config.repo.git_dir);
+        writable.push(config.repo.work_tree);
+    }
+    // Directories below one already listed are writable anyway.
+    writable.sort();
+    writable.dedup_by(|dir, kept| dir.starts_with(kept));
+
+    let options = UnitOptions {
+        exe: std::env::current_exe()?,
+        socket: socket_path.clone(),
+        harden,
+        writable,
+    };
+    let unit_path = unit_dir.join(systemd::SERVICE_UNIT);
+    fs::write(&unit_path, systemd::service_unit(&options)).context("write systemd unit")?;
     println!("installed {}", unit_path.display());
-    println!("run: systemctl --user daemon-reload");
+
+    let socket_unit_path = unit_dir.join(systemd::SOCKET_UNIT);
+    match &socket_path {
+        Some(socket_path) => {
+            fs::write(&socket_unit_path, systemd::socket_unit(socket_path))
+                .context("write systemd socket unit")?;
+            println!("installed {}", socket_unit_path.display());
+        }
+        None if socket_unit_path.exists() => {
+            let _ = systemctl_user(&["disable", "--now", systemd::SOCKET_UNIT]);
+            fs::remove_file(&socket_unit_path).context("remove systemd socket unit")?;
+            println!("removed {}", socket_unit_path.display());
+        }
+        None => {}
+    }
+    println!("run: systemctl --user daemon-reload && hometree daemon start");
     Ok(())
 }
 
 fn uninstall_systemd_unit(paths: &Paths) -> Result<()> {
-    let config_dir = paths.config_home_dir();
-    let unit_dir = config_dir.join("systemd").join("user");
-    let unit_path = unit_dir.join("hometree.service");
-    let _ = systemctl_user(&["disable", "--now", "hometree.service"]);
-    if unit_path.exists() {
-        fs::remove_file(&unit_path).context("remove systemd unit")?;
+    let unit_dir = systemd_unit_dir(paths);
+    let _ = systemctl_user(&["disable", "--now", systemd::SOCKET_UNIT]);
+    let _ = systemctl_user(&["disable", "--now", systemd::SERVICE_UNIT]);
+    for unit in [systemd::SOCKET_UNIT, systemd::SERVICE_UNIT] {
+        let unit_path = unit_dir.join(unit);
+        if unit_path.exists() {
+            fs::remove_file(&unit_path).context("remove systemd unit")?;
+        }
     }
     let _ = systemctl_user(&["daemon-reload"]);
     Ok(())
 }
 
+/// `systemctl --user is-active unit`; `None` when systemd cannot be asked.
+pub(crate) fn unit_active(unit: &str) -> Option<bool> {
+    let output = std::process::Command::new("systemctl")
+        .args(["--user", "is-active", unit])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "" => None,
+        "active" | "reloading" | "deactivating" => Some(true),
+        _ => Some(false),
+    }
+}
+
 fn systemctl_user(args: &[&str]) -> Result<()> {
     let status = std::process::Command::new("systemctl")
         .arg("--user")
@@ -899,8 +1824,11 @@ struct DaemonContext {
     secrets_backend: Option<AgeBackend>,
     allowlist: globset::GlobSet,
     auto_add_enabled: bool,
-    debounce: Duration,
+    debounce: DebounceWindows,
+    quiet_hours: Vec<TimeWindow>,
+    poll_interval: Duration,
     watch_roots: Vec<PathBuf>,
+    glob_roots: Vec<String>,
 }
 
 impl DaemonContext {
@@ -912,7 +1840,16 @@ impl DaemonContext {
         let managed =
             ManagedSet::from_config(&config, paths.home_dir()).context("build managed set")?;
         let watch_roots = watch_paths(&config);
-        if watch_roots.is_empty() {
+        let (glob_roots, unanchored): (Vec<String>, Vec<String>) = glob_roots(&config)
+            .into_iter()
+            .partition(|pattern| !recursive_at_root(pattern));
+        for pattern in unanchored {
+            warn!(
+                "not watching manage.paths entry '{pattern}': a leading '**' would watch all of \
+                 HOME recursively; anchor it under a directory, e.g. '.config/**'"
+            );
+        }
+        if watch_roots.is_empty() && glob_roots.is_empty() {
             return Err(anyhow!("no managed roots or extra files configured"));
         }
         let allowlist_patterns = &config.watch.auto_add_allow_patterns;
@@ -924,11 +1861,21 @@ impl DaemonContext {
         }
         let secrets = SecretsManager::from_config(&config.secrets);
         let secrets_backend = if secrets.enabled() {
-            Some(AgeBackend::from_config(&config.secrets)?)
+            Some(AgeBackend::from_config(
+                &config.secrets,
+                &config.repo.work_tree,
+            )?)
         } else {
             None
         };
-        let debounce_ms = config.watch.debounce_ms.max(50);
+        let debounce = DebounceWindows::from_config(&config.watch)?;
+        let poll_interval_ms = config.watch.poll_interval_ms.max(100);
+        let quiet_hours = config
+            .watch
+            .quiet_hours
+            .iter()
+            .map(|window| window.parse())
+            .collect::<hometree_core::Result<_>>()?;
         Ok(Self {
             paths,
             config,
@@ -937,24 +1884,26 @@ impl DaemonContext {
             secrets_backend,
             allowlist,
             auto_add_enabled,
-            debounce: Duration::from_millis(debounce_ms),
+            debounce,
+            quiet_hours,
+            poll_interval: Duration::from_millis(poll_interval_ms),
             watch_roots,
+            glob_roots,
         })
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-struct DaemonStatus {
-    running_since: String,
-    config_path: String,
-    watch_roots: Vec<String>,
-    queue_size: usize,
-    inhibited: bool,
-    inhibit_reason: Option<String>,
-    last_flush_at: Option<String>,
-    last_error: Option<String>,
-    last_error_at: Option<String>,
-    total_flushes: u64,
+impl DaemonContext {
+    fn debounce_for(&self, rel: &Path) -> Duration {
+        self.debounce.window_for(rel).0
+    }
+
+    fn rate_limit(&self) -> Option<RateLimit> {
+        self.config
+            .watch
+            .max_flushes_per_minute
+            .map(RateLimit::per_minute)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -966,7 +1915,7 @@ struct LastError {
 struct DaemonShared {
     running_since: SystemTime,
     config_path: PathBuf,
-    watch_roots: Vec<PathBuf>,
+    watch_roots: Vec<(PathBuf, WatchStrategy)>,
     queue_size: usize,
     inhibited: bool,
     inhibit_reason: Option<String>,
@@ -974,6 +1923,19 @@ struct DaemonShared {
     last_error: Option<String>,
     last_error_at: Option<SystemTime>,
     total_flushes: u64,
+    last_snapshot_at: Option<SystemTime>,
+    total_snapshots: u64,
+    push_remote: Option<String>,
+    unpushed_commits: usize,
+    last_push_attempt_at: Option<SystemTime>,
+    last_push_error: Option<String>,
+    metrics: DaemonMetrics,
+    inhibited_since: Option<Instant>,
+    inhibited_total: Duration,
+    failing_since: Option<SystemTime>,
+    throttle: Option<ThrottleReason>,
+    throttled_until: Option<SystemTime>,
+    subscribers: Vec<mpsc::SyncSender<String>>,
 }
 
 impl Default for DaemonShared {
@@ -989,12 +1951,25 @@ impl Default for DaemonShared {
             last_error: None,
             last_error_at: None,
             total_flushes: 0,
+            last_snapshot_at: None,
+            total_snapshots: 0,
+            push_remote: None,
+            unpushed_commits: 0,
+            last_push_attempt_at: None,
+            last_push_error: None,
+            metrics: DaemonMetrics::default(),
+            inhibited_since: None,
+            inhibited_total: Duration::ZERO,
+            failing_since: None,
+            throttle: None,
+            throttled_until: None,
+            subscribers: Vec::new(),
         }
     }
 }
 
 impl DaemonShared {
-    fn new(config_path: PathBuf, watch_roots: Vec<PathBuf>) -> Self {
+    fn new(config_path: PathBuf, watch_roots: Vec<(PathBuf, WatchStrategy)>) -> Self {
         Self {
             running_since: SystemTime::now(),
             config_path,
@@ -1003,6 +1978,27 @@ impl DaemonShared {
         }
     }
 
+    /// Send `event` to every subscriber, dropping those that disconnected.
+    /// Slow subscribers miss events instead of stalling the daemon.
+    fn publish(&mut self, event: DaemonEvent) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        let record = EventRecord {
+            ts: format_time(SystemTime::now()),
+            event,
+        };
+        let Ok(line) = serde_json::to_string(&record) else {
+            return;
+        };
+        self.subscribers.retain(|tx| {
+            !matches!(
+                tx.try_send(line.clone()),
+                Err(mpsc::TrySendError::Disconnected(_))
+            )
+        });
+    }
+
     fn status(&self) -> DaemonStatus {
         DaemonStatus {
             running_since: format_time(self.running_since),
@@ -1010,7 +2006,12 @@ impl DaemonShared {
             watch_roots: self
                 .watch_roots
                 .iter()
-                .map(|p| p.display().to_string())
+                .map(|(p, _)| p.display().to_string())
+                .collect(),
+            watch_strategies: self
+                .watch_roots
+                .iter()
+                .map(|(p, strategy)| (p.display().to_string(), strategy.to_string()))
                 .collect(),
             queue_size: self.queue_size,
             inhibited: self.inhibited,
@@ -1019,64 +2020,38 @@ impl DaemonShared {
             last_error: self.last_error.clone(),
             last_error_at: self.last_error_at.map(format_time),
             total_flushes: self.total_flushes,
+            last_snapshot_at: self.last_snapshot_at.map(format_time),
+            total_snapshots: self.total_snapshots,
+            push_remote: self.push_remote.clone(),
+            unpushed_commits: self.unpushed_commits,
+            last_push_attempt_at: self.last_push_attempt_at.map(format_time),
+            last_push_error: self.last_push_error.clone(),
+            throttle: self.throttle.map(|reason| reason.to_string()),
+            throttled_until: self.throttled_until.map(format_time),
+            metrics: DaemonMetrics {
+                inhibited_ms_total: duration_ms(self.inhibited_for(Instant::now())),
+                failing_since: self.failing_since.map(format_time),
+                ..self.metrics.clone()
+            },
         }
     }
-}
 
-fn format_time(ts: SystemTime) -> String {
-    OffsetDateTime::from(ts)
-        .format(&Rfc3339)
-        .unwrap_or_else(|_| "unknown".to_string())
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct IpcRequest {
-    cmd: String,
-    ttl_ms: Option<u64>,
-    reason: Option<String>,
-}
-
-impl IpcRequest {
-    fn ping() -> Self {
-        Self {
-            cmd: "ping".to_string(),
-            ttl_ms: None,
-            reason: None,
-        }
-    }
-
-    fn status() -> Self {
-        Self {
-            cmd: "status".to_string(),
-            ttl_ms: None,
-            reason: None,
-        }
+    fn inhibited_for(&self, now: Instant) -> Duration {
+        let current = self
+            .inhibited_since
+            .map_or(Duration::ZERO, |since| now.duration_since(since));
+        self.inhibited_total + current
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct IpcResponse<T> {
-    ok: bool,
-    result: Option<T>,
-    error: Option<String>,
+fn duration_ms(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
 }
 
-impl<T> IpcResponse<T> {
-    fn ok(result: T) -> Self {
-        Self {
-            ok: true,
-            result: Some(result),
-            error: None,
-        }
-    }
-
-    fn err(error: String) -> Self {
-        Self {
-            ok: false,
-            result: None,
-            error: Some(error),
-        }
-    }
+fn format_time(ts: SystemTime) -> String {
+    OffsetDateTime::from(ts)
+        .format(&Rfc3339)
+        .unwrap_or_else(|_| "unknown".to_string())
 }
 
 enum ControlMessage {
@@ -1099,7 +2074,14 @@ enum ControlMessage {
     },
 }
 
+const PUSH_RETRY_INITIAL: Duration = Duration::from_secs(15);
+const PUSH_RETRY_MAX: Duration = Duration::from_secs(30 * 60);
+const PUSH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const METRICS_WRITE_INTERVAL: Duration = Duration::from_secs(15);
+const BATTERY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
 struct Backoff {
+    initial: Duration,
     current: Duration,
     max: Duration,
     until: Option<Instant>,
@@ -1107,9 +2089,14 @@ struct Backoff {
 
 impl Backoff {
     fn new() -> Self {
+        Self::with_limits(Duration::from_millis(200), Duration::from_secs(10))
+    }
+
+    fn with_limits(initial: Duration, max: Duration) -> Self {
         Self {
-            current: Duration::from_millis(200),
-            max: Duration::from_secs(10),
+            initial,
+            current: initial,
+            max,
             until: None,
         }
     }
@@ -1125,7 +2112,277 @@ impl Backoff {
     }
 
     fn reset(&mut self) {
-        self.current = Duration::from_millis(200);
+        self.current = self.initial;
         self.until = None;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        handle_ipc_connection, push_summary, refresh_glob_watches, update_push_result,
+        ConnectionSlot, ControlMessage, DaemonEvent, DaemonShared, MAX_IPC_CONNECTIONS,
+    };
+    use hometree_cli::fswatch::Watchers;
+    use hometree_cli::watch::WatchTarget;
+    use hometree_core::ipc::{Client, Request, PROTOCOL_VERSION};
+    use notify::RecursiveMode;
+    use std::collections::BTreeSet;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn glob_watches_follow_matching_directories() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let home = temp.path();
+        std::fs::create_dir_all(home.join(".config/app")).unwrap();
+        let patterns = vec![".config/*/*.toml".to_string()];
+        let (mut watcher, _events) = Watchers::new(Duration::from_secs(1));
+        let mut current = BTreeSet::new();
+        let paths = |current: &BTreeSet<WatchTarget>| {
+            current
+                .iter()
+                .map(|target| target.path.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let added = refresh_glob_watches(home, &patterns, &[], &mut watcher, &mut current);
+        assert_eq!(added.len(), 2);
+        assert_eq!(
+            paths(&current),
+            vec![PathBuf::from(".config"), PathBuf::from(".config/app")]
+        );
+        assert!(watcher.strategy(&home.join(".config/app")).is_some());
+
+        std::fs::create_dir_all(home.join(".config/tool")).unwrap();
+        std::fs::remove_dir_all(home.join(".config/app")).unwrap();
+        let added = refresh_glob_watches(home, &patterns, &[], &mut watcher, &mut current);
+        assert_eq!(
+            added.iter().map(|t| t.path.clone()).collect::<Vec<_>>(),
+            vec![PathBuf::from(".config/tool")]
+        );
+        assert_eq!(
+            paths(&current),
+            vec![PathBuf::from(".config"), PathBuf::from(".config/tool")]
+        );
+        assert!(watcher.strategy(&home.join(".config/app")).is_none());
+        assert!(watcher.strategy(&home.join(".config/tool")).is_some());
+
+        let added = refresh_glob_watches(home, &[], &[], &mut watcher, &mut current);
+        assert!(added.is_empty());
+        assert!(current.is_empty());
+        assert!(watcher.strategy(&home.join(".config")).is_none());
+    }
+
+    #[test]
+    fn glob_watches_leave_literal_roots_alone() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let home = temp.path();
+        std::fs::create_dir_all(home.join(".config/nvim/lua")).unwrap();
+        std::fs::create_dir_all(home.join(".config/code")).unwrap();
+        let roots = vec![PathBuf::from(".config/nvim")];
+        let patterns = vec![".config/*/settings.json".to_string()];
+        let (mut watcher, _events) = Watchers::new(Duration::from_secs(1));
+        let root = home.join(".config/nvim");
+        watcher.watch(&root, RecursiveMode::Recursive).unwrap();
+
+        // Stands in for a glob target left over from before the root existed.
+        let mut current = BTreeSet::from([WatchTarget {
+            path: PathBuf::from(".config/nvim"),
+            recursive: false,
+        }]);
+        refresh_glob_watches(home, &patterns, &roots, &mut watcher, &mut current);
+        let paths: Vec<PathBuf> = current.iter().map(|target| target.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![PathBuf::from(".config"), PathBuf::from(".config/code")]
+        );
+        assert!(watcher.strategy(&root).is_some());
+
+        refresh_glob_watches(home, &[], &roots, &mut watcher, &mut current);
+        assert!(current.is_empty());
+        assert!(watcher.strategy(&home.join(".config/code")).is_none());
+        assert!(watcher.strategy(&root).is_some());
+    }
+
+    #[test]
+    fn unknown_unpushed_count_keeps_last_count_and_reports_failure() {
+        let shared = Arc::new(Mutex::new(DaemonShared::default()));
+        update_push_result(&shared, Some(2), None);
+        update_push_result(
+            &shared,
+            None,
+            Some("push failed; count unknown".to_string()),
+        );
+        let status = shared.lock().unwrap().status();
+        assert_eq!(status.unpushed_commits, 2);
+        assert!(push_summary("origin", &status)
+            .starts_with("2 commits unpushed to 'origin', last attempt failed: push failed"));
+
+        let shared = Arc::new(Mutex::new(DaemonShared::default()));
+        update_push_result(&shared, None, Some("push failed".to_string()));
+        let status = shared.lock().unwrap().status();
+        let summary = push_summary("origin", &status);
+        assert!(summary.starts_with("push to 'origin' failing"), "{summary}");
+    }
+
+    /// Serve `connections` connections on a temp socket with the real handler.
+    fn serve(
+        listener: UnixListener,
+        connections: usize,
+        shared: Arc<Mutex<DaemonShared>>,
+    ) -> (mpsc::Receiver<ControlMessage>, thread::JoinHandle<()>) {
+        let (control_tx, control_rx) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming().take(connections) {
+                handle_ipc_connection(stream.unwrap(), &control_tx, &shared).unwrap();
+            }
+        });
+        (control_rx, handle)
+    }
+
+    #[test]
+    fn handler_negotiates_and_serves_several_requests_per_connection() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("daemon.sock");
+        let shared = Arc::new(Mutex::new(DaemonShared::default()));
+        let (control_rx, server) = serve(UnixListener::bind(&path).unwrap(), 1, shared);
+        // Stands in for the event loop.
+        let event_loop = thread::spawn(move || {
+            let mut flushes = 0;
+            for message in control_rx {
+                if let ControlMessage::Flush {
+                    respond: Some(respond),
+                } = message
+                {
+                    flushes += 1;
+                    respond.send(Ok(())).unwrap();
+                }
+            }
+            flushes
+        });
+
+        let mut client = Client::connect(&path).unwrap();
+        assert_eq!(client.version(), PROTOCOL_VERSION);
+        client.command(&Request::Ping).unwrap();
+        client.status().unwrap();
+        client.command(&Request::Flush).unwrap();
+        let err = client
+            .command(&Request::Hello {
+                version: PROTOCOL_VERSION,
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("handshake already done"), "{err}");
+        drop(client);
+        server.join().unwrap();
+        assert_eq!(event_loop.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn handler_answers_v1_request_once_then_closes() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let shared = Arc::new(Mutex::new(DaemonShared::default()));
+        let (control_tx, _control_rx) = mpsc::channel();
+        let handler =
+            thread::spawn(move || handle_ipc_connection(server, &control_tx, &shared).unwrap());
+
+        let mut writer = client.try_clone().unwrap();
+        writer.write_all(b"{\"cmd\":\"status\"}\n").unwrap();
+        let mut reader = BufReader::new(client);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["ok"], true);
+        assert!(response["result"]["total_flushes"].is_number());
+
+        handler.join().unwrap();
+        line.clear();
+        assert_eq!(reader.read_line(&mut line).unwrap(), 0);
+    }
+
+    #[test]
+    fn handler_drops_idle_and_oversized_connections() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let shared = Arc::new(Mutex::new(DaemonShared::default()));
+        let (control_tx, _control_rx) = mpsc::channel();
+        let handler = thread::spawn(move || handle_ipc_connection(server, &control_tx, &shared));
+        let mut writer = client.try_clone().unwrap();
+        writeln!(
+            writer,
+            "{{\"cmd\":\"hello\",\"version\":{PROTOCOL_VERSION}}}"
+        )
+        .unwrap();
+        let mut reader = BufReader::new(client);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        // Say nothing more: the handler gives up after the idle timeout.
+        handler.join().unwrap().unwrap();
+        line.clear();
+        assert_eq!(reader.read_line(&mut line).unwrap(), 0);
+
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let shared = Arc::new(Mutex::new(DaemonShared::default()));
+        let (control_tx, _control_rx) = mpsc::channel();
+        let handler = thread::spawn(move || handle_ipc_connection(server, &control_tx, &shared));
+        let flood = thread::spawn(move || {
+            let _ = client.write_all(&vec![b'a'; hometree_core::ipc::MAX_LINE * 2]);
+        });
+        let err = handler.join().unwrap().unwrap_err();
+        assert!(err.to_string().contains("message too large"), "{err:#}");
+        flood.join().unwrap();
+    }
+
+    #[test]
+    fn handler_streams_events_to_subscribers() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("daemon.sock");
+        let shared = Arc::new(Mutex::new(DaemonShared::default()));
+        let (_control_rx, _server) = serve(UnixListener::bind(&path).unwrap(), 1, shared.clone());
+
+        let mut events = Client::connect(&path).unwrap().subscribe().unwrap();
+        // The subscription is registered before the acknowledgement is sent.
+        shared
+            .lock()
+            .unwrap()
+            .publish(DaemonEvent::Flushed { staged: 3 });
+        let line = events.next_line().unwrap().expect("event");
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["event"], "flushed");
+        assert_eq!(value["staged"], 3);
+    }
+
+    #[test]
+    fn connection_slots_are_capped_and_released() {
+        let active = Arc::new(AtomicUsize::new(0));
+        let slots: Vec<_> = (0..MAX_IPC_CONNECTIONS)
+            .map(|_| ConnectionSlot::acquire(&active).expect("slot"))
+            .collect();
+        assert!(ConnectionSlot::acquire(&active).is_none());
+        drop(slots);
+        assert_eq!(active.load(Ordering::Acquire), 0);
+        assert!(ConnectionSlot::acquire(&active).is_some());
+    }
+
+    #[test]
+    fn publish_streams_tagged_events_and_drops_closed_subscribers() {
+        let mut shared = DaemonShared::default();
+        let (tx, rx) = mpsc::sync_channel(4);
+        let (closed_tx, closed_rx) = mpsc::sync_channel(4);
+        drop(closed_rx);
+        shared.subscribers = vec![tx, closed_tx];
+
+        shared.publish(DaemonEvent::Flushed { staged: 2 });
+
+        assert_eq!(shared.subscribers.len(), 1);
+        let line = rx.try_recv().expect("event");
+        let value: serde_json::Value = serde_json::from_str(&line).expect("json");
+        assert_eq!(value["event"], "flushed");
+        assert_eq!(value["staged"], 2);
+        assert!(value["ts"].is_string());
+    }
+}
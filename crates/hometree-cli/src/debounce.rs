@@ -1,11 +1,13 @@
-use std::collections::BTreeSet;
+use std::collections::BTreeMap;
 use std::time::{Duration, Instant};
 
+/// Items pushed with the default window are flushed together once no such item
+/// arrived for `window`; items with their own window each wait on their own deadline.
 #[derive(Debug)]
 pub struct Debounce<T> {
     window: Duration,
     last_event: Option<Instant>,
-    pending: BTreeSet<T>,
+    pending: BTreeMap<T, Option<Instant>>,
 }
 
 impl<T> Debounce<T>
@@ -16,29 +18,58 @@ where
         Self {
             window,
             last_event: None,
-            pending: BTreeSet::new(),
+            pending: BTreeMap::new(),
         }
     }
 
     pub fn push(&mut self, value: T, now: Instant) {
         self.last_event = Some(now);
-        self.pending.insert(value);
+        self.pending.insert(value, None);
     }
 
-    pub fn is_due(&self, now: Instant) -> bool {
-        match self.last_event {
-            Some(last) => now.duration_since(last) >= self.window,
-            None => false,
+    pub fn push_for(&mut self, value: T, now: Instant, window: Duration) {
+        if window == self.window {
+            self.push(value, now);
+        } else {
+            self.pending.insert(value, Some(now + window));
         }
     }
 
+    pub fn is_due(&self, now: Instant) -> bool {
+        let quiet = self.is_quiet(now);
+        self.pending.values().any(|deadline| match deadline {
+            Some(deadline) => *deadline <= now,
+            None => quiet,
+        })
+    }
+
     pub fn drain(&mut self) -> Vec<T> {
-        let items: Vec<T> = self.pending.iter().cloned().collect();
+        let items: Vec<T> = self.pending.keys().cloned().collect();
         self.pending.clear();
         self.last_event = None;
         items
     }
 
+    /// Drain only the items whose window has passed.
+    pub fn drain_due(&mut self, now: Instant) -> Vec<T> {
+        let quiet = self.is_quiet(now);
+        let mut due = Vec::new();
+        self.pending.retain(|item, deadline| {
+            let ready = match deadline {
+                Some(deadline) => *deadline <= now,
+                None => quiet,
+            };
+            if ready {
+                due.push(item.clone());
+            }
+            !ready
+        });
+        if quiet {
+            self.last_event = None;
+        }
+        due
+    }
+
     pub fn clear(&mut self) {
         self.pending.clear();
         self.last_event = None;
@@ -51,6 +82,11 @@ where
     pub fn len(&self) -> usize {
         self.pending.len()
     }
+
+    fn is_quiet(&self, now: Instant) -> bool {
+        self.last_event
+            .is_some_and(|last| now.duration_since(last) >= self.window)
+    }
 }
 
 #[cfg(test)]
@@ -71,4 +107,31 @@ mod tests {
         assert_eq!(drained, vec!["a", "b"]);
         assert!(debouncer.is_empty());
     }
+
+    #[test]
+    fn own_windows_are_drained_separately() {
+        let mut debouncer = Debounce::new(Duration::from_millis(100));
+        let start = Instant::now();
+        debouncer.push_for("state", start, Duration::from_secs(5));
+        debouncer.push("a", start);
+        debouncer.push_for("b", start, Duration::from_millis(100));
+        let later = start + Duration::from_millis(100);
+        assert!(debouncer.is_due(later));
+        assert_eq!(debouncer.drain_due(later), vec!["a", "b"]);
+        assert!(!debouncer.is_due(later));
+        assert_eq!(debouncer.len(), 1);
+
+        // A rewrite pushes the deadline out again.
+        debouncer.push_for(
+            "state",
+            start + Duration::from_secs(4),
+            Duration::from_secs(5),
+        );
+        assert!(!debouncer.is_due(start + Duration::from_secs(5)));
+        assert!(debouncer.is_due(start + Duration::from_secs(9)));
+        assert_eq!(
+            debouncer.drain_due(start + Duration::from_secs(9)),
+            vec!["state"]
+        );
+    }
 }
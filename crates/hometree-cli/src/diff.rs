@@ -0,0 +1,262 @@
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use hometree_core::git::{DiffSpec, FileChange, FileChangeStatus, GitCliBackend};
+use hometree_core::secrets::SecretsManager;
+use hometree_core::{Config, ManagedSet};
+
+use crate::{load_config, resolve_rel_path, status_paths, ColorWhen, Overrides};
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn run_diff(
+    overrides: &Overrides,
+    staged: bool,
+    rev: Option<String>,
+    paths: Vec<PathBuf>,
+    stat: bool,
+    name_only: bool,
+    color: ColorWhen,
+) -> Result<()> {
+    if staged && rev.as_deref().is_some_and(|rev| rev.contains("..")) {
+        return Err(anyhow!(
+            "--staged compares a single revision with the index"
+        ));
+    }
+    let (paths_ctx, config) = load_config(overrides)?;
+    let managed =
+        ManagedSet::from_config(&config, paths_ctx.home_dir()).context("build managed set")?;
+    let secrets = SecretsManager::from_config(&config.secrets);
+    let pathspecs = if paths.is_empty() {
+        default_pathspecs(&config, &secrets)
+    } else {
+        paths
+            .iter()
+            .map(|path| resolve_rel_path(paths_ctx.home_dir(), path))
+            .collect::<Result<_>>()?
+    };
+
+    let git = GitCliBackend::new();
+    let spec = DiffSpec {
+        cached: staged,
+        rev,
+    };
+    let git_dir = &config.repo.git_dir;
+    let work_tree = &config.repo.work_tree;
+    let changes = git
+        .diff_changes(git_dir, work_tree, &spec, &pathspecs)
+        .context("git diff")?;
+    let changes = classify(changes, &managed, &secrets);
+    changes.note_hidden();
+
+    let mut stdout = std::io::stdout().lock();
+    if name_only {
+        let mut names: Vec<&str> = changes
+            .shown
+            .iter()
+            .chain(changes.sidecars.iter().map(|(change, _)| change))
+            .map(|change| change.path.as_str())
+            .collect();
+        names.sort_unstable();
+        for name in names {
+            writeln!(stdout, "{name}")?;
+        }
+        return Ok(());
+    }
+
+    let use_color = use_color(color);
+    write_patch(
+        &mut stdout,
+        &git,
+        git_dir,
+        work_tree,
+        &spec,
+        &changes,
+        stat,
+        use_color,
+    )
+}
+
+/// Managed roots plus both paths of every secret rule; rules are not part of
+/// `manage.paths`, but their changes still get summarized.
+pub(crate) fn default_pathspecs(config: &Config, secrets: &SecretsManager) -> Vec<PathBuf> {
+    let mut pathspecs = status_paths(config);
+    for rule in secrets.rules() {
+        pathspecs.push(secrets.ciphertext_path(rule));
+        pathspecs.push(secrets.plaintext_path(rule));
+    }
+    pathspecs
+}
+
+pub(crate) fn use_color(color: ColorWhen) -> bool {
+    match color {
+        ColorWhen::Auto => std::io::stdout().is_terminal(),
+        ColorWhen::Always => true,
+        ColorWhen::Never => false,
+    }
+}
+
+/// The patch (or diffstat) of the shown changes, then one summary line per sidecar.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_patch(
+    out: &mut impl Write,
+    git: &GitCliBackend,
+    git_dir: &Path,
+    work_tree: &Path,
+    spec: &DiffSpec,
+    changes: &Classified,
+    stat: bool,
+    use_color: bool,
+) -> Result<()> {
+    let shown: Vec<PathBuf> = changes
+        .shown
+        .iter()
+        .map(|change| PathBuf::from(&change.path))
+        .collect();
+    let mut options = vec![if use_color {
+        "--color=always"
+    } else {
+        "--color=never"
+    }];
+    if stat {
+        options.push("--stat");
+    }
+    let output = git
+        .diff(git_dir, work_tree, spec, &shown, &options)
+        .context("git diff")?;
+    out.write_all(&output)?;
+
+    for (change, plaintext) in &changes.sidecars {
+        let line = match plaintext {
+            Some(plaintext) => format!(
+                "secret {}: {} (sidecar {}, contents not shown)",
+                plaintext.display(),
+                status_word(change.status),
+                change.path
+            ),
+            None => format!(
+                "sidecar {}: {} (contents not shown)",
+                change.path,
+                status_word(change.status)
+            ),
+        };
+        if use_color {
+            writeln!(out, "\x1b[33m{line}\x1b[0m")?;
+        } else {
+            writeln!(out, "{line}")?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Classified {
+    pub(crate) shown: Vec<FileChange>,
+    /// Sidecar changes with the plaintext path of their rule, when one matches.
+    pub(crate) sidecars: Vec<(FileChange, Option<PathBuf>)>,
+    /// Secret plaintext that is tracked despite the rules; never printed.
+    pub(crate) hidden: usize,
+}
+
+impl Classified {
+    pub(crate) fn note_hidden(&self) {
+        if self.hidden > 0 {
+            eprintln!("note: {} secret plaintext path(s) not shown", self.hidden);
+        }
+    }
+}
+
+pub(crate) fn classify(
+    changes: Vec<FileChange>,
+    managed: &ManagedSet,
+    secrets: &SecretsManager,
+) -> Classified {
+    let mut classified = Classified::default();
+    for change in changes {
+        let path = Path::new(&change.path);
+        if secrets.is_secret_plaintext(path) {
+            classified.hidden += 1;
+            continue;
+        }
+        let rule = secrets
+            .rules()
+            .iter()
+            .find(|rule| secrets.ciphertext_path(rule) == path);
+        if let Some(rule) = rule {
+            let plaintext = secrets.plaintext_path(rule);
+            classified.sidecars.push((change, Some(plaintext)));
+        } else if secrets.enabled() && secrets.is_ciphertext_path(path) {
+            classified.sidecars.push((change, None));
+        } else if managed.is_managed(path) {
+            classified.shown.push(change);
+        }
+    }
+    classified
+}
+
+pub(crate) fn status_word(status: FileChangeStatus) -> &'static str {
+    match status {
+        FileChangeStatus::Added => "added",
+        FileChangeStatus::Modified => "modified",
+        FileChangeStatus::Deleted => "deleted",
+        FileChangeStatus::TypeChanged => "type changed",
+        FileChangeStatus::Renamed | FileChangeStatus::Copied | FileChangeStatus::Unknown => {
+            "changed"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::classify;
+    use hometree_core::config::{SecretRule, SecretsConfig};
+    use hometree_core::git::{FileChange, FileChangeStatus};
+    use hometree_core::secrets::SecretsManager;
+    use hometree_core::ManagedSet;
+    use std::path::PathBuf;
+
+    fn change(path: &str) -> FileChange {
+        FileChange {
+            status: FileChangeStatus::Modified,
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn secrets_are_hidden_or_summarized() {
+        let managed =
+            ManagedSet::new(vec![".config/app/**".to_string()], Vec::new(), Vec::new()).unwrap();
+        let secrets = SecretsManager::from_config(&SecretsConfig {
+            enabled: true,
+            rules: vec![SecretRule {
+                path: ".config/app/token".to_string(),
+                ciphertext: None,
+                mode: None,
+            }],
+            ..Default::default()
+        });
+        let classified = classify(
+            vec![
+                change(".config/app/app.toml"),
+                change(".config/app/token"),
+                change(".config/app/token.age"),
+                change(".config/app/stray.age"),
+                change(".bashrc"),
+            ],
+            &managed,
+            &secrets,
+        );
+        assert_eq!(classified.shown, vec![change(".config/app/app.toml")]);
+        assert_eq!(classified.hidden, 1);
+        assert_eq!(
+            classified.sidecars,
+            vec![
+                (
+                    change(".config/app/token.age"),
+                    Some(PathBuf::from(".config/app/token"))
+                ),
+                (change(".config/app/stray.age"), None),
+            ]
+        );
+    }
+}
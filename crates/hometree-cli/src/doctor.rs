@@ -0,0 +1,466 @@
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+use anyhow::Result;
+use fs2::FileExt;
+use hometree_cli::systemd;
+use hometree_core::config::SecretsConfig;
+use hometree_core::git::GitCliBackend;
+use hometree_core::secrets::{AgeBackend, SecretsManager};
+use hometree_core::{active_inhibit, inhibit_path, ipc, lock_path, read_inhibit, Config, Paths};
+use serde::Serialize;
+
+use crate::daemon::{socket_state, systemd_unit_dir, unit_active, SocketState};
+use crate::{load_config, load_paths, print_json, OutputFormat, Overrides, ProblemsReported};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+    Skip,
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    status: CheckStatus,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Ok,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    fn skip(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Skip,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DoctorReport {
+    ok: bool,
+    checks: Vec<Check>,
+}
+
+pub(crate) fn run_doctor(overrides: &Overrides, json: bool, format: OutputFormat) -> Result<()> {
+    let paths = load_paths(overrides)?;
+    let mut checks = vec![check_git(), check_filter_repo()];
+    let config = match load_config(overrides) {
+        Ok((_, config)) => {
+            checks.push(Check::ok(
+                "config",
+                paths.config_file().display().to_string(),
+            ));
+            Some(config)
+        }
+        Err(err) => {
+            checks.push(Check::fail(
+                "config",
+                format!("{err:#}"),
+                format!(
+                    "run `hometree init` or fix {}",
+                    paths.config_file().display()
+                ),
+            ));
+            None
+        }
+    };
+    if let Some(config) = &config {
+        checks.push(check_repo(config));
+        checks.push(check_work_tree(&paths, config));
+        checks.extend(check_secrets(config));
+        checks.push(check_tracked_plaintext(config));
+    }
+    checks.push(check_lock(&paths));
+    checks.push(check_inhibit(&paths));
+    checks.push(check_socket(&paths));
+
+    let report = DoctorReport {
+        ok: checks.iter().all(|check| check.status != CheckStatus::Fail),
+        checks,
+    };
+    if json || format == OutputFormat::Json {
+        print_json("doctor", &report)?;
+    } else {
+        for check in &report.checks {
+            let label = match check.status {
+                CheckStatus::Ok => "ok",
+                CheckStatus::Warn => "WARN",
+                CheckStatus::Fail => "FAIL",
+                CheckStatus::Skip => "skip",
+            };
+            println!("{label:<5} {}: {}", check.name, check.message);
+            if let Some(fix) = &check.fix {
+                println!("      fix: {fix}");
+            }
+        }
+    }
+    if !report.ok {
+        return Err(ProblemsReported.into());
+    }
+    Ok(())
+}
+
+fn tool_version(program: &str) -> Option<String> {
+    let output = Command::new(program).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn check_git() -> Check {
+    match tool_version("git") {
+        Some(version) => Check::ok("git", version),
+        None => Check::fail(
+            "git",
+            "git not found in PATH",
+            "install git with your package manager",
+        ),
+    }
+}
+
+fn check_filter_repo() -> Check {
+    match tool_version("git-filter-repo") {
+        Some(version) => Check::ok("git-filter-repo", format!("version {version}")),
+        None => Check::warn(
+            "git-filter-repo",
+            "not found; `hometree secret add` cannot purge plaintext from history",
+            "pip install git-filter-repo",
+        ),
+    }
+}
+
+fn check_repo(config: &Config) -> Check {
+    let git_dir = &config.repo.git_dir;
+    if git_dir.join("HEAD").is_file() {
+        Check::ok("repo", git_dir.display().to_string())
+    } else {
+        Check::fail(
+            "repo",
+            format!("{} is not a git repository", git_dir.display()),
+            "run `hometree init`, or point repo.git_dir at the existing repository",
+        )
+    }
+}
+
+fn check_work_tree(paths: &Paths, config: &Config) -> Check {
+    let work_tree = &config.repo.work_tree;
+    let home = paths.home_dir();
+    let set_fix = format!("set repo.work_tree = \"{}\" in config.toml", home.display());
+    let Ok(canonical) = work_tree.canonicalize() else {
+        return Check::fail(
+            "work_tree",
+            format!("{} does not exist", work_tree.display()),
+            set_fix,
+        );
+    };
+    if work_tree == home {
+        return Check::ok("work_tree", work_tree.display().to_string());
+    }
+    if home.canonicalize().ok().as_deref() == Some(canonical.as_path()) {
+        return Check::warn(
+            "work_tree",
+            format!(
+                "{} and HOME ({}) are the same directory under different paths",
+                work_tree.display(),
+                home.display()
+            ),
+            set_fix,
+        );
+    }
+    Check::fail(
+        "work_tree",
+        format!(
+            "{} is not HOME ({}); files would be read and deployed elsewhere",
+            work_tree.display(),
+            home.display()
+        ),
+        set_fix,
+    )
+}
+
+fn check_secrets(config: &Config) -> Vec<Check> {
+    let secrets = &config.secrets;
+    if !secrets.enabled {
+        return vec![
+            Check::skip("identities", "secrets are disabled"),
+            Check::skip("recipients", "secrets are disabled"),
+        ];
+    }
+
+    // Validate each half on its own so one bad setting doesn't mask the other.
+    let identities_only = SecretsConfig {
+        recipients: Vec::new(),
+        recipients_files: Vec::new(),
+        recipients_dir: None,
+        ..secrets.clone()
+    };
+    let recipients_only = SecretsConfig {
+        identity_files: Vec::new(),
+        ..secrets.clone()
+    };
+    let work_tree = &config.repo.work_tree;
+
+    let unreadable: Vec<String> = secrets
+        .identity_files
+        .iter()
+        .filter_map(|path| {
+            std::fs::read_to_string(path)
+                .err()
+                .map(|err| format!("{}: {err}", path.display()))
+        })
+        .collect();
+    let identities = if !unreadable.is_empty() {
+        Check::fail(
+            "identities",
+            format!("cannot read identity file {}", unreadable.join(", ")),
+            "fix secrets.identity_files or the file permissions",
+        )
+    } else {
+        match AgeBackend::from_config(&identities_only, work_tree) {
+            Ok(backend) => match backend.ensure_identities() {
+                Ok(()) => Check::ok(
+                    "identities",
+                    format!("{} identity file(s)", secrets.identity_files.len()),
+                ),
+                Err(err) => Check::warn(
+                    "identities",
+                    err.to_string(),
+                    "add an age identity to secrets.identity_files to decrypt on deploy",
+                ),
+            },
+            Err(err) => Check::fail(
+                "identities",
+                err.to_string(),
+                "each line of an identity file must be an AGE-SECRET-KEY-1... key",
+            ),
+        }
+    };
+
+    let recipients = match AgeBackend::from_config(&recipients_only, work_tree) {
+        Ok(backend) => match backend.ensure_recipients() {
+            Ok(()) => Check::ok(
+                "recipients",
+                format!("{} recipient(s)", backend.recipient_keys().len()),
+            ),
+            Err(err) => Check::fail(
+                "recipients",
+                err.to_string(),
+                "add an age public key to secrets.recipients or secrets.recipients_files",
+            ),
+        },
+        Err(err) => Check::fail(
+            "recipients",
+            err.to_string(),
+            "each recipient must be an age1... public key",
+        ),
+    };
+    vec![identities, recipients]
+}
+
+fn check_tracked_plaintext(config: &Config) -> Check {
+    let secrets = SecretsManager::from_config(&config.secrets);
+    if secrets.rules().is_empty() {
+        return Check::skip("plaintext", "no secret rules");
+    }
+    let plaintext: Vec<PathBuf> = secrets
+        .rules()
+        .iter()
+        .map(|rule| secrets.plaintext_path(rule))
+        .collect();
+    let git = GitCliBackend::new();
+    let git_dir = &config.repo.git_dir;
+    let work_tree = &config.repo.work_tree;
+    let tracked = match git.tracked_paths(git_dir, work_tree, &plaintext) {
+        Ok(tracked) => tracked,
+        Err(err) => return Check::skip("plaintext", format!("git ls-files failed: {err}")),
+    };
+    if !tracked.is_empty() {
+        return Check::fail(
+            "plaintext",
+            format!(
+                "secret plaintext tracked in the index: {}",
+                tracked.join(", ")
+            ),
+            format!(
+                "git --git-dir {} --work-tree {} rm --cached -- <path>",
+                git_dir.display(),
+                work_tree.display()
+            ),
+        );
+    }
+    let in_history: Vec<String> = plaintext
+        .iter()
+        .filter(|path| {
+            git.file_in_history(git_dir, work_tree, path)
+                .unwrap_or(false)
+        })
+        .map(|path| path.display().to_string())
+        .collect();
+    if !in_history.is_empty() {
+        return Check::warn(
+            "plaintext",
+            format!(
+                "secret plaintext present in git history: {}",
+                in_history.join(", ")
+            ),
+            "git filter-repo --invert-paths --path <path>, then rotate the secret",
+        );
+    }
+    Check::ok(
+        "plaintext",
+        format!("{} secret(s), none tracked", plaintext.len()),
+    )
+}
+
+/// Probes the lock without creating the state directory or the lock file; the
+/// shared lock is released as soon as the file is dropped.
+fn check_lock(paths: &Paths) -> Check {
+    if !paths.state_dir().exists() {
+        return Check::skip("lock", "no state directory yet");
+    }
+    let file = match File::open(lock_path(paths)) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Check::ok("lock", "free"),
+        Err(err) => {
+            return Check::fail(
+                "lock",
+                format!("cannot open {}: {err}", lock_path(paths).display()),
+                format!("check permissions on {}", paths.state_dir().display()),
+            )
+        }
+    };
+    match FileExt::try_lock_shared(&file) {
+        Ok(()) => Check::ok("lock", "free"),
+        Err(err) if err.kind() == ErrorKind::WouldBlock => Check::warn(
+            "lock",
+            format!("{} is held by another process", lock_path(paths).display()),
+            format!(
+                "wait for the running hometree command, or find a hung one with `fuser {}`",
+                lock_path(paths).display()
+            ),
+        ),
+        Err(err) => Check::fail(
+            "lock",
+            format!("cannot lock {}: {err}", lock_path(paths).display()),
+            format!("check permissions on {}", paths.state_dir().display()),
+        ),
+    }
+}
+
+fn check_inhibit(paths: &Paths) -> Check {
+    let remove_fix = format!("rm {}", inhibit_path(paths).display());
+    let marker = match read_inhibit(paths) {
+        Ok(Some(marker)) => marker,
+        Ok(None) => return Check::ok("inhibit", "not paused"),
+        Err(err) => return Check::fail("inhibit", format!("unreadable marker: {err}"), remove_fix),
+    };
+    if marker.is_expired(SystemTime::now()) {
+        return Check::warn(
+            "inhibit",
+            format!(
+                "expired marker from '{}' left behind (expired {})",
+                marker.reason, marker.expires_at
+            ),
+            remove_fix,
+        );
+    }
+    if !process_alive(marker.pid) {
+        return Check::warn(
+            "inhibit",
+            format!(
+                "'{}' paused the daemon until {}, but process {} has exited",
+                marker.reason, marker.expires_at, marker.pid
+            ),
+            remove_fix,
+        );
+    }
+    match active_inhibit(paths) {
+        Ok(Some(marker)) => Check::ok(
+            "inhibit",
+            format!("paused by '{}' until {}", marker.reason, marker.expires_at),
+        ),
+        _ => Check::ok("inhibit", "not paused"),
+    }
+}
+
+fn process_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+fn check_socket(paths: &Paths) -> Check {
+    let Some(socket) = ipc::socket_path(paths) else {
+        return Check::skip("daemon", "XDG_RUNTIME_DIR is not set");
+    };
+    // Connecting to a listening socket unit would make systemd start the
+    // daemon, so a stopped socket-activated daemon is not probed.
+    if systemd_unit_dir(paths).join(systemd::SOCKET_UNIT).exists()
+        && unit_active(systemd::SOCKET_UNIT) == Some(true)
+        && unit_active(systemd::SERVICE_UNIT) == Some(false)
+    {
+        return Check::ok(
+            "daemon",
+            format!(
+                "socket-activated; {} starts it on the first connection to {}",
+                systemd::SOCKET_UNIT,
+                socket.display()
+            ),
+        );
+    }
+    match socket_state(&socket) {
+        SocketState::Absent => Check::ok("daemon", "not running"),
+        SocketState::Responding => {
+            Check::ok("daemon", format!("responding on {}", socket.display()))
+        }
+        SocketState::NotResponding => Check::warn(
+            "daemon",
+            format!("daemon not responding on {}", socket.display()),
+            "hometree daemon restart",
+        ),
+        SocketState::Stale => Check::warn(
+            "daemon",
+            format!("{} exists but no daemon is listening", socket.display()),
+            format!(
+                "rm {} (or hometree daemon start if one should run)",
+                socket.display()
+            ),
+        ),
+    }
+}
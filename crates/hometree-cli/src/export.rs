@@ -0,0 +1,211 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use hometree_core::export::{CONFIG_ENTRY, REPO_BUNDLE_ENTRY};
+use hometree_core::git::{GitBackend, GitCliBackend};
+use hometree_core::secrets::{AgeBackend, SecretsManager};
+use hometree_core::{
+    deploy_with_options, read_export, write_export, Config, DeployOptions, ExportManifest, Paths,
+};
+use tracing::info;
+
+use crate::{init_bare_repo, load_config, Overrides};
+
+pub(crate) fn run_export(overrides: &Overrides, rev: String, output: PathBuf) -> Result<()> {
+    let (paths, config) = load_config(overrides)?;
+    let git = GitCliBackend::new();
+    let git_dir = &config.repo.git_dir;
+    let work_tree = &config.repo.work_tree;
+
+    std::fs::create_dir_all(paths.state_dir()).context("create state dir")?;
+    let scratch = paths.state_dir().join("export.bundle");
+    let created = git
+        .create_bundle(git_dir, &rev, &scratch)
+        .with_context(|| format!("git bundle {rev}"))
+        .and_then(|commit| {
+            let bundle =
+                std::fs::read(&scratch).with_context(|| format!("read {}", scratch.display()))?;
+            Ok((commit, bundle))
+        });
+    let _ = std::fs::remove_file(&scratch);
+    let (commit, bundle) = created?;
+    let branch = git
+        .current_branch(git_dir)
+        .context("git symbolic-ref")?
+        .unwrap_or_else(|| "main".to_string());
+    let config_bytes = std::fs::read(paths.config_file())
+        .with_context(|| format!("read {}", paths.config_file().display()))?;
+
+    let secrets = SecretsManager::from_config(&config.secrets);
+    let tree = git
+        .ls_tree(git_dir, work_tree, &commit)
+        .context("git ls-tree")?;
+    let sidecars: Vec<String> = tree
+        .into_iter()
+        .filter(|path| secrets.is_ciphertext_rule_path(Path::new(path)))
+        .collect();
+    for rule in secrets.rules() {
+        let sidecar = secrets.ciphertext_path(rule);
+        if !sidecars.iter().any(|path| Path::new(path) == sidecar) {
+            println!(
+                "warning: {} has no committed sidecar at {rev}; snapshot first to include it",
+                secrets.plaintext_path(rule).display()
+            );
+        }
+    }
+
+    let manifest = ExportManifest {
+        version: 0,
+        rev: rev.clone(),
+        commit: commit.clone(),
+        branch,
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        secrets: sidecars,
+        entries: Vec::new(),
+    };
+    let manifest = write_export(
+        &output,
+        manifest,
+        &[(REPO_BUNDLE_ENTRY, &bundle), (CONFIG_ENTRY, &config_bytes)],
+    )
+    .with_context(|| format!("write {}", output.display()))?;
+    let size: u64 = manifest.entries.iter().map(|entry| entry.size).sum();
+    println!(
+        "exported {} ({rev}) to {}: {size} bytes, {} encrypted secret(s)",
+        short(&commit),
+        output.display(),
+        manifest.secrets.len()
+    );
+    Ok(())
+}
+
+/// Restore the repository and config from an export archive after checking
+/// its checksums, the git bundle and the restored commit.
+pub(crate) fn restore_export(
+    paths: &Paths,
+    git: &GitCliBackend,
+    file: &Path,
+) -> Result<ExportManifest> {
+    let archive = read_export(file).with_context(|| format!("read {}", file.display()))?;
+    let manifest = archive.manifest.clone();
+    let bundle = archive
+        .file(REPO_BUNDLE_ENTRY)
+        .ok_or_else(|| anyhow!("{} has no {REPO_BUNDLE_ENTRY}", file.display()))?;
+
+    let repo_dir = paths.repo_dir();
+    if repo_dir.exists() {
+        return Err(anyhow!(
+            "repo already exists at {}; remove it first to restore from an export",
+            repo_dir.display()
+        ));
+    }
+    let scratch = paths.state_dir().join("restore.bundle");
+    std::fs::write(&scratch, bundle).with_context(|| format!("write {}", scratch.display()))?;
+    init_bare_repo(&repo_dir).context("init bare repo")?;
+    let fetched = git.fetch_bundle(&repo_dir, &scratch, &manifest.branch);
+    let _ = std::fs::remove_file(&scratch);
+    let verified = fetched
+        .context("verify git bundle")
+        .and_then(|head| check_restored(git, &repo_dir, paths.home_dir(), &manifest, &head));
+    if let Err(err) = verified {
+        let _ = std::fs::remove_dir_all(&repo_dir);
+        return Err(err.context(format!("{} failed verification", file.display())));
+    }
+    println!(
+        "verified {}: {} ({}), {} encrypted secret(s)",
+        file.display(),
+        short(&manifest.commit),
+        manifest.rev,
+        manifest.secrets.len()
+    );
+    info!(path = %repo_dir.display(), "restored bare repo from {}", file.display());
+
+    git.reset(&repo_dir, paths.home_dir(), "HEAD")
+        .context("sync index with HEAD after restore")?;
+
+    let config_path = paths.config_file();
+    if config_path.exists() {
+        info!(path = %config_path.display(), "config exists; leaving unchanged");
+    } else if let Some(config_bytes) = archive.file(CONFIG_ENTRY) {
+        std::fs::write(&config_path, config_bytes)?;
+        let mut config = Config::load_from(&config_path).context("load exported config")?;
+        config.repo.git_dir = repo_dir;
+        config.repo.work_tree = paths.home_dir().to_path_buf();
+        config.write_to(&config_path).context("write config")?;
+        info!(path = %config_path.display(), "restored config from export");
+    } else {
+        let cfg = Config::default_with_paths(paths);
+        cfg.write_to(&config_path).context("write default config")?;
+        info!(path = %config_path.display(), "wrote default config (none in export)");
+    }
+    Ok(manifest)
+}
+
+fn check_restored(
+    git: &GitCliBackend,
+    repo_dir: &Path,
+    home: &Path,
+    manifest: &ExportManifest,
+    head: &str,
+) -> Result<()> {
+    if head != manifest.commit {
+        return Err(anyhow!(
+            "bundle restored {} but the manifest records {}",
+            short(head),
+            short(&manifest.commit)
+        ));
+    }
+    let tree = git.ls_tree(repo_dir, home, "HEAD").context("git ls-tree")?;
+    let missing: Vec<&str> = manifest
+        .secrets
+        .iter()
+        .filter(|sidecar| !tree.contains(sidecar))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "secret sidecars missing from the bundle: {}",
+            missing.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Deploy the restored commit, skipping decryption when no identity is
+/// available on this machine yet.
+pub(crate) fn deploy_restored(
+    paths: &Paths,
+    git: &GitCliBackend,
+    manifest: &ExportManifest,
+) -> Result<()> {
+    let mut config = Config::load_from(&paths.config_file()).context("load config")?;
+    if config.secrets.enabled && !manifest.secrets.is_empty() {
+        let usable = AgeBackend::from_config(&config.secrets, &config.repo.work_tree)
+            .and_then(|backend| backend.ensure_identities());
+        if let Err(err) = usable {
+            println!(
+                "note: secrets not decrypted ({err}); add an identity and run `hometree deploy HEAD`"
+            );
+            config.secrets.enabled = false;
+        }
+    }
+    println!("deploying {}...", short(&manifest.commit));
+    let entry = deploy_with_options(
+        &config,
+        paths,
+        git,
+        &manifest.commit,
+        DeployOptions { no_backup: false },
+    )
+    .context("deploy")?;
+    println!("deployed {}", entry.rev);
+    Ok(())
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(12)]
+}
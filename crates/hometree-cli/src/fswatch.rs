@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{ErrorKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use tracing::warn;
+
+pub type EventResult = notify::Result<notify::Event>;
+
+/// How a watched root receives change events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchStrategy {
+    Native,
+    Poll,
+}
+
+impl fmt::Display for WatchStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Native => "native",
+            Self::Poll => "poll",
+        })
+    }
+}
+
+/// Native watcher with a per-root polling fallback. Both feed the same channel.
+pub struct Watchers {
+    tx: mpsc::Sender<EventResult>,
+    native: Option<RecommendedWatcher>,
+    poll: Option<PollWatcher>,
+    poll_interval: Duration,
+    strategies: BTreeMap<PathBuf, WatchStrategy>,
+}
+
+impl Watchers {
+    pub fn new(poll_interval: Duration) -> (Self, mpsc::Receiver<EventResult>) {
+        let (tx, rx) = mpsc::channel();
+        let native = match notify::recommended_watcher(tx.clone()) {
+            Ok(watcher) => Some(watcher),
+            Err(err) => {
+                warn!("native file watching unavailable, polling instead: {err}");
+                None
+            }
+        };
+        let watchers = Self {
+            tx,
+            native,
+            poll: None,
+            poll_interval,
+            strategies: BTreeMap::new(),
+        };
+        (watchers, rx)
+    }
+
+    /// Watch `path`, polling it when native watches are exhausted or unsupported there.
+    pub fn watch(&mut self, path: &Path, mode: RecursiveMode) -> notify::Result<WatchStrategy> {
+        if let Some(native) = self.native.as_mut() {
+            if !is_remote_filesystem(path) {
+                match native.watch(path, mode) {
+                    Ok(()) => {
+                        self.strategies
+                            .insert(path.to_path_buf(), WatchStrategy::Native);
+                        return Ok(WatchStrategy::Native);
+                    }
+                    Err(err) if needs_polling(&err) => {
+                        // A recursive watch may have been added partway.
+                        let _ = native.unwatch(path);
+                        warn!(path = %path.display(), "falling back to polling: {err}");
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        if self.poll.is_none() {
+            let config = notify::Config::default().with_poll_interval(self.poll_interval);
+            self.poll = Some(PollWatcher::new(self.tx.clone(), config)?);
+        }
+        if let Some(poll) = self.poll.as_mut() {
+            poll.watch(path, mode)?;
+        }
+        self.strategies
+            .insert(path.to_path_buf(), WatchStrategy::Poll);
+        Ok(WatchStrategy::Poll)
+    }
+
+    /// Errors are ignored: the kernel drops watches on deleted directories by itself.
+    pub fn unwatch(&mut self, path: &Path) {
+        let watcher: Option<&mut dyn Watcher> = match self.strategies.remove(path) {
+            Some(WatchStrategy::Native) => self.native.as_mut().map(|w| w as &mut dyn Watcher),
+            Some(WatchStrategy::Poll) => self.poll.as_mut().map(|w| w as &mut dyn Watcher),
+            None => None,
+        };
+        if let Some(watcher) = watcher {
+            let _ = watcher.unwatch(path);
+        }
+    }
+
+    pub fn strategy(&self, path: &Path) -> Option<WatchStrategy> {
+        self.strategies.get(path).copied()
+    }
+}
+
+/// Whether a native watch failed in a way that polling can work around.
+pub fn needs_polling(err: &notify::Error) -> bool {
+    match &err.kind {
+        ErrorKind::MaxFilesWatch => true,
+        ErrorKind::Io(io) => matches!(
+            io.raw_os_error(),
+            Some(libc::ENOSPC | libc::EMFILE | libc::ENOSYS | libc::EOPNOTSUPP)
+        ),
+        _ => false,
+    }
+}
+
+/// Network and FUSE filesystems accept inotify watches but miss changes made elsewhere.
+#[cfg(target_os = "linux")]
+pub fn is_remote_filesystem(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    const NFS: i64 = 0x6969;
+    const SMB: i64 = 0x517b;
+    const CIFS: i64 = 0xff53_4d42;
+    const SMB2: i64 = 0xfe53_4d42;
+    const FUSE: i64 = 0x6573_5546;
+    const CEPH: i64 = 0x00c3_6400;
+    const AFS: i64 = 0x5346_414f;
+    const NINEP: i64 = 0x0102_1997;
+
+    let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: statfs only writes into the provided struct.
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+    // f_type is u32 on some targets; the magic numbers all fit in 32 bits.
+    let kind = stat.f_type as i64 & 0xffff_ffff;
+    matches!(kind, NFS | SMB | CIFS | SMB2 | FUSE | CEPH | AFS | NINEP)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_remote_filesystem(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_remote_filesystem, needs_polling, WatchStrategy, Watchers};
+    use notify::{Error, ErrorKind, RecursiveMode};
+    use std::io;
+    use std::time::Duration;
+
+    #[test]
+    fn watch_limit_and_unsupported_errors_fall_back() {
+        assert!(needs_polling(&Error::new(ErrorKind::MaxFilesWatch)));
+        assert!(needs_polling(&Error::io(io::Error::from_raw_os_error(
+            libc::ENOSPC
+        ))));
+        assert!(needs_polling(&Error::io(io::Error::from_raw_os_error(
+            libc::ENOSYS
+        ))));
+        assert!(!needs_polling(&Error::new(ErrorKind::PathNotFound)));
+        assert!(!needs_polling(&Error::io(io::Error::from_raw_os_error(
+            libc::EACCES
+        ))));
+    }
+
+    #[test]
+    fn strategy_is_recorded_per_root() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let expected = if is_remote_filesystem(temp.path()) {
+            WatchStrategy::Poll
+        } else {
+            WatchStrategy::Native
+        };
+        let (mut watchers, _rx) = Watchers::new(Duration::from_millis(100));
+        let strategy = watchers
+            .watch(temp.path(), RecursiveMode::Recursive)
+            .expect("watch");
+        assert_eq!(strategy, expected);
+        assert_eq!(watchers.strategy(temp.path()), Some(expected));
+        watchers.unwatch(temp.path());
+        assert_eq!(watchers.strategy(temp.path()), None);
+    }
+}
@@ -0,0 +1,780 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use hometree_core::git::GitCliBackend;
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    Chezmoi,
+    Yadm,
+    Stow,
+}
+
+impl ImportSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            ImportSource::Chezmoi => "chezmoi",
+            ImportSource::Yadm => "yadm",
+            ImportSource::Stow => "stow",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportContent {
+    /// Copy this file into HOME.
+    File(PathBuf),
+    /// Create a symlink with this target.
+    Symlink(PathBuf),
+    /// Already present in HOME (yadm work tree, applied chezmoi templates).
+    InPlace,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportEntry {
+    /// Path relative to HOME.
+    pub target: PathBuf,
+    /// Where the entry came from, relative to the source.
+    pub origin: String,
+    pub content: ImportContent,
+    /// File mode to apply when writing; `None` keeps the source mode.
+    pub mode: Option<u32>,
+    pub secret: bool,
+    pub note: Option<String>,
+    /// Whether HOME needs to change; false when it already matches.
+    pub write: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Untranslated {
+    pub origin: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportPlan {
+    pub entries: Vec<ImportEntry>,
+    pub untranslated: Vec<Untranslated>,
+}
+
+impl ImportPlan {
+    fn push(
+        &mut self,
+        target: PathBuf,
+        origin: String,
+        content: ImportContent,
+    ) -> &mut ImportEntry {
+        self.entries.push(ImportEntry {
+            target,
+            origin,
+            content,
+            mode: None,
+            secret: false,
+            note: None,
+            write: false,
+        });
+        self.entries.last_mut().expect("entry")
+    }
+
+    fn skip(&mut self, origin: impl Into<String>, reason: impl Into<String>) {
+        self.untranslated.push(Untranslated {
+            origin: origin.into(),
+            reason: reason.into(),
+        });
+    }
+
+    /// Drop duplicate targets and entries that would clobber unrelated files in HOME.
+    fn resolve(&mut self, root: &Path, home: &Path) {
+        let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        let mut seen: BTreeMap<PathBuf, String> = BTreeMap::new();
+        for mut entry in std::mem::take(&mut self.entries) {
+            if let Some(first) = seen.get(&entry.target) {
+                let reason = format!(
+                    "{} is already imported from {first}",
+                    entry.target.display()
+                );
+                self.skip(entry.origin, reason);
+                continue;
+            }
+            match check_target(home, &root, &entry) {
+                Ok(write) => {
+                    entry.write = write;
+                    seen.insert(entry.target.clone(), entry.origin.clone());
+                    self.entries.push(entry);
+                }
+                Err(reason) => {
+                    let reason = format!("{}: {reason}", entry.target.display());
+                    self.skip(entry.origin, reason);
+                }
+            }
+        }
+        self.entries.sort_by(|a, b| a.target.cmp(&b.target));
+    }
+}
+
+/// Read another tool's source layout and translate it into paths under HOME.
+pub fn plan_import(source: ImportSource, root: &Path, home: &Path) -> Result<ImportPlan> {
+    if !root.exists() {
+        return Err(anyhow!(
+            "{} source not found: {}",
+            source.name(),
+            root.display()
+        ));
+    }
+    let mut plan = ImportPlan::default();
+    match source {
+        ImportSource::Chezmoi => {
+            let marker = root.join(".chezmoiroot");
+            let source_root = if marker.is_file() {
+                let sub = fs::read_to_string(&marker).context("read .chezmoiroot")?;
+                root.join(sub.trim())
+            } else {
+                root.to_path_buf()
+            };
+            chezmoi_dir(&source_root, root, Path::new(""), home, &mut plan)?;
+        }
+        ImportSource::Yadm => yadm(root, home, &mut plan)?,
+        ImportSource::Stow => stow(root, &mut plan)?,
+    }
+    plan.resolve(root, home);
+    Ok(plan)
+}
+
+/// Write an entry into HOME, unfolding directory symlinks that point into the source.
+pub fn apply_entry(home: &Path, root: &Path, entry: &ImportEntry) -> Result<()> {
+    if !entry.write {
+        return Ok(());
+    }
+    let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    if let Some(parent) = entry.target.parent() {
+        let mut dir = home.to_path_buf();
+        for component in parent.components() {
+            dir.push(component);
+            if is_symlink(&dir) && links_into(&dir, &root) {
+                fs::remove_file(&dir)?;
+                fs::create_dir(&dir)?;
+            }
+        }
+        fs::create_dir_all(home.join(parent))?;
+    }
+    let path = home.join(&entry.target);
+    match &entry.content {
+        ImportContent::File(src) => {
+            let contents = fs::read(src).with_context(|| format!("read {}", src.display()))?;
+            let mode = match entry.mode {
+                Some(mode) => mode,
+                None => fs::metadata(src)?.permissions().mode() & 0o7777,
+            };
+            if is_symlink(&path) {
+                fs::remove_file(&path)?;
+            }
+            fs::write(&path, contents)?;
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+        }
+        ImportContent::Symlink(link) => {
+            if is_symlink(&path) {
+                fs::remove_file(&path)?;
+            }
+            symlink(link, &path)?;
+        }
+        ImportContent::InPlace => {}
+    }
+    Ok(())
+}
+
+fn check_target(home: &Path, root: &Path, entry: &ImportEntry) -> Result<bool, String> {
+    if let Some(parent) = entry.target.parent() {
+        let mut dir = home.to_path_buf();
+        for component in parent.components() {
+            dir.push(component);
+            match fs::symlink_metadata(&dir) {
+                Ok(meta) if meta.file_type().is_symlink() => {
+                    if !links_into(&dir, root) {
+                        return Err(format!("{} is a symlink to somewhere else", dir.display()));
+                    }
+                    return match entry.content {
+                        ImportContent::InPlace => Err("lives in a symlinked directory".to_string()),
+                        _ => Ok(true),
+                    };
+                }
+                Ok(meta) if !meta.is_dir() => {
+                    return Err(format!("{} is not a directory", dir.display()));
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+    }
+
+    let path = home.join(&entry.target);
+    let Ok(meta) = fs::symlink_metadata(&path) else {
+        return match entry.content {
+            ImportContent::InPlace => Err("not present in HOME".to_string()),
+            _ => Ok(true),
+        };
+    };
+    match &entry.content {
+        ImportContent::InPlace if meta.is_file() => Ok(false),
+        ImportContent::InPlace => Err("not a regular file in HOME".to_string()),
+        ImportContent::File(src) if meta.file_type().is_symlink() => {
+            let same = fs::canonicalize(&path).ok() == fs::canonicalize(src).ok();
+            if same || links_into(&path, root) {
+                Ok(true)
+            } else {
+                Err("HOME has a symlink to somewhere else; left untouched".to_string())
+            }
+        }
+        ImportContent::File(src) if meta.is_file() => {
+            if fs::read(src).ok() == fs::read(&path).ok() {
+                Ok(false)
+            } else {
+                Err("HOME has a different version; left untouched".to_string())
+            }
+        }
+        ImportContent::Symlink(link) if meta.file_type().is_symlink() => {
+            if fs::read_link(&path).ok().as_ref() == Some(link) {
+                Ok(false)
+            } else if links_into(&path, root) {
+                Ok(true)
+            } else {
+                Err("HOME has a different symlink; left untouched".to_string())
+            }
+        }
+        _ => Err("HOME has something else in its place; left untouched".to_string()),
+    }
+}
+
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_symlink())
+}
+
+fn links_into(path: &Path, root: &Path) -> bool {
+    fs::canonicalize(path).is_ok_and(|target| target.starts_with(root))
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<fs::DirEntry>> {
+    let mut entries = fs::read_dir(dir)
+        .with_context(|| format!("read {}", dir.display()))?
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    Ok(entries)
+}
+
+fn origin(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+const CHEZMOI_DIR_ATTRS: &[&str] = &["remove_", "external_", "exact_", "private_", "readonly_"];
+const CHEZMOI_FILE_ATTRS: &[&str] = &[
+    "encrypted_",
+    "private_",
+    "readonly_",
+    "empty_",
+    "executable_",
+    "create_",
+    "modify_",
+    "remove_",
+    "run_",
+    "once_",
+    "onchange_",
+    "before_",
+    "after_",
+    "symlink_",
+];
+
+#[derive(Debug, PartialEq, Eq)]
+struct ChezmoiName {
+    name: String,
+    attrs: Vec<&'static str>,
+    template: bool,
+}
+
+impl ChezmoiName {
+    fn has(&self, attr: &str) -> bool {
+        self.attrs.contains(&attr)
+    }
+}
+
+fn chezmoi_name(raw: &str, attrs: &[&'static str], is_file: bool) -> ChezmoiName {
+    let mut rest = raw;
+    let mut found = Vec::new();
+    let mut literal = false;
+    'prefixes: loop {
+        if let Some(stripped) = rest.strip_prefix("literal_") {
+            rest = stripped;
+            literal = true;
+            break;
+        }
+        for attr in attrs {
+            if let Some(stripped) = rest.strip_prefix(attr) {
+                found.push(*attr);
+                rest = stripped;
+                continue 'prefixes;
+            }
+        }
+        break;
+    }
+    let mut name = match rest.strip_prefix("dot_") {
+        Some(stripped) if !literal => format!(".{stripped}"),
+        _ => rest.to_string(),
+    };
+    let mut template = false;
+    if is_file {
+        if found.contains(&"encrypted_") {
+            for suffix in [".age", ".asc"] {
+                if let Some(stripped) = name.strip_suffix(suffix) {
+                    name = stripped.to_string();
+                    break;
+                }
+            }
+        }
+        if let Some(stripped) = name.strip_suffix(".literal") {
+            name = stripped.to_string();
+        } else if let Some(stripped) = name.strip_suffix(".tmpl") {
+            name = stripped.to_string();
+            template = true;
+        }
+    }
+    ChezmoiName {
+        name,
+        attrs: found,
+        template,
+    }
+}
+
+fn chezmoi_mode(parsed: &ChezmoiName) -> u32 {
+    let mut mode = if parsed.has("executable_") {
+        0o755
+    } else {
+        0o644
+    };
+    if parsed.has("private_") {
+        mode &= 0o700;
+    }
+    if parsed.has("readonly_") {
+        mode &= !0o222;
+    }
+    mode
+}
+
+fn chezmoi_dir(
+    dir: &Path,
+    root: &Path,
+    target: &Path,
+    home: &Path,
+    plan: &mut ImportPlan,
+) -> Result<()> {
+    for entry in sorted_entries(dir)? {
+        let raw = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        let origin = origin(&path, root);
+        if raw.starts_with('.') {
+            if raw == ".chezmoiscripts" {
+                plan.skip(origin, "chezmoi scripts are not imported");
+            } else if raw.starts_with(".chezmoi")
+                && !matches!(raw.as_str(), ".chezmoiroot" | ".chezmoiversion")
+            {
+                plan.skip(origin, format!("{raw} has no hometree equivalent"));
+            }
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            let parsed = chezmoi_name(&raw, CHEZMOI_DIR_ATTRS, false);
+            if parsed.has("remove_") {
+                plan.skip(origin, "remove_ directories have no hometree equivalent");
+                continue;
+            }
+            if parsed.has("external_") {
+                plan.skip(origin, "external_ directories are not imported");
+                continue;
+            }
+            if parsed.has("exact_") {
+                plan.skip(
+                    origin,
+                    "exact_ (remove unlisted files) has no hometree equivalent; its files are still imported",
+                );
+            }
+            chezmoi_dir(&path, root, &target.join(&parsed.name), home, plan)?;
+            continue;
+        }
+
+        let parsed = chezmoi_name(&raw, CHEZMOI_FILE_ATTRS, true);
+        let rel = target.join(&parsed.name);
+        if parsed.has("run_") {
+            plan.skip(origin, "chezmoi scripts are not imported");
+            continue;
+        }
+        if parsed.has("modify_") || parsed.has("remove_") {
+            let attr = if parsed.has("modify_") {
+                "modify_"
+            } else {
+                "remove_"
+            };
+            plan.skip(
+                origin,
+                format!("{attr} entries have no hometree equivalent"),
+            );
+            continue;
+        }
+        let secret = parsed.has("encrypted_");
+        if parsed.template || secret {
+            let what = if secret { "encrypted" } else { "a template" };
+            if fs::symlink_metadata(home.join(&rel)).is_ok_and(|meta| meta.is_file()) {
+                let note = if secret {
+                    "decrypted copy taken from HOME"
+                } else {
+                    "rendered template taken from HOME"
+                };
+                let imported = plan.push(rel, origin, ImportContent::InPlace);
+                imported.secret = secret;
+                imported.note = Some(note.to_string());
+            } else {
+                plan.skip(
+                    origin,
+                    format!("{what} and not applied in HOME; run `chezmoi apply` first"),
+                );
+            }
+            continue;
+        }
+        if parsed.has("symlink_") {
+            let link = fs::read_to_string(&path)?;
+            plan.push(
+                rel,
+                origin,
+                ImportContent::Symlink(PathBuf::from(link.trim())),
+            );
+            continue;
+        }
+        if !parsed.has("empty_") && entry.metadata()?.len() == 0 {
+            plan.skip(
+                origin,
+                "empty file without empty_; chezmoi does not create it",
+            );
+            continue;
+        }
+        let mode = chezmoi_mode(&parsed);
+        plan.push(rel, origin, ImportContent::File(path)).mode = Some(mode);
+    }
+    Ok(())
+}
+
+fn yadm(repo: &Path, home: &Path, plan: &mut ImportPlan) -> Result<()> {
+    for (pattern, files) in yadm_encrypted(home)? {
+        if files.is_empty() {
+            plan.skip(format!("encrypt: {pattern}"), "matches nothing in HOME");
+        }
+        for file in files {
+            let entry = plan.push(file, format!("encrypt: {pattern}"), ImportContent::InPlace);
+            entry.secret = true;
+        }
+    }
+
+    // An empty pathspec covers the whole work tree.
+    let tracked = GitCliBackend::new()
+        .tracked_paths(repo, home, &[PathBuf::new()])
+        .context("list files tracked by yadm")?;
+    for file in tracked {
+        let rel = PathBuf::from(&file);
+        if rel.starts_with(".config/yadm") || rel.starts_with(".local/share/yadm") {
+            plan.skip(
+                file,
+                "yadm's own configuration and archive are not imported",
+            );
+            continue;
+        }
+        let Some((base, conditions)) = file.split_once("##") else {
+            plan.push(rel, file, ImportContent::InPlace);
+            continue;
+        };
+        let (base, conditions) = (PathBuf::from(base), conditions.to_string());
+        let template = conditions.split(',').any(|condition| {
+            condition == "t"
+                || condition == "template"
+                || condition.starts_with("t.")
+                || condition.starts_with("template.")
+        });
+        if template {
+            if fs::symlink_metadata(home.join(&base)).is_ok_and(|meta| meta.is_file()) {
+                let note = "rendered template taken from HOME".to_string();
+                plan.push(base, file, ImportContent::InPlace).note = Some(note);
+            } else {
+                plan.skip(file, "template not rendered in HOME; run `yadm alt` first");
+            }
+            continue;
+        }
+        let alternate = home.join(&rel);
+        let selected = fs::canonicalize(home.join(&base)).ok() == fs::canonicalize(&alternate).ok()
+            && is_symlink(&home.join(&base));
+        if selected {
+            let note = format!("yadm alternate ##{conditions}");
+            plan.push(base, file, ImportContent::File(alternate)).note = Some(note);
+        } else {
+            plan.skip(
+                file,
+                format!("alternate ##{conditions} is not selected on this system"),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Expand yadm's encrypt list into the files it covers, per pattern.
+fn yadm_encrypted(home: &Path) -> Result<Vec<(String, Vec<PathBuf>)>> {
+    let list = [".config/yadm/encrypt", ".yadm/encrypt"]
+        .iter()
+        .map(|rel| home.join(rel))
+        .find(|path| path.is_file());
+    let Some(list) = list else {
+        return Ok(Vec::new());
+    };
+    let contents = fs::read_to_string(&list).context("read yadm encrypt list")?;
+    let mut include = Vec::new();
+    let mut exclude = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.strip_prefix('!') {
+            Some(pattern) => exclude.push(yadm_glob(pattern)?),
+            None => include.push(line.to_string()),
+        }
+    }
+
+    let mut result = Vec::new();
+    for pattern in include {
+        let matcher = yadm_glob(&pattern)?;
+        let prefix: PathBuf = Path::new(&pattern)
+            .components()
+            .take_while(|c| {
+                !c.as_os_str()
+                    .to_string_lossy()
+                    .contains(['*', '?', '[', '{'])
+            })
+            .collect();
+        let covered = |rel: &Path, globs: &[GlobMatcher]| {
+            rel.ancestors()
+                .any(|a| !a.as_os_str().is_empty() && globs.iter().any(|g| g.is_match(a)))
+        };
+        let mut files = Vec::new();
+        for entry in WalkDir::new(home.join(&prefix)).follow_links(false) {
+            let Ok(entry) = entry else { continue };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Ok(rel) = entry.path().strip_prefix(home) else {
+                continue;
+            };
+            if covered(rel, std::slice::from_ref(&matcher)) && !covered(rel, &exclude) {
+                files.push(rel.to_path_buf());
+            }
+        }
+        result.push((pattern, files));
+    }
+    Ok(result)
+}
+
+fn yadm_glob(pattern: &str) -> Result<GlobMatcher> {
+    Ok(GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("invalid yadm encrypt pattern: {pattern}"))?
+        .compile_matcher())
+}
+
+fn stow(root: &Path, plan: &mut ImportPlan) -> Result<()> {
+    for entry in sorted_entries(root)? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if !entry.file_type()?.is_dir() {
+            plan.skip(origin(&path, root), "not inside a stow package");
+            continue;
+        }
+        if path.join(".stow-local-ignore").exists() {
+            plan.skip(
+                origin(&path.join(".stow-local-ignore"), root),
+                "package ignore lists are not honored; stow's default list applies",
+            );
+        }
+        stow_dir(&path, root, Path::new(""), true, plan)?;
+    }
+    Ok(())
+}
+
+fn stow_dir(
+    dir: &Path,
+    root: &Path,
+    target: &Path,
+    top: bool,
+    plan: &mut ImportPlan,
+) -> Result<()> {
+    for entry in sorted_entries(dir)? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if stow_ignored(&name, top) {
+            continue;
+        }
+        let path = entry.path();
+        let target_name = match name.strip_prefix("dot-") {
+            Some(stripped) if !stripped.is_empty() => format!(".{stripped}"),
+            _ => name,
+        };
+        let rel = target.join(target_name);
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            let link = fs::read_link(&path)?;
+            plan.push(rel, origin(&path, root), ImportContent::Symlink(link));
+        } else if file_type.is_dir() {
+            stow_dir(&path, root, &rel, false, plan)?;
+        } else {
+            plan.push(rel, origin(&path, root), ImportContent::File(path));
+        }
+    }
+    Ok(())
+}
+
+/// Stow's built-in ignore list.
+fn stow_ignored(name: &str, top: bool) -> bool {
+    matches!(
+        name,
+        ".git"
+            | ".gitignore"
+            | ".gitmodules"
+            | ".stow-local-ignore"
+            | ".cvsignore"
+            | "CVS"
+            | "RCS"
+            | ".hg"
+            | ".svn"
+            | "_darcs"
+    ) || name.starts_with(".#")
+        || name.ends_with('~')
+        || (name.len() > 1 && name.starts_with('#') && name.ends_with('#'))
+        || (top && (name.starts_with("README") || name.starts_with("LICENSE") || name == "COPYING"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn chezmoi_names_are_translated() {
+        let parsed = chezmoi_name("private_executable_dot_local", CHEZMOI_FILE_ATTRS, true);
+        assert_eq!(parsed.name, ".local");
+        assert_eq!(chezmoi_mode(&parsed), 0o700);
+
+        let parsed = chezmoi_name(
+            "encrypted_private_dot_netrc.tmpl.age",
+            CHEZMOI_FILE_ATTRS,
+            true,
+        );
+        assert_eq!(parsed.name, ".netrc");
+        assert!(parsed.template);
+        assert!(parsed.has("encrypted_"));
+
+        let parsed = chezmoi_name("literal_dot_keep.tmpl.literal", CHEZMOI_FILE_ATTRS, true);
+        assert_eq!(parsed.name, "dot_keep.tmpl");
+        assert!(!parsed.template);
+
+        let parsed = chezmoi_name("exact_private_dot_config", CHEZMOI_DIR_ATTRS, false);
+        assert_eq!(parsed.name, ".config");
+        assert_eq!(parsed.attrs, vec!["exact_", "private_"]);
+    }
+
+    #[test]
+    fn chezmoi_plan_reports_untranslatable_entries() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("chezmoi");
+        let home = temp.path().join("home");
+        fs::create_dir_all(source.join("dot_config/app")).unwrap();
+        fs::create_dir_all(&home).unwrap();
+        fs::write(source.join("dot_bashrc"), "alias ll='ls -l'\n").unwrap();
+        fs::write(
+            source.join("dot_config/app/executable_run.sh"),
+            "#!/bin/sh\n",
+        )
+        .unwrap();
+        fs::write(source.join("dot_gitconfig.tmpl"), "{{ .email }}\n").unwrap();
+        fs::write(source.join("run_once_install.sh"), "#!/bin/sh\n").unwrap();
+        fs::write(source.join(".chezmoiignore"), "README.md\n").unwrap();
+        fs::write(source.join("encrypted_dot_netrc.age"), "age").unwrap();
+        fs::write(home.join(".netrc"), "machine example.com\n").unwrap();
+
+        let plan = plan_import(ImportSource::Chezmoi, &source, &home).unwrap();
+        let targets: Vec<_> = plan
+            .entries
+            .iter()
+            .map(|entry| (entry.target.to_string_lossy().to_string(), entry.secret))
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                (".bashrc".to_string(), false),
+                (".config/app/run.sh".to_string(), false),
+                (".netrc".to_string(), true),
+            ]
+        );
+        assert_eq!(plan.entries[1].mode, Some(0o755));
+        let skipped: Vec<_> = plan
+            .untranslated
+            .iter()
+            .map(|u| u.origin.as_str())
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                ".chezmoiignore",
+                "dot_gitconfig.tmpl",
+                "run_once_install.sh"
+            ]
+        );
+    }
+
+    #[test]
+    fn stow_packages_unfold_symlinked_directories() {
+        let temp = TempDir::new().unwrap();
+        let stow_dir = temp.path().join("dotfiles");
+        let home = temp.path().join("home");
+        fs::create_dir_all(stow_dir.join("nvim/.config/nvim")).unwrap();
+        fs::create_dir_all(stow_dir.join("bash")).unwrap();
+        fs::create_dir_all(home.join(".config")).unwrap();
+        fs::write(stow_dir.join("nvim/.config/nvim/init.lua"), "-- nvim\n").unwrap();
+        fs::write(stow_dir.join("bash/dot-bashrc"), "export A=1\n").unwrap();
+        fs::write(stow_dir.join("bash/README.md"), "docs\n").unwrap();
+        symlink(
+            stow_dir.join("nvim/.config/nvim"),
+            home.join(".config/nvim"),
+        )
+        .unwrap();
+        fs::write(home.join(".bashrc"), "export A=2\n").unwrap();
+
+        let plan = plan_import(ImportSource::Stow, &stow_dir, &home).unwrap();
+        assert_eq!(plan.entries.len(), 1);
+        assert_eq!(
+            plan.entries[0].target,
+            PathBuf::from(".config/nvim/init.lua")
+        );
+        assert!(plan.entries[0].write);
+        assert_eq!(plan.untranslated.len(), 1);
+        assert!(plan.untranslated[0].reason.contains("different version"));
+
+        apply_entry(&home, &stow_dir, &plan.entries[0]).unwrap();
+        assert!(!is_symlink(&home.join(".config/nvim")));
+        assert_eq!(
+            fs::read_to_string(home.join(".config/nvim/init.lua")).unwrap(),
+            "-- nvim\n"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use std::path::Path;
+
+use globset::Glob;
+
+/// One file's section of a unified `git diff`, split into hunks so that each
+/// hunk can be applied to the index on its own.
+///
+/// Lines are kept as the exact bytes git printed, including the trailing
+/// `\n` and any `\r` before it, so CRLF and non-UTF-8 files apply cleanly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePatch {
+    /// `diff --git`, mode and `---`/`+++` lines preceding the first hunk.
+    pub header: Vec<Vec<u8>>,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// The `@@ -a,b +c,d @@` line.
+    pub header: Vec<u8>,
+    pub lines: Vec<Vec<u8>>,
+}
+
+impl FilePatch {
+    /// A patch holding only hunk `index`, suitable for `git apply --cached`.
+    pub fn hunk_patch(&self, index: usize) -> Option<Vec<u8>> {
+        let hunk = self.hunks.get(index)?;
+        let mut patch = Vec::new();
+        for line in self.header.iter().chain([&hunk.header]).chain(&hunk.lines) {
+            patch.extend_from_slice(line);
+            if !line.ends_with(b"\n") {
+                patch.push(b'\n');
+            }
+        }
+        Some(patch)
+    }
+
+    /// Binary and mode-only changes have no hunks to pick from.
+    pub fn is_binary(&self) -> bool {
+        self.header
+            .iter()
+            .any(|line| line.starts_with(b"Binary files "))
+    }
+}
+
+/// Split `git diff --no-color` output into per-file patches.
+pub fn parse_diff(output: &[u8]) -> Vec<FilePatch> {
+    let mut patches: Vec<FilePatch> = Vec::new();
+    for line in output.split_inclusive(|b| *b == b'\n') {
+        if line.starts_with(b"diff --git ") {
+            patches.push(FilePatch {
+                header: vec![line.to_vec()],
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+        let Some(patch) = patches.last_mut() else {
+            continue;
+        };
+        if line.starts_with(b"@@") {
+            patch.hunks.push(Hunk {
+                header: line.to_vec(),
+                lines: Vec::new(),
+            });
+        } else if let Some(hunk) = patch.hunks.last_mut() {
+            hunk.lines.push(line.to_vec());
+        } else {
+            patch.header.push(line.to_vec());
+        }
+    }
+    patches
+}
+
+/// A diff line for display, without its line ending.
+pub fn display_line(line: &[u8]) -> String {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    String::from_utf8_lossy(line).into_owned()
+}
+
+/// Help appended to the message file opened in the editor.
+pub const MESSAGE_HELP: &str = "\n\
+# Write the commit message above; a blank line separates subject and body.\n\
+# Lines starting with '#' are ignored; an empty message aborts the commit.\n";
+
+/// The commit message written in the editor, with comment lines and
+/// surrounding blank lines removed, as `git commit` does.
+pub fn clean_message(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_string()
+}
+
+/// The `manage.paths` entry a status path belongs to, for grouping; the first
+/// matching entry wins, as in the config file order.
+pub fn managed_root<'a>(rel: &Path, roots: &'a [String]) -> Option<&'a str> {
+    roots.iter().map(String::as_str).find(|root| {
+        let trimmed = root.trim_start_matches("./");
+        let base = trimmed.trim_end_matches("/**").trim_end_matches('/');
+        if base.contains(['*', '?', '[', '{']) {
+            return Glob::new(trimmed)
+                .map(|glob| glob.compile_matcher().is_match(rel))
+                .unwrap_or(false);
+        }
+        !base.is_empty() && rel.starts_with(base)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/.config/app/app.toml b/.config/app/app.toml
+index 1111111..2222222 100644
+--- a/.config/app/app.toml
++++ b/.config/app/app.toml
+@@ -1,3 +1,3 @@
+-a = 1
++a = 2
+ b = 1
+ c = 1
+@@ -10,2 +10,3 @@ [section]
+ x = 1
++y = 2
+ z = 1
+diff --git a/.config/app/logo.png b/.config/app/logo.png
+index 3333333..4444444 100644
+Binary files a/.config/app/logo.png and b/.config/app/logo.png differ
+";
+
+    #[test]
+    fn splits_files_and_hunks() {
+        let patches = parse_diff(DIFF.as_bytes());
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].header.len(), 4);
+        assert_eq!(patches[0].hunks.len(), 2);
+        assert_eq!(
+            patches[0].hunks[1]
+                .lines
+                .iter()
+                .map(|line| display_line(line))
+                .collect::<Vec<_>>(),
+            vec![" x = 1", "+y = 2", " z = 1"]
+        );
+        assert!(!patches[0].is_binary());
+        assert!(patches[1].hunks.is_empty());
+        assert!(patches[1].is_binary());
+    }
+
+    #[test]
+    fn hunk_patch_keeps_header_and_one_hunk() {
+        let patches = parse_diff(DIFF.as_bytes());
+        let patch = String::from_utf8(patches[0].hunk_patch(1).unwrap()).unwrap();
+        assert!(patch.starts_with("diff --git a/.config/app/app.toml"));
+        assert!(patch.contains("+++ b/.config/app/app.toml\n@@ -10,2 +10,3 @@"));
+        assert!(!patch.contains("+a = 2"));
+        assert!(patch.ends_with(" z = 1\n"));
+        assert_eq!(patches[0].hunk_patch(2), None);
+    }
+
+    #[test]
+    fn keeps_crlf_and_non_utf8_bytes() {
+        let diff: &[u8] = b"diff --git a/win.ini b/win.ini\n\
+index 1111111..2222222 100644\n\
+--- a/win.ini\n\
++++ b/win.ini\n\
+@@ -1,2 +1,2 @@\n\
+-name=caf\xe9\r\n\
++name=cafe\r\n\
+ [section]\r\n";
+        let patches = parse_diff(diff);
+        assert_eq!(patches[0].hunks[0].lines[0], b"-name=caf\xe9\r\n".to_vec());
+        assert_eq!(display_line(&patches[0].hunks[0].lines[1]), "+name=cafe");
+        assert_eq!(patches[0].hunk_patch(0).unwrap(), diff.to_vec());
+    }
+
+    #[test]
+    fn clean_message_drops_comments_and_keeps_the_body() {
+        let text = format!("\nsubject\n\nbody line  \n# a comment\n{MESSAGE_HELP}");
+        assert_eq!(clean_message(&text), "subject\n\nbody line");
+        assert_eq!(clean_message(MESSAGE_HELP), "");
+    }
+
+    #[test]
+    fn groups_by_first_matching_root() {
+        let roots = vec![
+            ".config/app/*.toml".to_string(),
+            ".config/".to_string(),
+            ".local/bin/**".to_string(),
+        ];
+        assert_eq!(
+            managed_root(Path::new(".config/app/app.toml"), &roots),
+            Some(".config/app/*.toml")
+        );
+        assert_eq!(
+            managed_root(Path::new(".config/app/state.json"), &roots),
+            Some(".config/")
+        );
+        assert_eq!(
+            managed_root(Path::new(".local/bin/tool"), &roots),
+            Some(".local/bin/**")
+        );
+        assert_eq!(managed_root(Path::new(".bashrc"), &roots), None);
+    }
+
+    #[test]
+    fn one_hunk_can_be_staged_and_unstaged() {
+        use hometree_core::git::{AddMode, DiffSpec, GitBackend, GitCliBackend};
+        use std::path::PathBuf;
+        use std::process::Command;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let git_dir = temp.path().join("repo.git");
+        let work_tree = temp.path().join("home");
+        std::fs::create_dir_all(&work_tree).unwrap();
+        let git = GitCliBackend::new();
+        git.init_repo(&git_dir).unwrap();
+
+        let files = [PathBuf::from("app.toml")];
+        let original: Vec<String> = (0..20).map(|i| format!("line {i}")).collect();
+        std::fs::write(work_tree.join(&files[0]), original.join("\n") + "\n").unwrap();
+        git.add(&git_dir, &work_tree, &files, AddMode::Paths)
+            .unwrap();
+        let committed = Command::new("git")
+            .args(["--git-dir", git_dir.to_str().unwrap()])
+            .args(["--work-tree", work_tree.to_str().unwrap()])
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+            .args(["commit", "-q", "-m", "base"])
+            .status()
+            .unwrap();
+        assert!(committed.success());
+
+        let mut edited = original.clone();
+        edited[1] = "first edit".to_string();
+        edited[18] = "second edit".to_string();
+        std::fs::write(work_tree.join(&files[0]), edited.join("\n") + "\n").unwrap();
+
+        let diff = |cached: bool| {
+            let spec = DiffSpec { cached, rev: None };
+            let output = git
+                .diff(&git_dir, &work_tree, &spec, &files, &["--color=never"])
+                .unwrap();
+            parse_diff(&output)
+        };
+        let unstaged = diff(false);
+        assert_eq!(unstaged[0].hunks.len(), 2);
+        let patch = unstaged[0].hunk_patch(1).unwrap();
+        git.apply_cached(&git_dir, &work_tree, &patch, false)
+            .unwrap();
+
+        let staged = diff(true);
+        assert_eq!(staged[0].hunks.len(), 1);
+        assert!(staged[0].hunks[0]
+            .lines
+            .contains(&b"+second edit\n".to_vec()));
+        assert_eq!(diff(false)[0].hunks.len(), 1);
+
+        let patch = staged[0].hunk_patch(0).unwrap();
+        git.apply_cached(&git_dir, &work_tree, &patch, true)
+            .unwrap();
+        assert!(diff(true).is_empty());
+
+        git.add(&git_dir, &work_tree, &files, AddMode::Paths)
+            .unwrap();
+        git.unstage(&git_dir, &work_tree, &files).unwrap();
+        assert!(diff(true).is_empty());
+    }
+
+    #[test]
+    fn crlf_hunk_stages_byte_for_byte() {
+        use hometree_core::git::{AddMode, DiffSpec, GitBackend, GitCliBackend};
+        use std::path::PathBuf;
+        use std::process::Command;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let git_dir = temp.path().join("repo.git");
+        let work_tree = temp.path().join("home");
+        std::fs::create_dir_all(&work_tree).unwrap();
+        let git = GitCliBackend::new();
+        git.init_repo(&git_dir).unwrap();
+        let git_cmd = |args: &[&str]| {
+            Command::new("git")
+                .args(["--git-dir", git_dir.to_str().unwrap()])
+                .args(["--work-tree", work_tree.to_str().unwrap()])
+                .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+        };
+
+        let files = [PathBuf::from("win.ini")];
+        let lines = |first: &[u8], last: &[u8]| {
+            let mut content = Vec::new();
+            for i in 0..20 {
+                match i {
+                    0 => content.extend_from_slice(first),
+                    19 => content.extend_from_slice(last),
+                    _ => content.extend_from_slice(format!("line {i}").as_bytes()),
+                }
+                content.extend_from_slice(b"\r\n");
+            }
+            content
+        };
+        std::fs::write(work_tree.join(&files[0]), lines(b"caf\xe9", b"end")).unwrap();
+        git.add(&git_dir, &work_tree, &files, AddMode::Paths)
+            .unwrap();
+        assert!(git_cmd(&["commit", "-q", "-m", "base"]).status.success());
+
+        std::fs::write(work_tree.join(&files[0]), lines(b"cafe", b"end\xff")).unwrap();
+        let spec = DiffSpec {
+            cached: false,
+            rev: None,
+        };
+        let output = git
+            .diff(&git_dir, &work_tree, &spec, &files, &["--color=never"])
+            .unwrap();
+        let patches = parse_diff(&output);
+        assert_eq!(patches[0].hunks.len(), 2);
+        let patch = patches[0].hunk_patch(0).unwrap();
+        git.apply_cached(&git_dir, &work_tree, &patch, false)
+            .unwrap();
+
+        let staged = git_cmd(&["show", ":win.ini"]).stdout;
+        assert_eq!(staged, lines(b"cafe", b"end"));
+    }
+}
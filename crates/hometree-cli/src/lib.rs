@@ -1,3 +1,10 @@
+pub mod autosnapshot;
 pub mod debounce;
+pub mod fswatch;
+pub mod import;
+pub mod interactive;
+pub mod metrics;
+pub mod systemd;
+pub mod throttle;
 pub mod track;
 pub mod watch;
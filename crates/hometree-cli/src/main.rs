@@ -1,15 +1,26 @@
 use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, ExitCode};
 
 use anyhow::{anyhow, Context, Result};
 use clap::{Parser, Subcommand, ValueEnum};
+use hometree_cli::import::{apply_entry, plan_import, ImportSource, Untranslated};
 use hometree_cli::track::decide_track;
 use hometree_cli::watch::root_to_pathspec;
-use hometree_core::git::{AddMode, FileChangeStatus, GitBackend, GitCliBackend};
+use hometree_core::config::SecretRule;
+use hometree_core::git::{AddMode, FileChangeStatus, FileStatus, GitBackend, GitCliBackend};
+use hometree_core::oplog::oplog_path;
+use hometree_core::output::{
+    BackupEntry, BackupListReport, LogReport, Output, RemoteListReport, SecretStatusEntry,
+    SecretStatusReport, StatusEntry, StatusReport, VerifyResult,
+};
+use hometree_core::secret_state::{secret_state_path, AuditFinding};
 use hometree_core::secrets::{AgeBackend, SecretsBackend, SecretsManager};
 use hometree_core::{
-    deploy_with_options, plan_deploy, read_generations, rollback, verify, Config, ManagedSet, Paths,
+    audit_secrets, capture_manifest, config_changes, deploy_with_options, plan_deploy,
+    read_generations, read_operations, read_secret_state, render_message, repair, rollback,
+    undo_last, verify, write_manifest, write_secret_state, Config, Effect, Effects, ManagedSet,
+    MessageContext, OperationRecorder, Paths, Plaintext, RepairOptions, RepairReport, SecretState,
 };
 use std::time::Duration;
 use tracing::info;
@@ -17,6 +28,12 @@ use tracing_subscriber::EnvFilter;
 use walkdir::WalkDir;
 
 mod daemon;
+mod diff;
+mod doctor;
+mod export;
+mod show;
+mod ui;
+mod why;
 
 #[derive(Parser)]
 #[command(name = "hometree", version, about = "Manage a versioned home tree")]
@@ -27,6 +44,12 @@ struct Cli {
     /// Override the XDG base directory root (config/data/state/cache)
     #[arg(long, env = "HOMETREE_XDG_ROOT")]
     xdg_root: Option<PathBuf>,
+    /// Output format for command results
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// Report what a mutating command would change instead of changing it
+    #[arg(long, global = true)]
+    dry_run: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -44,6 +67,9 @@ enum Commands {
         /// Clone from existing remote repository
         #[arg(long)]
         from: Option<String>,
+        /// Restore from an archive written by `hometree export`, then deploy it
+        #[arg(long, value_name = "FILE", conflicts_with = "from")]
+        from_bundle: Option<PathBuf>,
         /// Automatically deploy after cloning from remote
         #[arg(long, requires = "from")]
         deploy: bool,
@@ -63,6 +89,29 @@ enum Commands {
         #[arg(required = true)]
         paths: Vec<PathBuf>,
     },
+    /// Revert the last track, untrack or secret add operations
+    Undo {
+        /// Number of operations to revert
+        #[arg(default_value_t = 1, conflicts_with = "list")]
+        count: usize,
+        /// List recorded operations, newest first, instead of reverting
+        #[arg(long)]
+        list: bool,
+        /// Revert even if a recorded file was edited since the operation
+        #[arg(long)]
+        force: bool,
+    },
+    /// Import files managed by chezmoi, yadm or GNU stow and snapshot them
+    Import {
+        /// Tool that manages the files today
+        #[arg(long = "from", value_enum)]
+        from: ImportFrom,
+        /// chezmoi source dir, yadm repo or stow dir (defaults to the tool's usual location)
+        source: Option<PathBuf>,
+        /// Commit message for the import snapshot
+        #[arg(short = 'm', long = "message")]
+        message: Option<String>,
+    },
     /// Create a snapshot commit from staged changes
     Snapshot {
         /// Commit message (auto-generated if not provided)
@@ -71,6 +120,9 @@ enum Commands {
         /// Use the auto message template from config
         #[arg(long)]
         auto: bool,
+        /// Pick what to commit in the terminal UI (`-m` pre-fills the message)
+        #[arg(short, long, conflicts_with = "auto")]
+        interactive: bool,
     },
     /// Show commit history
     Log {
@@ -78,6 +130,61 @@ enum Commands {
         #[arg(long)]
         limit: Option<usize>,
     },
+    /// Show changes to managed files (secret plaintext is never shown)
+    Diff {
+        /// Compare the index with HEAD (or <rev>) instead of the work tree
+        #[arg(long, alias = "cached")]
+        staged: bool,
+        /// Revision, or <rev>..<rev> range, to compare against
+        rev: Option<String>,
+        /// Limit to these paths (relative to HOME)
+        #[arg(last = true)]
+        paths: Vec<PathBuf>,
+        /// Show a diffstat instead of the patch
+        #[arg(long, conflicts_with = "name_only")]
+        stat: bool,
+        /// Show only the names of changed files
+        #[arg(long)]
+        name_only: bool,
+        /// When to color the output
+        #[arg(long, value_enum, default_value_t = ColorWhen::Auto)]
+        color: ColorWhen,
+    },
+    /// Show a commit: metadata, changed managed files and their diff
+    Show {
+        /// Commit, branch, or tag (default: HEAD)
+        #[arg(default_value = "HEAD")]
+        rev: String,
+        /// Show a diffstat instead of the patch
+        #[arg(long)]
+        stat: bool,
+        /// When to color the output
+        #[arg(long, value_enum, default_value_t = ColorWhen::Auto)]
+        color: ColorWhen,
+    },
+    /// Print a file as it was at a revision
+    Cat {
+        /// Commit, branch, or tag
+        rev: String,
+        /// Path (relative to HOME or absolute under HOME)
+        path: PathBuf,
+        /// Decrypt the sidecar when the path is a secret and print its plaintext
+        #[arg(long)]
+        decrypt: bool,
+    },
+    /// Review, stage and commit changes in a terminal UI
+    Ui,
+    /// Check the environment and repository for common problems
+    Doctor {
+        /// Emit JSON output
+        #[arg(long)]
+        json: bool,
+    },
+    /// Explain why a path is or isn't managed, staged, or auto-added
+    Why {
+        /// Path to explain (relative to HOME or absolute under HOME)
+        path: PathBuf,
+    },
     /// Run and manage the daemon (alias: watch)
     #[command(alias = "watch")]
     Daemon {
@@ -130,6 +237,12 @@ enum Commands {
         /// Show plaintext secret paths in output
         #[arg(long)]
         show_paths: bool,
+        /// Repair reported drift (backs up what it overwrites)
+        #[arg(long)]
+        fix: bool,
+        /// With --fix and --strict, delete unexpected files
+        #[arg(long, requires_all = ["fix", "strict"])]
+        remove_unexpected: bool,
     },
     /// Manage secret sidecar files
     Secret {
@@ -155,6 +268,15 @@ enum Commands {
         #[command(subcommand)]
         command: BackupCommand,
     },
+    /// Write a self-contained archive of a commit for `init --from-bundle`
+    Export {
+        /// Commit, branch, or tag to export (default: HEAD)
+        #[arg(default_value = "HEAD")]
+        rev: String,
+        /// Archive file to write
+        #[arg(short, long)]
+        output: PathBuf,
+    },
 }
 
 #[derive(Subcommand)]
@@ -167,6 +289,26 @@ enum PlanCommand {
     },
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ColorWhen {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ImportFrom {
+    Chezmoi,
+    Yadm,
+    Stow,
+}
+
 #[derive(Clone, Copy, Debug, ValueEnum)]
 enum SecretsVerifyArg {
     Skip,
@@ -194,9 +336,28 @@ enum SecretCommand {
         /// Show plaintext paths in output
         #[arg(long)]
         show_paths: bool,
+        /// Emit JSON output including rotation metadata
+        #[arg(long)]
+        json: bool,
+    },
+    /// Re-encrypt secrets whose recipient set changed
+    Rekey {
+        /// Re-encrypt every secret even if its recipients are unchanged
+        #[arg(long)]
+        force: bool,
+    },
+    /// Report stale secrets and sidecars encrypted to outdated recipients
+    Audit {
+        /// Override `secrets.stale_after_days`
+        #[arg(long)]
+        max_age_days: Option<u64>,
+        /// Emit JSON output
+        #[arg(long)]
+        json: bool,
+        /// Show plaintext paths in output
+        #[arg(long)]
+        show_paths: bool,
     },
-    /// Re-encrypt secrets with current recipients
-    Rekey,
 }
 
 #[derive(Subcommand)]
@@ -250,24 +411,93 @@ enum BackupCommand {
     },
 }
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) if err.is::<ProblemsReported>() => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Returned by commands whose output already lists the problems they found
+/// (`doctor`, `secret audit`), so that `main` exits 1 without printing an
+/// error of its own.
+#[derive(Debug)]
+pub(crate) struct ProblemsReported;
+
+impl std::fmt::Display for ProblemsReported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("problems reported")
+    }
+}
+
+impl std::error::Error for ProblemsReported {}
+
+fn run() -> Result<()> {
     init_tracing();
     let Cli {
         command,
         home_root,
         xdg_root,
+        format,
+        dry_run,
     } = Cli::parse();
     let overrides = Overrides {
         home_root,
         xdg_root,
     };
+    if format == OutputFormat::Json && !supports_json(&command) {
+        return Err(anyhow!("--format json is not supported by this command"));
+    }
+    if dry_run {
+        if !supports_dry_run(&command) {
+            return Err(anyhow!("--dry-run is not supported by this command"));
+        }
+        if format == OutputFormat::Json {
+            return Err(anyhow!("--dry-run reports are text only"));
+        }
+    }
+    let effects = Effects::new(dry_run);
     match command {
-        Commands::Init { from, deploy } => run_init(&overrides, from, deploy),
-        Commands::Status => run_status(&overrides),
-        Commands::Track { paths, force } => run_track(&overrides, paths, force),
-        Commands::Untrack { paths } => run_untrack(&overrides, paths),
-        Commands::Snapshot { message, auto } => run_snapshot(&overrides, message, auto),
-        Commands::Log { limit } => run_log(&overrides, limit),
+        Commands::Init {
+            from,
+            from_bundle,
+            deploy,
+        } => run_init(&overrides, from, from_bundle, deploy, &effects),
+        Commands::Status => run_status(&overrides, format),
+        Commands::Track { paths, force } => run_track(&overrides, paths, force, &effects),
+        Commands::Untrack { paths } => run_untrack(&overrides, paths, &effects),
+        Commands::Undo { count, list, force } => run_undo(&overrides, count, list, force),
+        Commands::Import {
+            from,
+            source,
+            message,
+        } => run_import(&overrides, from, source, message, &effects),
+        Commands::Snapshot {
+            message,
+            auto: _,
+            interactive: true,
+        } => ui::run_ui(&overrides, message),
+        Commands::Snapshot { message, auto, .. } => {
+            run_snapshot(&overrides, message, auto, &effects)
+        }
+        Commands::Show { rev, stat, color } => show::run_show(&overrides, rev, stat, color),
+        Commands::Cat { rev, path, decrypt } => show::run_cat(&overrides, rev, path, decrypt),
+        Commands::Ui => ui::run_ui(&overrides, None),
+        Commands::Log { limit } => run_log(&overrides, limit, format),
+        Commands::Diff {
+            staged,
+            rev,
+            paths,
+            stat,
+            name_only,
+            color,
+        } => diff::run_diff(&overrides, staged, rev, paths, stat, name_only, color),
+        Commands::Doctor { json } => doctor::run_doctor(&overrides, json, format),
+        Commands::Why { path } => why::run_why(&overrides, path),
         Commands::Daemon {
             command,
             foreground,
@@ -276,21 +506,98 @@ fn main() -> Result<()> {
             target,
             no_secrets,
             no_backup,
-        } => run_deploy(&overrides, target, no_secrets, no_backup),
-        Commands::Rollback { to, steps } => run_rollback(&overrides, to, steps),
-        Commands::Plan { command } => run_plan(&overrides, command),
+        } => run_deploy(&overrides, target, no_secrets, no_backup, format, &effects),
+        Commands::Rollback { to, steps } => run_rollback(&overrides, to, steps, format),
+        Commands::Plan { command } => run_plan(&overrides, command, format),
         Commands::Verify {
             rev,
             strict,
             with_secrets,
             json,
             show_paths,
-        } => run_verify(&overrides, rev, strict, with_secrets, json, show_paths),
-        Commands::Secret { command } => run_secret(&overrides, command),
-        Commands::Remote { command } => run_remote(&overrides, command),
-        Commands::Sync { remote, no_deploy } => run_sync(&overrides, remote, no_deploy),
-        Commands::Backup { command } => run_backup(&overrides, command),
+            fix,
+            remove_unexpected,
+        } => run_verify(
+            &overrides,
+            rev,
+            strict,
+            with_secrets,
+            json,
+            format,
+            show_paths,
+            fix.then_some(RepairOptions { remove_unexpected }),
+        ),
+        Commands::Secret { command } => run_secret(&overrides, command, format, &effects),
+        Commands::Remote { command } => run_remote(&overrides, command, format),
+        Commands::Sync { remote, no_deploy } => run_sync(&overrides, remote, no_deploy, &effects),
+        Commands::Backup { command } => run_backup(&overrides, command, format, &effects),
+        Commands::Export { rev, output } => export::run_export(&overrides, rev, output),
+    }?;
+    if dry_run {
+        for effect in effects.take() {
+            println!("would {effect}");
+        }
+        println!("dry run; nothing changed");
     }
+    Ok(())
+}
+
+/// Mutating commands that route their side effects through [`Effects`].
+fn supports_dry_run(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Init {
+            from_bundle: None,
+            ..
+        } | Commands::Track { .. }
+            | Commands::Untrack { .. }
+            | Commands::Import { .. }
+            | Commands::Snapshot {
+                interactive: false,
+                ..
+            }
+            | Commands::Deploy { .. }
+            | Commands::Sync { .. }
+            | Commands::Secret {
+                command: SecretCommand::Add { .. }
+                    | SecretCommand::Refresh { .. }
+                    | SecretCommand::Rekey { .. },
+            }
+            | Commands::Backup {
+                command: BackupCommand::Restore { .. },
+            }
+    )
+}
+
+/// Commands with a structured result; see `hometree_core::output`.
+fn supports_json(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Status
+            | Commands::Log { .. }
+            | Commands::Doctor { .. }
+            | Commands::Deploy { .. }
+            | Commands::Rollback { .. }
+            | Commands::Plan { .. }
+            | Commands::Verify { .. }
+            | Commands::Secret {
+                command: SecretCommand::Status { .. } | SecretCommand::Audit { .. },
+            }
+            | Commands::Remote {
+                command: RemoteCommand::List,
+            }
+            | Commands::Backup {
+                command: BackupCommand::List,
+            }
+    )
+}
+
+/// Print `result` wrapped in the versioned `--format json` envelope.
+pub(crate) fn print_json<T: serde::Serialize>(command: &str, result: T) -> Result<()> {
+    let output =
+        serde_json::to_string_pretty(&Output::new(command, result)).context("serialize json")?;
+    println!("{output}");
+    Ok(())
 }
 
 #[derive(Subcommand)]
@@ -302,20 +609,34 @@ enum DaemonCommand {
         foreground: bool,
     },
     /// Compatibility alias for `run --foreground`
-    #[command(alias = "foreground")]
+    #[command(name = "foreground")]
     Foreground,
     /// Install a systemd user unit
-    InstallSystemd,
-    /// Uninstall the systemd user unit
+    #[command(alias = "install")]
+    InstallSystemd {
+        /// Also install hometree.socket so the daemon starts on first use
+        #[arg(long)]
+        socket: bool,
+        /// Leave out the sandboxing directives
+        #[arg(long)]
+        no_harden: bool,
+    },
+    /// Uninstall the systemd user units
     UninstallSystemd,
-    /// Start the systemd user unit
+    /// Start the systemd user unit (the socket unit when installed)
     Start,
-    /// Stop the systemd user unit
+    /// Stop the systemd user units
     Stop,
     /// Restart the systemd user unit
     Restart,
     /// Show daemon status
-    Status,
+    Status {
+        /// Print the full status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print daemon metrics in the Prometheus text format
+    Metrics,
     /// Reload daemon config
     Reload,
     /// Pause staging (inhibit)
@@ -331,6 +652,8 @@ enum DaemonCommand {
     Resume,
     /// Flush staged changes immediately
     Flush,
+    /// Stream daemon events as newline-delimited JSON
+    Events,
 }
 
 fn init_tracing() {
@@ -338,31 +661,59 @@ fn init_tracing() {
     tracing_subscriber::fmt().with_env_filter(filter).init();
 }
 
-fn run_init(overrides: &Overrides, from: Option<String>, auto_deploy: bool) -> Result<()> {
+fn run_init(
+    overrides: &Overrides,
+    from: Option<String>,
+    from_bundle: Option<PathBuf>,
+    auto_deploy: bool,
+    effects: &Effects,
+) -> Result<()> {
     let paths = load_paths(overrides).context("resolve XDG paths")?;
 
-    std::fs::create_dir_all(paths.config_dir()).context("create config dir")?;
-    std::fs::create_dir_all(paths.data_dir()).context("create data dir")?;
-    std::fs::create_dir_all(paths.state_dir()).context("create state dir")?;
+    for dir in [paths.config_dir(), paths.data_dir(), paths.state_dir()] {
+        if !dir.exists()
+            && effects.perform(Effect::CreateDir {
+                path: dir.to_path_buf(),
+            })
+        {
+            std::fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+        }
+    }
 
     let repo_dir = paths.repo_dir();
     let git = GitCliBackend::new();
+    let mut restored = None;
 
-    if let Some(url) = from {
+    if let Some(file) = from_bundle {
+        restored = Some(export::restore_export(&paths, &git, &file)?);
+    } else if let Some(url) = from {
         if repo_dir.exists() {
             return Err(anyhow!(
                 "repo already exists at {}; remove it first to clone from remote",
                 repo_dir.display()
             ));
         }
-        clone_bare_repo(&url, &repo_dir).context("clone bare repo")?;
-        info!(path = %repo_dir.display(), "cloned bare repo from {}", url);
+        if effects.perform(Effect::Clone {
+            url: url.clone(),
+            path: repo_dir.clone(),
+        }) {
+            clone_bare_repo(&url, &repo_dir).context("clone bare repo")?;
+            info!(path = %repo_dir.display(), "cloned bare repo from {}", url);
+        }
 
-        git.reset(&repo_dir, paths.home_dir(), "HEAD")
-            .context("sync index with HEAD after clone")?;
+        if effects.perform(Effect::ResetIndex {
+            rev: "HEAD".to_string(),
+        }) {
+            git.reset(&repo_dir, paths.home_dir(), "HEAD")
+                .context("sync index with HEAD after clone")?;
+        }
 
         let config_path = paths.config_file();
-        if !config_path.exists() {
+        if !config_path.exists()
+            && effects.perform(Effect::WriteFile {
+                path: config_path.clone(),
+            })
+        {
             if let Ok(config_bytes) = git.show_blob(
                 &repo_dir,
                 paths.home_dir(),
@@ -382,23 +733,33 @@ fn run_init(overrides: &Overrides, from: Option<String>, auto_deploy: bool) -> R
         }
     } else {
         let config_path = paths.config_file();
-        if !config_path.exists() {
+        if config_path.exists() {
+            info!(path = %config_path.display(), "config exists; leaving unchanged");
+        } else if effects.perform(Effect::WriteFile {
+            path: config_path.clone(),
+        }) {
             let cfg = Config::default_with_paths(&paths);
             cfg.write_to(&config_path).context("write default config")?;
             info!(path = %config_path.display(), "wrote config");
-        } else {
-            info!(path = %config_path.display(), "config exists; leaving unchanged");
         }
 
-        if !repo_dir.exists() {
+        if repo_dir.exists() {
+            info!(path = %repo_dir.display(), "repo exists; leaving unchanged");
+        } else if effects.perform(Effect::InitRepo {
+            path: repo_dir.clone(),
+        }) {
             init_bare_repo(&repo_dir).context("init bare repo")?;
             info!(path = %repo_dir.display(), "initialized bare repo");
-        } else {
-            info!(path = %repo_dir.display(), "repo exists; leaving unchanged");
         }
     }
 
-    if git.is_repository(&repo_dir) {
+    // In a dry run the repository may not exist yet; report the setting anyway.
+    if (effects.dry_run() || git.is_repository(&repo_dir))
+        && effects.perform(Effect::GitConfig {
+            key: "status.showUntrackedFiles".to_string(),
+            value: "no".to_string(),
+        })
+    {
         let _ = git.config_set(
             &repo_dir,
             paths.home_dir(),
@@ -407,8 +768,19 @@ fn run_init(overrides: &Overrides, from: Option<String>, auto_deploy: bool) -> R
         );
     }
 
+    if effects.dry_run() {
+        if auto_deploy {
+            effects.perform(Effect::Deploy {
+                rev: "HEAD".to_string(),
+            });
+        }
+        return Ok(());
+    }
     println!("hometree initialized.");
 
+    if let Some(manifest) = restored {
+        return export::deploy_restored(&paths, &git, &manifest);
+    }
     if auto_deploy {
         println!("deploying HEAD...");
         let config = Config::load_from(&paths.config_file()).context("load config")?;
@@ -426,19 +798,55 @@ fn run_init(overrides: &Overrides, from: Option<String>, auto_deploy: bool) -> R
     Ok(())
 }
 
-fn run_status(overrides: &Overrides) -> Result<()> {
+fn run_status(overrides: &Overrides, format: OutputFormat) -> Result<()> {
     let (paths, config) = load_config(overrides)?;
-    let managed =
-        ManagedSet::from_config(&config, paths.home_dir()).context("build managed set")?;
+    let filtered = managed_statuses(&paths, &config)?;
+
+    if format == OutputFormat::Json {
+        let entries = filtered
+            .into_iter()
+            .map(|status| StatusEntry {
+                index: status.index_status.to_string(),
+                worktree: status.worktree_status.to_string(),
+                path: status.path,
+            })
+            .collect::<Vec<_>>();
+        return print_json(
+            "status",
+            StatusReport {
+                clean: entries.is_empty(),
+                entries,
+            },
+        );
+    }
+
+    if filtered.is_empty() {
+        println!("clean");
+        return Ok(());
+    }
+
+    for status in filtered {
+        println!(
+            "{}{} {}",
+            status.index_status, status.worktree_status, status.path
+        );
+    }
+
+    Ok(())
+}
+
+/// Porcelain status of managed paths, sorted by path, without secret plaintext.
+fn managed_statuses(paths: &Paths, config: &Config) -> Result<Vec<FileStatus>> {
+    let managed = ManagedSet::from_config(config, paths.home_dir()).context("build managed set")?;
     let secrets = SecretsManager::from_config(&config.secrets);
     let git = GitCliBackend::new();
-    let paths = status_paths(&config);
-    let include_untracked = !paths.is_empty();
+    let pathspecs = status_paths(config);
+    let include_untracked = !pathspecs.is_empty();
     let statuses = git
         .status_porcelain(
             &config.repo.git_dir,
             &config.repo.work_tree,
-            &paths,
+            &pathspecs,
             include_untracked,
         )
         .context("git status")?;
@@ -450,24 +858,17 @@ fn run_status(overrides: &Overrides) -> Result<()> {
         .filter(|status| !secrets.is_secret_plaintext(Path::new(&status.path)))
         .collect();
     filtered.sort_by(|a, b| a.path.cmp(&b.path));
-
-    if filtered.is_empty() {
-        println!("clean");
-        return Ok(());
-    }
-
-    for status in filtered {
-        println!(
-            "{}{} {}",
-            status.index_status, status.worktree_status, status.path
-        );
-    }
-
-    Ok(())
+    Ok(filtered)
 }
 
-fn run_track(overrides: &Overrides, paths: Vec<PathBuf>, force: bool) -> Result<()> {
+fn run_track(
+    overrides: &Overrides,
+    paths: Vec<PathBuf>,
+    force: bool,
+    effects: &Effects,
+) -> Result<()> {
     let (paths_ctx, mut config) = load_config(overrides)?;
+    let before = config.clone();
     let managed =
         ManagedSet::from_config(&config, paths_ctx.home_dir()).context("build managed set")?;
     let home_dir = paths_ctx.home_dir();
@@ -475,6 +876,7 @@ fn run_track(overrides: &Overrides, paths: Vec<PathBuf>, force: bool) -> Result<
 
     let mut to_stage: Vec<PathBuf> = Vec::new();
     let mut paths_changed = false;
+    let command = operation_command("track", &paths);
 
     for input in paths {
         if secrets.enabled() {
@@ -498,36 +900,213 @@ fn run_track(overrides: &Overrides, paths: Vec<PathBuf>, force: bool) -> Result<
         to_stage.push(decision.rel_path);
     }
 
+    let git = GitCliBackend::new();
+    let recorder = begin_operation(&paths_ctx, &config, &git, command, &[], to_stage.clone())?;
     if paths_changed {
-        let config_path = paths_ctx.config_file();
+        write_config(&paths_ctx, &before, &config, effects)?;
+    }
+
+    if effects.perform(Effect::Stage {
+        paths: to_stage.clone(),
+    }) {
+        with_lock(&paths_ctx, || {
+            git.add(
+                &config.repo.git_dir,
+                &config.repo.work_tree,
+                &to_stage,
+                AddMode::Paths,
+            )
+            .context("git add")
+        })?;
+    }
+    finish_operation(recorder, &paths_ctx, effects)?;
+
+    if !effects.dry_run() {
+        println!("tracked {} path(s)", to_stage.len());
+    }
+    Ok(())
+}
+
+fn run_import(
+    overrides: &Overrides,
+    from: ImportFrom,
+    source: Option<PathBuf>,
+    message: Option<String>,
+    effects: &Effects,
+) -> Result<()> {
+    let dry_run = effects.dry_run();
+    let (paths, mut config) = load_config(overrides)?;
+    let home = paths.home_dir().to_path_buf();
+    let kind = match from {
+        ImportFrom::Chezmoi => ImportSource::Chezmoi,
+        ImportFrom::Yadm => ImportSource::Yadm,
+        ImportFrom::Stow => ImportSource::Stow,
+    };
+    let source = match source {
+        Some(source) => source,
+        None => match kind {
+            ImportSource::Chezmoi => home.join(".local/share/chezmoi"),
+            ImportSource::Yadm => [".local/share/yadm/repo.git", ".config/yadm/repo.git"]
+                .iter()
+                .map(|rel| home.join(rel))
+                .find(|path| path.exists())
+                .unwrap_or_else(|| home.join(".local/share/yadm/repo.git")),
+            ImportSource::Stow => return Err(anyhow!("--from stow needs the stow directory")),
+        },
+    };
+    let mut plan = plan_import(kind, &source, &home)?;
+
+    // hometree's own rules still apply: ignored paths and existing secrets are left alone.
+    let managed = ManagedSet::from_config(&config, &home).context("build managed set")?;
+    let secrets = SecretsManager::from_config(&config.secrets);
+    let mut entries = Vec::new();
+    let mut new_paths = Vec::new();
+    for entry in std::mem::take(&mut plan.entries) {
+        let reason = if secrets.is_secret_plaintext(&entry.target) {
+            Some("already a hometree secret")
+        } else if entry.secret {
+            None
+        } else {
+            match decide_track(&entry.target, &home, &managed, false) {
+                Ok(decision) => {
+                    if decision.add_to_paths {
+                        new_paths.push(decision.rel_path.to_string_lossy().to_string());
+                    }
+                    None
+                }
+                Err(_) => Some("ignored or denylisted by hometree; use `track --force` afterwards"),
+            }
+        };
+        match reason {
+            Some(reason) => plan.untranslated.push(Untranslated {
+                reason: format!("{}: {reason}", entry.target.display()),
+                origin: entry.origin,
+            }),
+            None => entries.push(entry),
+        }
+    }
+
+    for entry in &entries {
+        let verb = if entry.secret { "secret" } else { "add" };
+        let mut line = format!("{verb:<6} {} <- {}", entry.target.display(), entry.origin);
+        if let Some(note) = &entry.note {
+            line.push_str(&format!(" ({note})"));
+        }
+        println!("{line}");
+    }
+    for item in &plan.untranslated {
+        println!("skip   {}: {}", item.origin, item.reason);
+    }
+    let secret_rels: Vec<PathBuf> = entries
+        .iter()
+        .filter(|entry| entry.secret)
+        .map(|entry| entry.target.clone())
+        .collect();
+    println!(
+        "{} path(s) to import ({} secret), {} item(s) not translated",
+        entries.len(),
+        secret_rels.len(),
+        plan.untranslated.len()
+    );
+
+    if !secret_rels.is_empty() {
+        let ready = AgeBackend::from_config(&config.secrets, &config.repo.work_tree)
+            .and_then(|backend| backend.ensure_recipients());
+        if let Err(err) = ready {
+            let message = format!(
+                "importing secrets needs age recipients in [secrets] ({err}); \
+                 configure them or remove the secrets from the source"
+            );
+            if !dry_run {
+                return Err(anyhow!(message));
+            }
+            println!("note: {message}");
+        }
+    }
+    if dry_run {
+        return Ok(());
+    }
+    if entries.is_empty() {
+        println!("nothing to import");
+        return Ok(());
+    }
+
+    {
+        let _inhibit = daemon::DaemonInhibitGuard::new(&paths, "import", Duration::from_secs(300))?;
+        for entry in &entries {
+            apply_entry(&home, &source, entry)
+                .with_context(|| format!("import {}", entry.target.display()))?;
+        }
+
+        for path in new_paths {
+            if !config.manage.paths.contains(&path) {
+                config.manage.paths.push(path);
+            }
+        }
+        if !secret_rels.is_empty() {
+            config.secrets.enabled = true;
+            for rel in &secret_rels {
+                add_secret_rule(&mut config, &rel.to_string_lossy());
+            }
+        }
+        let config_path = paths.config_file();
         config
             .write_to(&config_path)
             .with_context(|| format!("write config to {}", config_path.display()))?;
+
+        let mut to_stage: Vec<PathBuf> = entries
+            .iter()
+            .filter(|entry| !entry.secret)
+            .map(|entry| entry.target.clone())
+            .collect();
+        to_stage.extend(encrypt_new_secrets(&paths, &config, &secret_rels, effects)?);
+        let git = GitCliBackend::new();
+        with_lock(&paths, || {
+            git.add(
+                &config.repo.git_dir,
+                &config.repo.work_tree,
+                &to_stage,
+                AddMode::Paths,
+            )
+            .context("git add")
+        })?;
+        let staged = git
+            .staged_paths(&config.repo.git_dir, &config.repo.work_tree)
+            .context("list staged paths")?;
+        if staged.is_empty() {
+            println!("everything was already committed");
+            return Ok(());
+        }
     }
 
-    let git = GitCliBackend::new();
-    with_lock(&paths_ctx, || {
-        git.add(
-            &config.repo.git_dir,
-            &config.repo.work_tree,
-            &to_stage,
-            AddMode::Paths,
-        )
-        .context("git add")
-    })?;
+    let message = message.unwrap_or_else(|| format!("import from {}", kind.name()));
+    run_snapshot(overrides, Some(message), false, effects)
+}
 
-    println!("tracked {} path(s)", to_stage.len());
+fn run_untrack(overrides: &Overrides, paths: Vec<PathBuf>, effects: &Effects) -> Result<()> {
+    let (paths_ctx, mut config) = load_config(overrides)?;
+    let count = untrack_paths(&paths_ctx, &mut config, paths, effects)?;
+    if !effects.dry_run() {
+        println!("untracked {count} path(s)");
+    }
     Ok(())
 }
 
-fn run_untrack(overrides: &Overrides, paths: Vec<PathBuf>) -> Result<()> {
-    let (paths_ctx, mut config) = load_config(overrides)?;
+/// Drop `paths` from the managed set in `config.toml` and from the index.
+fn untrack_paths(
+    paths_ctx: &Paths,
+    config: &mut Config,
+    paths: Vec<PathBuf>,
+    effects: &Effects,
+) -> Result<usize> {
+    let before = config.clone();
     let managed =
-        ManagedSet::from_config(&config, paths_ctx.home_dir()).context("build managed set")?;
+        ManagedSet::from_config(config, paths_ctx.home_dir()).context("build managed set")?;
     let secrets = SecretsManager::from_config(&config.secrets);
     let home_dir = paths_ctx.home_dir();
     let mut changed = false;
     let mut to_unstage: Vec<PathBuf> = Vec::new();
+    let command = operation_command("untrack", &paths);
 
     for input in paths {
         let rel = resolve_rel_path(home_dir, &input)?;
@@ -551,39 +1130,172 @@ fn run_untrack(overrides: &Overrides, paths: Vec<PathBuf>) -> Result<()> {
         to_unstage.push(rel);
     }
 
+    let git = GitCliBackend::new();
+    let recorder = begin_operation(paths_ctx, config, &git, command, &[], to_unstage.clone())?;
     if changed {
-        let config_path = paths_ctx.config_file();
-        config
-            .write_to(&config_path)
-            .with_context(|| format!("write config to {}", config_path.display()))?;
+        write_config(paths_ctx, &before, config, effects)?;
     }
 
-    if !to_unstage.is_empty() {
-        with_lock(&paths_ctx, || {
+    if !to_unstage.is_empty()
+        && effects.perform(Effect::Unstage {
+            paths: to_unstage.clone(),
+        })
+    {
+        with_lock(paths_ctx, || {
             git_rm_cached(&config.repo.git_dir, &config.repo.work_tree, &to_unstage)
                 .context("git rm --cached")
         })?;
     }
+    finish_operation(recorder, paths_ctx, effects)?;
+
+    Ok(to_unstage.len())
+}
+
+/// Start journaling an operation: `config.toml`, the excludes file and the
+/// secret state are always recorded, plus `extra_files`.
+fn begin_operation(
+    paths: &Paths,
+    config: &Config,
+    git: &GitCliBackend,
+    command: String,
+    extra_files: &[PathBuf],
+    pathspecs: Vec<PathBuf>,
+) -> Result<OperationRecorder> {
+    let mut files = vec![
+        paths.config_file(),
+        paths.config_dir().join("gitignore"),
+        secret_state_path(paths),
+    ];
+    files.extend_from_slice(extra_files);
+    OperationRecorder::begin(
+        git,
+        &config.repo.git_dir,
+        &config.repo.work_tree,
+        command,
+        &files,
+        pathspecs,
+    )
+    .context("record operation for undo")
+}
+
+fn finish_operation(recorder: OperationRecorder, paths: &Paths, effects: &Effects) -> Result<()> {
+    if effects.perform(Effect::WriteFile {
+        path: oplog_path(paths),
+    }) {
+        recorder
+            .finish(paths)
+            .context("record operation for undo")?;
+    }
+    Ok(())
+}
 
-    println!("untracked {} path(s)", to_unstage.len());
+/// Write `config` to `config.toml`; `before` is what was loaded, for `--dry-run`.
+fn write_config(paths: &Paths, before: &Config, config: &Config, effects: &Effects) -> Result<()> {
+    let config_path = paths.config_file();
+    if effects.perform(Effect::Config {
+        path: config_path.clone(),
+        changes: config_changes(before, config),
+    }) {
+        config
+            .write_to(&config_path)
+            .with_context(|| format!("write config to {}", config_path.display()))?;
+    }
     Ok(())
 }
 
-fn run_snapshot(overrides: &Overrides, message: Option<String>, auto: bool) -> Result<()> {
+fn operation_command(name: &str, paths: &[PathBuf]) -> String {
+    let mut command = name.to_string();
+    for path in paths {
+        command.push(' ');
+        command.push_str(&path.to_string_lossy());
+    }
+    command
+}
+
+fn run_undo(overrides: &Overrides, count: usize, list: bool, force: bool) -> Result<()> {
+    let (paths, config) = load_config(overrides)?;
+    if list {
+        let operations = read_operations(&paths).context("read operation log")?;
+        if operations.is_empty() {
+            println!("no operations to undo");
+        }
+        for operation in operations.iter().rev() {
+            let note = if operation.history_purged {
+                " (history purge cannot be undone)"
+            } else {
+                ""
+            };
+            println!("{}  {}{note}", operation.timestamp, operation.command);
+        }
+        return Ok(());
+    }
+
+    let git = GitCliBackend::new();
+    let _inhibit = daemon::DaemonInhibitGuard::new(&paths, "undo", Duration::from_secs(300))?;
+    with_lock(&paths, || {
+        for _ in 0..count {
+            let Some(outcome) = undo_last(
+                &paths,
+                &git,
+                &config.repo.git_dir,
+                &config.repo.work_tree,
+                force,
+            )?
+            else {
+                println!("no more operations to undo");
+                break;
+            };
+            println!("undid `{}`", outcome.operation.command);
+            for entry in &outcome.lost {
+                eprintln!(
+                    "warning: {} could not be restored to the index; its content is no longer in the repository",
+                    entry.path
+                );
+            }
+            if outcome.operation.history_purged {
+                eprintln!(
+                    "warning: `{}` purged plaintext from git history; the rewritten history cannot be undone",
+                    outcome.operation.command
+                );
+            }
+        }
+        Ok(())
+    })
+}
+
+fn run_snapshot(
+    overrides: &Overrides,
+    message: Option<String>,
+    auto: bool,
+    effects: &Effects,
+) -> Result<()> {
     let (paths, config) = load_config(overrides)?;
-    let _inhibit = daemon::DaemonInhibitGuard::new(&paths, "rollback", Duration::from_secs(300))?;
+    let _inhibit = if effects.dry_run() {
+        None
+    } else {
+        Some(daemon::DaemonInhibitGuard::new(
+            &paths,
+            "rollback",
+            Duration::from_secs(300),
+        )?)
+    };
     let git = GitCliBackend::new();
     guard_snapshot_secrets(&config, &git)?;
-    let msg = if auto {
+    let template = if auto {
         if message.is_some() {
             return Err(anyhow!("cannot use --auto with -m"));
         }
-        config
-            .snapshot
-            .auto_message_template
-            .clone()
-            .ok_or_else(|| anyhow!("auto message template is not configured"))?
-    } else if let Some(m) = message {
+        Some(
+            config
+                .snapshot
+                .auto_message_template
+                .clone()
+                .ok_or_else(|| anyhow!("auto message template is not configured"))?,
+        )
+    } else {
+        None
+    };
+    let msg = if let Some(m) = message {
         m
     } else {
         let now = time::OffsetDateTime::now_utc();
@@ -598,7 +1310,23 @@ fn run_snapshot(overrides: &Overrides, message: Option<String>, auto: bool) -> R
 
     let output = with_lock(&paths, || {
         let managed_paths = collect_managed_paths(&config);
-        if !managed_paths.is_empty() {
+        // `git add -u` only picks up tracked files with work tree changes.
+        let pending: Vec<PathBuf> = if effects.dry_run() {
+            managed_statuses(&paths, &config)?
+                .into_iter()
+                .filter(|status| !matches!(status.worktree_status, ' ' | '?' | '!'))
+                .map(|status| PathBuf::from(status.path))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        if effects.dry_run() {
+            if !pending.is_empty() {
+                effects.perform(Effect::Stage {
+                    paths: pending.clone(),
+                });
+            }
+        } else if !managed_paths.is_empty() {
             git.add(
                 &config.repo.git_dir,
                 &config.repo.work_tree,
@@ -607,13 +1335,128 @@ fn run_snapshot(overrides: &Overrides, message: Option<String>, auto: bool) -> R
             )
             .context("git add -u")?;
         }
+        stage_metadata_manifest(&paths, &config, &git, effects)?;
+        let msg = match &template {
+            Some(template) => {
+                let mut staged = git
+                    .staged_paths(&config.repo.git_dir, &config.repo.work_tree)
+                    .context("list staged paths")?;
+                if effects.dry_run() {
+                    for path in &pending {
+                        let path = path.to_string_lossy().to_string();
+                        if !staged.contains(&path) {
+                            staged.push(path);
+                        }
+                    }
+                }
+                auto_snapshot_message(template, &staged)
+            }
+            None => msg,
+        };
+        if !effects.perform(Effect::Commit {
+            message: msg.clone(),
+        }) {
+            return Ok(String::new());
+        }
         git.commit(&config.repo.git_dir, &config.repo.work_tree, &msg)
             .context("git commit")
     })?;
+    if effects.dry_run() {
+        if let Some(remote) = &config.snapshot.auto_push_remote {
+            effects.perform(Effect::Push {
+                remote: remote.clone(),
+            });
+        }
+        return Ok(());
+    }
+    println!("{output}");
+    push_after_snapshot(&config, &git);
+    Ok(())
+}
+
+/// Commit exactly what is staged, plus the metadata manifest, without `git add -u`.
+fn commit_staged(paths: &Paths, config: &Config, git: &GitCliBackend, msg: &str) -> Result<()> {
+    guard_snapshot_secrets(config, git)?;
+    let output = with_lock(paths, || {
+        stage_metadata_manifest(paths, config, git, &Effects::default())?;
+        git.commit(&config.repo.git_dir, &config.repo.work_tree, msg)
+            .context("git commit")
+    })?;
     println!("{output}");
+    push_after_snapshot(config, git);
     Ok(())
 }
 
+fn push_after_snapshot(config: &Config, git: &GitCliBackend) {
+    if let Some(remote) = &config.snapshot.auto_push_remote {
+        match push_snapshot(config, git, remote) {
+            Ok(()) => println!("pushed to '{remote}'"),
+            Err(err) => {
+                match git.unpushed_count(&config.repo.git_dir, &config.repo.work_tree, remote) {
+                    Ok(unpushed) => eprintln!("warning: {err:#}; {unpushed} commit(s) unpushed"),
+                    Err(count_err) => {
+                        eprintln!("warning: {err:#}; unpushed commit count unknown ({count_err:#})")
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn push_snapshot(config: &Config, git: &GitCliBackend, remote: &str) -> Result<()> {
+    git.push(
+        &config.repo.git_dir,
+        &config.repo.work_tree,
+        remote,
+        Some("HEAD"),
+        false,
+        false,
+    )
+    .with_context(|| format!("push to '{remote}' failed"))?;
+    Ok(())
+}
+
+fn stage_metadata_manifest(
+    paths: &Paths,
+    config: &Config,
+    git: &GitCliBackend,
+    effects: &Effects,
+) -> Result<()> {
+    if !config.metadata.enabled {
+        return Ok(());
+    }
+    let manifest = capture_manifest(config, paths).context("capture metadata")?;
+    let manifest_rel = PathBuf::from(&config.metadata.manifest);
+    if !effects.perform(Effect::WriteFile {
+        path: config.repo.work_tree.join(&manifest_rel),
+    }) {
+        effects.perform(Effect::Stage {
+            paths: vec![manifest_rel],
+        });
+        return Ok(());
+    }
+    let manifest_rel = write_manifest(config, &manifest).context("write metadata")?;
+    git.add(
+        &config.repo.git_dir,
+        &config.repo.work_tree,
+        &[manifest_rel],
+        AddMode::Paths,
+    )
+    .context("stage metadata manifest")?;
+    Ok(())
+}
+
+fn auto_snapshot_message(template: &str, staged: &[String]) -> String {
+    render_message(
+        template,
+        &MessageContext {
+            files: staged,
+            host: &hometree_core::snapshot::hostname(),
+            time: hometree_core::snapshot::local_now(),
+        },
+    )
+}
+
 fn collect_managed_paths(config: &Config) -> Vec<PathBuf> {
     let mut paths_out = Vec::new();
     let work_tree = &config.repo.work_tree;
@@ -655,13 +1498,17 @@ fn guard_snapshot_secrets(config: &Config, git: &GitCliBackend) -> Result<()> {
     Ok(())
 }
 
-fn run_log(overrides: &Overrides, limit: Option<usize>) -> Result<()> {
+fn run_log(overrides: &Overrides, limit: Option<usize>, format: OutputFormat) -> Result<()> {
     let (_paths, config) = load_config(overrides)?;
     let git = GitCliBackend::new();
     let entries = git
         .log_detailed(&config.repo.git_dir, &config.repo.work_tree, limit)
         .context("git log")?;
 
+    if format == OutputFormat::Json {
+        return print_json("log", LogReport { commits: entries });
+    }
+
     if entries.is_empty() {
         println!("no commits");
         return Ok(());
@@ -710,13 +1557,18 @@ fn run_deploy(
     target: String,
     no_secrets: bool,
     no_backup: bool,
+    format: OutputFormat,
+    effects: &Effects,
 ) -> Result<()> {
     let (paths, mut config) = load_config(overrides)?;
-    let _inhibit = daemon::DaemonInhibitGuard::new(&paths, "deploy", Duration::from_secs(300))?;
     if no_secrets {
         config.secrets.enabled = false;
     }
     let git = GitCliBackend::new();
+    if effects.dry_run() {
+        return record_deploy(&paths, &config, &git, &target, effects);
+    }
+    let _inhibit = daemon::DaemonInhibitGuard::new(&paths, "deploy", Duration::from_secs(300))?;
     let entry = deploy_with_options(
         &config,
         &paths,
@@ -725,11 +1577,41 @@ fn run_deploy(
         hometree_core::DeployOptions { no_backup },
     )
     .context("deploy")?;
+    if format == OutputFormat::Json {
+        return print_json("deploy", entry);
+    }
     println!("deployed {}", entry.rev);
     Ok(())
 }
 
-fn run_rollback(overrides: &Overrides, to: Option<String>, steps: usize) -> Result<()> {
+/// Record the files deploying `rev` would create, update or delete, for `--dry-run`.
+fn record_deploy(
+    paths: &Paths,
+    config: &Config,
+    git: &GitCliBackend,
+    rev: &str,
+    effects: &Effects,
+) -> Result<()> {
+    effects.perform(Effect::Deploy {
+        rev: rev.to_string(),
+    });
+    let plan = plan_deploy(config, paths, git, rev).context("plan deploy")?;
+    for entry in plan.entries {
+        let path = paths.home_dir().join(&entry.path);
+        effects.perform(match entry.action {
+            hometree_core::PlanAction::Delete => Effect::RemoveFile { path },
+            _ => Effect::WriteFile { path },
+        });
+    }
+    Ok(())
+}
+
+fn run_rollback(
+    overrides: &Overrides,
+    to: Option<String>,
+    steps: usize,
+    format: OutputFormat,
+) -> Result<()> {
     if steps == 0 {
         return Err(anyhow!("steps must be >= 1"));
     }
@@ -748,20 +1630,26 @@ fn run_rollback(overrides: &Overrides, to: Option<String>, steps: usize) -> Resu
     };
     let git = GitCliBackend::new();
     let entry = rollback(&config, &paths, &git, &target).context("rollback")?;
+    if format == OutputFormat::Json {
+        return print_json("rollback", entry);
+    }
     println!("rolled back to {}", entry.rev);
     Ok(())
 }
 
-fn run_plan(overrides: &Overrides, command: PlanCommand) -> Result<()> {
+fn run_plan(overrides: &Overrides, command: PlanCommand, format: OutputFormat) -> Result<()> {
     match command {
-        PlanCommand::Deploy { target } => run_plan_deploy(overrides, target),
+        PlanCommand::Deploy { target } => run_plan_deploy(overrides, target, format),
     }
 }
 
-fn run_plan_deploy(overrides: &Overrides, target: String) -> Result<()> {
+fn run_plan_deploy(overrides: &Overrides, target: String, format: OutputFormat) -> Result<()> {
     let (paths, config) = load_config(overrides)?;
     let git = GitCliBackend::new();
     let plan = plan_deploy(&config, &paths, &git, &target).context("plan deploy")?;
+    if format == OutputFormat::Json {
+        return print_json("plan deploy", plan);
+    }
     for entry in plan.entries {
         let action = match entry.action {
             hometree_core::PlanAction::Create => "create",
@@ -773,42 +1661,74 @@ fn run_plan_deploy(overrides: &Overrides, target: String) -> Result<()> {
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_verify(
     overrides: &Overrides,
     rev: Option<String>,
     strict: bool,
     with_secrets: SecretsVerifyArg,
     json: bool,
+    format: OutputFormat,
     show_paths: bool,
+    fix: Option<RepairOptions>,
 ) -> Result<()> {
     let (paths, config) = load_config(overrides)?;
     let git = GitCliBackend::new();
     let target = rev.as_deref().unwrap_or("HEAD");
-    let report = verify(
-        &config,
-        &paths,
-        &git,
-        target,
-        hometree_core::VerifyOptions {
-            strict,
-            secrets_mode: match with_secrets {
-                SecretsVerifyArg::Skip => hometree_core::verify::SecretsVerifyMode::Skip,
-                SecretsVerifyArg::Presence => hometree_core::verify::SecretsVerifyMode::Presence,
-                SecretsVerifyArg::Decrypt => hometree_core::verify::SecretsVerifyMode::Decrypt,
-            },
+    let options = hometree_core::VerifyOptions {
+        strict,
+        secrets_mode: match with_secrets {
+            SecretsVerifyArg::Skip => hometree_core::verify::SecretsVerifyMode::Skip,
+            SecretsVerifyArg::Presence => hometree_core::verify::SecretsVerifyMode::Presence,
+            SecretsVerifyArg::Decrypt => hometree_core::verify::SecretsVerifyMode::Decrypt,
         },
-    )
-    .context("verify")?;
+    };
+    let mut report = verify(&config, &paths, &git, target, options).context("verify")?;
+
+    let mut repaired = None;
+    if let Some(fix_options) = fix.filter(|_| !report.is_clean()) {
+        let _inhibit =
+            daemon::DaemonInhibitGuard::new(&paths, "verify --fix", Duration::from_secs(300))?;
+        let mut repair_report =
+            repair(&config, &paths, &git, &report, fix_options).context("repair")?;
+        report = verify(&config, &paths, &git, &repair_report.rev, options).context("verify")?;
+        if !show_paths {
+            let redacted = vec!["<redacted>".to_string(); repair_report.secrets_restored.len()];
+            repair_report.secrets_restored = redacted;
+        }
+        repaired = Some(repair_report);
+    }
 
-    if json {
+    if json || format == OutputFormat::Json {
         let output_report = if show_paths {
             report.clone()
         } else {
             redact_verify_report(&report)
         };
-        let output = serde_json::to_string_pretty(&output_report).context("serialize json")?;
-        println!("{output}");
+        if format == OutputFormat::Json {
+            print_json(
+                "verify",
+                VerifyResult {
+                    repair: repaired,
+                    report: output_report,
+                },
+            )?;
+        } else {
+            let output = if fix.is_some() {
+                serde_json::to_string_pretty(&VerifyResult {
+                    repair: repaired,
+                    report: output_report,
+                })
+            } else {
+                serde_json::to_string_pretty(&output_report)
+            }
+            .context("serialize json")?;
+            println!("{output}");
+        }
     } else {
+        if let Some(repair_report) = &repaired {
+            print_repair_report(repair_report);
+        }
         print_verify_report(&report, show_paths);
     }
 
@@ -819,6 +1739,25 @@ fn run_verify(
     Ok(())
 }
 
+fn print_repair_report(report: &RepairReport) {
+    for path in &report.restored {
+        println!("restored {path}");
+    }
+    for path in &report.mode_fixed {
+        println!("mode-fixed {path}");
+    }
+    for path in &report.metadata_fixed {
+        println!("metadata-fixed {path}");
+    }
+    for path in &report.secrets_restored {
+        println!("secret-restored {path}");
+    }
+    for path in &report.removed {
+        println!("removed {path}");
+    }
+    println!("backup {}", report.backup_dir.display());
+}
+
 fn print_verify_report(report: &hometree_core::VerifyReport, show_paths: bool) {
     if report.is_clean() {
         println!("clean");
@@ -837,6 +1776,9 @@ fn print_verify_report(report: &hometree_core::VerifyReport, show_paths: bool) {
     for path in &report.mode_mismatch {
         println!("mode-mismatch {path}");
     }
+    for path in &report.metadata_mismatch {
+        println!("metadata-mismatch {path}");
+    }
     for path in &report.unexpected {
         println!("unexpected {path}");
     }
@@ -879,16 +1821,33 @@ fn redact_verify_report(report: &hometree_core::VerifyReport) -> hometree_core::
     redacted
 }
 
-fn run_secret(overrides: &Overrides, command: SecretCommand) -> Result<()> {
+fn run_secret(
+    overrides: &Overrides,
+    command: SecretCommand,
+    format: OutputFormat,
+    effects: &Effects,
+) -> Result<()> {
     match command {
-        SecretCommand::Add { path, no_purge } => run_secret_add(overrides, path, no_purge),
-        SecretCommand::Refresh { paths } => run_secret_refresh(overrides, paths),
-        SecretCommand::Status { show_paths } => run_secret_status(overrides, show_paths),
-        SecretCommand::Rekey => run_secret_rekey(overrides),
+        SecretCommand::Add { path, no_purge } => run_secret_add(overrides, path, no_purge, effects),
+        SecretCommand::Refresh { paths } => run_secret_refresh(overrides, paths, effects),
+        SecretCommand::Status { show_paths, json } => {
+            run_secret_status(overrides, show_paths, json, format)
+        }
+        SecretCommand::Rekey { force } => run_secret_rekey(overrides, force, effects),
+        SecretCommand::Audit {
+            max_age_days,
+            json,
+            show_paths,
+        } => run_secret_audit(overrides, max_age_days, json, format, show_paths),
     }
 }
 
-fn run_secret_add(overrides: &Overrides, path: PathBuf, no_purge: bool) -> Result<()> {
+fn run_secret_add(
+    overrides: &Overrides,
+    path: PathBuf,
+    no_purge: bool,
+    effects: &Effects,
+) -> Result<()> {
     let (paths, mut config) = load_config(overrides)?;
     config.secrets.enabled = true;
     let rel = resolve_rel_path(paths.home_dir(), &path)?;
@@ -903,7 +1862,13 @@ fn run_secret_add(overrides: &Overrides, path: PathBuf, no_purge: bool) -> Resul
         .file_in_history(&config.repo.git_dir, &config.repo.work_tree, &rel)
         .unwrap_or(false);
 
-    if in_history && !no_purge {
+    let purge = in_history && !no_purge;
+    if purge && !effects.perform(Effect::RewriteHistory { path: rel.clone() }) {
+        eprintln!(
+            "note: {} exists in git history as plaintext; history would be rewritten",
+            rel.display()
+        );
+    } else if purge {
         eprintln!(
             "WARNING: {} exists in git history as plaintext.",
             rel.display()
@@ -924,79 +1889,152 @@ fn run_secret_add(overrides: &Overrides, path: PathBuf, no_purge: bool) -> Resul
         eprintln!("history purged");
     }
 
-    eprintln!("unstaging plaintext from index...");
-    git.remove_cached(&config.repo.git_dir, &config.repo.work_tree, &rel)
-        .context("failed to unstage plaintext")?;
+    if !effects.dry_run() {
+        eprintln!(
+            "encrypting to {}...",
+            rel_str.clone() + &config.secrets.sidecar_suffix
+        );
+    }
+    convert_to_secret(&paths, &mut config, &git, &rel, purge, effects)?;
 
-    config
-        .secrets
-        .rules
-        .push(hometree_core::config::SecretRule {
-            path: rel_str.clone(),
-            ciphertext: None,
-            mode: None,
-        });
-    if !config.ignore.patterns.contains(&rel_str) {
-        config.ignore.patterns.push(rel_str.clone());
+    if !effects.dry_run() {
+        eprintln!("done");
     }
-    config.manage.paths.retain(|p| p != &rel_str);
+    Ok(())
+}
 
-    eprintln!("updating config...");
-    let config_path = paths.config_file();
-    config
-        .write_to(&config_path)
-        .with_context(|| format!("write config to {}", config_path.display()))?;
+/// Unstage the plaintext at `rel`, record a secret rule for it in `config.toml`,
+/// and stage the freshly encrypted sidecar. History is left alone; `purged`
+/// tells the undo journal that the caller already rewrote it.
+fn convert_to_secret(
+    paths: &Paths,
+    config: &mut Config,
+    git: &GitCliBackend,
+    rel: &Path,
+    purged: bool,
+    effects: &Effects,
+) -> Result<()> {
+    let before = config.clone();
+    let sidecar_rel = SecretsManager::from_config(&config.secrets).ciphertext_path(&SecretRule {
+        path: rel.to_string_lossy().to_string(),
+        ciphertext: None,
+        mode: None,
+    });
+    let command = operation_command("secret add", &[rel.to_path_buf()]);
+    let mut recorder = begin_operation(
+        paths,
+        config,
+        git,
+        command,
+        &[paths.home_dir().join(&sidecar_rel)],
+        vec![rel.to_path_buf(), sidecar_rel],
+    )?;
+    if purged {
+        recorder.set_history_purged();
+    }
+    if effects.perform(Effect::Unstage {
+        paths: vec![rel.to_path_buf()],
+    }) {
+        git.remove_cached(&config.repo.git_dir, &config.repo.work_tree, rel)
+            .context("failed to unstage plaintext")?;
+    }
 
-    let secrets = SecretsManager::from_config(&config.secrets);
-    let backend = AgeBackend::from_config(&config.secrets)?;
-    let plaintext_abs = paths.home_dir().join(&rel);
-    let plaintext = std::fs::read(&plaintext_abs).context("read secret plaintext")?;
+    config.secrets.enabled = true;
+    add_secret_rule(config, &rel.to_string_lossy());
+    write_config(paths, &before, config, effects)?;
+
+    let ciphertext_rels = encrypt_new_secrets(paths, config, &[rel.to_path_buf()], effects)?;
+    if effects.perform(Effect::Stage {
+        paths: ciphertext_rels.clone(),
+    }) {
+        with_lock(paths, || {
+            git.add(
+                &config.repo.git_dir,
+                &config.repo.work_tree,
+                &ciphertext_rels,
+                AddMode::Paths,
+            )
+            .context("git add")
+        })?;
+    }
+    finish_operation(recorder, paths, effects)
+}
 
-    eprintln!(
-        "encrypting to {}...",
-        rel_str.clone() + &config.secrets.sidecar_suffix
-    );
-    let ciphertext = backend.encrypt(&plaintext)?;
-    let ciphertext_rel = secrets.ciphertext_path(
-        secrets
+/// Turn a path into a secret rule and keep its plaintext out of the managed set.
+fn add_secret_rule(config: &mut Config, rel_str: &str) {
+    config.secrets.rules.push(SecretRule {
+        path: rel_str.to_string(),
+        ciphertext: None,
+        mode: None,
+    });
+    if !config.ignore.patterns.iter().any(|p| p == rel_str) {
+        config.ignore.patterns.push(rel_str.to_string());
+    }
+    config.manage.paths.retain(|p| p != rel_str);
+}
+
+/// Encrypt freshly added secrets to their sidecars; returns the sidecar paths to stage.
+fn encrypt_new_secrets(
+    paths: &Paths,
+    config: &Config,
+    rels: &[PathBuf],
+    effects: &Effects,
+) -> Result<Vec<PathBuf>> {
+    let secrets = SecretsManager::from_config(&config.secrets);
+    let backend = AgeBackend::from_config(&config.secrets, &config.repo.work_tree)?;
+    let mut written = Vec::new();
+    for rel in rels {
+        let plaintext_abs = paths.home_dir().join(rel);
+        let plaintext = Plaintext::read_file(&plaintext_abs).context("read secret plaintext")?;
+        let ciphertext = backend.encrypt(plaintext.as_bytes())?;
+        let rule = secrets
             .rules()
             .iter()
-            .find(|rule| rule.path == rel_str)
-            .expect("rule"),
-    );
-    let ciphertext_abs = paths.home_dir().join(&ciphertext_rel);
+            .find(|rule| Path::new(&rule.path) == rel)
+            .ok_or_else(|| anyhow!("no secret rule for {}", rel.display()))?;
+        let ciphertext_rel = secrets.ciphertext_path(rule);
+        write_sidecar(paths, &ciphertext_rel, &ciphertext, effects)?;
+        written.push((rel.clone(), ciphertext_rel, ciphertext));
+    }
+    record_secret_encryptions(paths, &backend, &written, effects)?;
+    ensure_git_excludes(paths, config, effects)?;
+    Ok(written
+        .into_iter()
+        .map(|(_, ciphertext_rel, _)| ciphertext_rel)
+        .collect())
+}
+
+/// Write a freshly encrypted sidecar under `$HOME`.
+fn write_sidecar(
+    paths: &Paths,
+    ciphertext_rel: &Path,
+    ciphertext: &[u8],
+    effects: &Effects,
+) -> Result<()> {
+    let ciphertext_abs = paths.home_dir().join(ciphertext_rel);
+    if !effects.perform(Effect::WriteFile {
+        path: ciphertext_abs.clone(),
+    }) {
+        return Ok(());
+    }
     if let Some(parent) = ciphertext_abs.parent() {
         std::fs::create_dir_all(parent)?;
     }
     std::fs::write(&ciphertext_abs, ciphertext)?;
-
-    ensure_git_excludes(&paths, &config)?;
-
-    eprintln!("staging ciphertext...");
-    with_lock(&paths, || {
-        git.add(
-            &config.repo.git_dir,
-            &config.repo.work_tree,
-            std::slice::from_ref(&ciphertext_rel),
-            AddMode::Paths,
-        )
-        .context("git add")
-    })?;
-
-    eprintln!("done");
     Ok(())
 }
 
-fn run_secret_refresh(overrides: &Overrides, paths: Vec<PathBuf>) -> Result<()> {
+fn run_secret_refresh(overrides: &Overrides, paths: Vec<PathBuf>, effects: &Effects) -> Result<()> {
     let (paths_ctx, config) = load_config(overrides)?;
     let secrets = SecretsManager::from_config(&config.secrets);
     if !secrets.enabled() {
         return Err(anyhow!("secrets are not enabled"));
     }
-    let backend = AgeBackend::from_config(&config.secrets)?;
+    let backend = AgeBackend::from_config(&config.secrets, &config.repo.work_tree)?;
     let git = GitCliBackend::new();
     let mut to_stage = Vec::new();
     let mut to_unstage = Vec::new();
+    let mut written = Vec::new();
     let filter: Option<std::collections::BTreeSet<PathBuf>> = if paths.is_empty() {
         None
     } else {
@@ -1019,29 +2057,35 @@ fn run_secret_refresh(overrides: &Overrides, paths: Vec<PathBuf>) -> Result<()>
             continue;
         }
         let plaintext_abs = paths_ctx.home_dir().join(&plaintext_rel);
-        let plaintext = std::fs::read(&plaintext_abs)?;
+        let plaintext = Plaintext::read_file(&plaintext_abs)?;
         let ciphertext_rel = secrets.ciphertext_path(rule);
         eprintln!(
             "encrypting {} -> {}",
             plaintext_rel.display(),
             ciphertext_rel.display()
         );
-        let ciphertext = backend.encrypt(&plaintext)?;
-        let ciphertext_abs = paths_ctx.home_dir().join(&ciphertext_rel);
-        if let Some(parent) = ciphertext_abs.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        std::fs::write(&ciphertext_abs, ciphertext)?;
+        let ciphertext = backend.encrypt(plaintext.as_bytes())?;
+        write_sidecar(&paths_ctx, &ciphertext_rel, &ciphertext, effects)?;
+        written.push((plaintext_rel.clone(), ciphertext_rel.clone(), ciphertext));
         to_stage.push(ciphertext_rel);
         to_unstage.push(plaintext_rel);
     }
+    record_secret_encryptions(&paths_ctx, &backend, &written, effects)?;
 
     for plaintext_rel in &to_unstage {
-        eprintln!("unstaging {}", plaintext_rel.display());
-        let _ = git.remove_cached(&config.repo.git_dir, &config.repo.work_tree, plaintext_rel);
+        if effects.perform(Effect::Unstage {
+            paths: vec![plaintext_rel.clone()],
+        }) {
+            eprintln!("unstaging {}", plaintext_rel.display());
+            let _ = git.remove_cached(&config.repo.git_dir, &config.repo.work_tree, plaintext_rel);
+        }
     }
 
-    if !to_stage.is_empty() {
+    if !to_stage.is_empty()
+        && effects.perform(Effect::Stage {
+            paths: to_stage.clone(),
+        })
+    {
         with_lock(&paths_ctx, || {
             git.add(
                 &config.repo.git_dir,
@@ -1053,18 +2097,40 @@ fn run_secret_refresh(overrides: &Overrides, paths: Vec<PathBuf>) -> Result<()>
         })?;
     }
 
-    println!("refreshed {} secret(s)", to_stage.len());
+    if !effects.dry_run() {
+        println!("refreshed {} secret(s)", to_stage.len());
+    }
     Ok(())
 }
 
-fn run_secret_status(overrides: &Overrides, show_paths: bool) -> Result<()> {
+fn run_secret_status(
+    overrides: &Overrides,
+    show_paths: bool,
+    json: bool,
+    format: OutputFormat,
+) -> Result<()> {
     let (paths_ctx, config) = load_config(overrides)?;
     let secrets = SecretsManager::from_config(&config.secrets);
+    let json_format = format == OutputFormat::Json;
     if !secrets.enabled() {
-        println!("secrets disabled");
+        if json_format {
+            print_json(
+                "secret status",
+                SecretStatusReport {
+                    enabled: false,
+                    secrets: Vec::new(),
+                },
+            )?;
+        } else if json {
+            println!("[]");
+        } else {
+            println!("secrets disabled");
+        }
         return Ok(());
     }
-    let backend = AgeBackend::from_config(&config.secrets).ok();
+    let backend = AgeBackend::from_config(&config.secrets, &config.repo.work_tree).ok();
+    let state = load_secret_state_with_commits(&paths_ctx, &config)?;
+    let mut entries = Vec::new();
 
     for rule in secrets.rules() {
         let plaintext_rel = secrets.plaintext_path(rule);
@@ -1078,7 +2144,7 @@ fn run_secret_status(overrides: &Overrides, show_paths: bool) -> Result<()> {
         } else if !has_ciphertext {
             "missing-ciphertext"
         } else if let Some(backend) = backend.as_ref() {
-            let plaintext = std::fs::read(&plaintext_abs)?;
+            let plaintext = Plaintext::read_file(&plaintext_abs)?;
             let ciphertext = std::fs::read(&ciphertext_abs)?;
             match backend.decrypt(&ciphertext) {
                 Ok(decrypted) => {
@@ -1093,41 +2159,166 @@ fn run_secret_status(overrides: &Overrides, show_paths: bool) -> Result<()> {
         } else {
             "unknown"
         };
-        if show_paths {
-            println!("{status} {}", plaintext_rel.display());
+        let display_path = if show_paths {
+            plaintext_rel.display().to_string()
+        } else {
+            "<redacted>".to_string()
+        };
+        if json || json_format {
+            let record = state.secrets.get(plaintext_rel.to_string_lossy().as_ref());
+            entries.push(SecretStatusEntry {
+                path: display_path,
+                status: status.to_string(),
+                last_encrypted_at: record.map(|r| r.last_encrypted_at.clone()),
+                recipients: record.map(|r| r.recipients.clone()).unwrap_or_default(),
+                commit: record.and_then(|r| r.commit.clone()),
+            });
         } else {
-            println!("{status} <redacted>");
+            println!("{status} {display_path}");
+        }
+    }
+
+    if json_format {
+        print_json(
+            "secret status",
+            SecretStatusReport {
+                enabled: true,
+                secrets: entries,
+            },
+        )?;
+    } else if json {
+        let output = serde_json::to_string_pretty(&entries).context("serialize json")?;
+        println!("{output}");
+    }
+
+    Ok(())
+}
+
+fn run_secret_audit(
+    overrides: &Overrides,
+    max_age_days: Option<u64>,
+    json: bool,
+    format: OutputFormat,
+    show_paths: bool,
+) -> Result<()> {
+    let (paths_ctx, config) = load_config(overrides)?;
+    if !config.secrets.enabled {
+        return Err(anyhow!("secrets are not enabled"));
+    }
+    let backend = AgeBackend::from_config(&config.secrets, &config.repo.work_tree)?;
+    let state = load_secret_state_with_commits(&paths_ctx, &config)?;
+    let stale_after_days = max_age_days.unwrap_or(config.secrets.stale_after_days);
+    let mut report = audit_secrets(
+        &config,
+        &paths_ctx,
+        &state,
+        &backend.recipient_keys(),
+        stale_after_days,
+        std::time::SystemTime::now(),
+    )
+    .context("audit secrets")?;
+    if !show_paths {
+        for entry in &mut report.entries {
+            entry.path = "<redacted>".to_string();
+            entry.ciphertext = "<redacted>".to_string();
+        }
+    }
+
+    if format == OutputFormat::Json {
+        print_json("secret audit", &report)?;
+    } else if json {
+        let output = serde_json::to_string_pretty(&report).context("serialize json")?;
+        println!("{output}");
+    } else if report.is_clean() {
+        println!("clean");
+    } else {
+        for entry in &report.entries {
+            for finding in &entry.findings {
+                let label = match finding {
+                    AuditFinding::Unrecorded => "unrecorded".to_string(),
+                    AuditFinding::Stale => {
+                        format!("stale({}d)", entry.age_days.unwrap_or_default())
+                    }
+                    AuditFinding::RecipientMismatch => "recipient-mismatch".to_string(),
+                    AuditFinding::CiphertextChanged => "ciphertext-changed".to_string(),
+                    AuditFinding::MissingCiphertext => "missing-ciphertext".to_string(),
+                };
+                println!("{label} {}", entry.path);
+            }
         }
     }
 
+    if !report.is_clean() {
+        return Err(ProblemsReported.into());
+    }
     Ok(())
 }
 
-fn run_secret_rekey(overrides: &Overrides) -> Result<()> {
+fn record_secret_encryptions(
+    paths: &Paths,
+    backend: &AgeBackend,
+    written: &[(PathBuf, PathBuf, Vec<u8>)],
+    effects: &Effects,
+) -> Result<()> {
+    if written.is_empty() {
+        return Ok(());
+    }
+    let recipients = backend.recipient_keys();
+    let mut state = read_secret_state(paths).context("read secret state")?;
+    for (plaintext_rel, ciphertext_rel, ciphertext) in written {
+        state.record_encryption(plaintext_rel, ciphertext_rel, ciphertext, &recipients)?;
+    }
+    if !effects.perform(Effect::WriteFile {
+        path: secret_state_path(paths),
+    }) {
+        return Ok(());
+    }
+    write_secret_state(paths, &state).context("write secret state")
+}
+
+fn load_secret_state_with_commits(paths: &Paths, config: &Config) -> Result<SecretState> {
+    let mut state = read_secret_state(paths).context("read secret state")?;
+    let git = GitCliBackend::new();
+    if state.resolve_commits(config, &git) {
+        write_secret_state(paths, &state).context("write secret state")?;
+    }
+    Ok(state)
+}
+
+fn run_secret_rekey(overrides: &Overrides, force: bool, effects: &Effects) -> Result<()> {
     let (paths_ctx, config) = load_config(overrides)?;
     let secrets = SecretsManager::from_config(&config.secrets);
     if !secrets.enabled() {
         return Err(anyhow!("secrets are not enabled"));
     }
-    let backend = AgeBackend::from_config(&config.secrets)?;
+    let backend = AgeBackend::from_config(&config.secrets, &config.repo.work_tree)?;
+    backend.ensure_recipients()?;
+    let recipients = backend.recipient_keys();
+    let state = read_secret_state(&paths_ctx).context("read secret state")?;
     let git = GitCliBackend::new();
     let mut to_stage = Vec::new();
+    let mut written = Vec::new();
 
     for rule in secrets.rules() {
         let plaintext_rel = secrets.plaintext_path(rule);
+        if !force && !state.needs_rekey(&plaintext_rel, &recipients) {
+            continue;
+        }
         let plaintext_abs = paths_ctx.home_dir().join(&plaintext_rel);
-        let plaintext = std::fs::read(&plaintext_abs)?;
-        let ciphertext = backend.encrypt(&plaintext)?;
+        let plaintext = Plaintext::read_file(&plaintext_abs)?;
+        let ciphertext = backend.encrypt(plaintext.as_bytes())?;
         let ciphertext_rel = secrets.ciphertext_path(rule);
-        let ciphertext_abs = paths_ctx.home_dir().join(&ciphertext_rel);
-        if let Some(parent) = ciphertext_abs.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        std::fs::write(&ciphertext_abs, ciphertext)?;
+        write_sidecar(&paths_ctx, &ciphertext_rel, &ciphertext, effects)?;
+        written.push((plaintext_rel, ciphertext_rel.clone(), ciphertext));
         to_stage.push(ciphertext_rel);
     }
+    record_secret_encryptions(&paths_ctx, &backend, &written, effects)?;
 
-    if !to_stage.is_empty() {
+    if !to_stage.is_empty()
+        && effects.perform(Effect::Stage {
+            paths: to_stage.clone(),
+        })
+    {
         with_lock(&paths_ctx, || {
             git.add(
                 &config.repo.git_dir,
@@ -1139,15 +2330,19 @@ fn run_secret_rekey(overrides: &Overrides) -> Result<()> {
         })?;
     }
 
-    println!("rekeyed {} secret(s)", to_stage.len());
+    if to_stage.is_empty() {
+        println!("recipients unchanged; nothing to rekey");
+    } else if !effects.dry_run() {
+        println!("rekeyed {} secret(s)", to_stage.len());
+    }
     Ok(())
 }
 
-fn run_remote(overrides: &Overrides, command: RemoteCommand) -> Result<()> {
+fn run_remote(overrides: &Overrides, command: RemoteCommand, format: OutputFormat) -> Result<()> {
     match command {
         RemoteCommand::Add { name, url } => run_remote_add(overrides, name, url),
         RemoteCommand::Remove { name } => run_remote_remove(overrides, name),
-        RemoteCommand::List => run_remote_list(overrides),
+        RemoteCommand::List => run_remote_list(overrides, format),
         RemoteCommand::Push {
             remote,
             branch,
@@ -1175,12 +2370,15 @@ fn run_remote_remove(overrides: &Overrides, name: String) -> Result<()> {
     Ok(())
 }
 
-fn run_remote_list(overrides: &Overrides) -> Result<()> {
+fn run_remote_list(overrides: &Overrides, format: OutputFormat) -> Result<()> {
     let (_paths, config) = load_config(overrides)?;
     let git = GitCliBackend::new();
     let remotes = git
         .remote_list(&config.repo.git_dir, &config.repo.work_tree)
         .context("git remote list")?;
+    if format == OutputFormat::Json {
+        return print_json("remote list", RemoteListReport { remotes });
+    }
     if remotes.is_empty() {
         println!("no remotes configured");
     } else {
@@ -1217,10 +2415,24 @@ fn run_remote_push(
     Ok(())
 }
 
-fn run_sync(overrides: &Overrides, remote: String, no_deploy: bool) -> Result<()> {
+fn run_sync(
+    overrides: &Overrides,
+    remote: String,
+    no_deploy: bool,
+    effects: &Effects,
+) -> Result<()> {
     let (paths, config) = load_config(overrides)?;
     let git = GitCliBackend::new();
 
+    if effects.dry_run() {
+        effects.perform(Effect::Pull { remote });
+        if !no_deploy {
+            eprintln!("note: deploy is planned against the current HEAD; the pull may move it");
+            record_deploy(&paths, &config, &git, "HEAD", effects)?;
+        }
+        return Ok(());
+    }
+
     println!("pulling from '{}'...", remote);
     let output = git
         .pull(&config.repo.git_dir, &config.repo.work_tree, &remote)
@@ -1247,46 +2459,39 @@ fn run_sync(overrides: &Overrides, remote: String, no_deploy: bool) -> Result<()
     Ok(())
 }
 
-fn run_backup(overrides: &Overrides, command: BackupCommand) -> Result<()> {
+fn run_backup(
+    overrides: &Overrides,
+    command: BackupCommand,
+    format: OutputFormat,
+    effects: &Effects,
+) -> Result<()> {
     let paths = load_paths(overrides)?;
     let backups_dir = paths.state_dir().join("backups");
 
     match command {
         BackupCommand::List => {
-            if !backups_dir.exists() {
-                println!("No backups found.");
-                return Ok(());
+            let backups = list_backups(&backups_dir)?;
+            if format == OutputFormat::Json {
+                return print_json("backup list", BackupListReport { backups });
             }
-
-            let mut entries: Vec<_> = std::fs::read_dir(&backups_dir)
-                .context("read backups directory")?
-                .filter_map(|e| e.ok())
-                .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
-                .collect();
-
-            if entries.is_empty() {
+            if backups.is_empty() {
                 println!("No backups found.");
                 return Ok(());
             }
 
-            entries.sort_by_key(|e| e.file_name());
-            entries.reverse();
-
             println!("Available backups (newest first):");
-            for entry in entries {
-                let name = entry.file_name();
-                let ts_str = name.to_string_lossy();
-                if let Ok(ts) = ts_str.parse::<u64>() {
+            for backup in backups {
+                if let Some(ts) = backup.timestamp {
                     let dt = time::OffsetDateTime::from_unix_timestamp(ts as i64)
                         .unwrap_or(time::OffsetDateTime::UNIX_EPOCH);
                     let format = time::format_description::parse(
                         "[year]-[month]-[day] [hour]:[minute]:[second]",
                     )
                     .unwrap();
-                    let formatted = dt.format(&format).unwrap_or_else(|_| ts_str.to_string());
-                    println!("  {} ({})", ts_str, formatted);
+                    let formatted = dt.format(&format).unwrap_or_else(|_| backup.name.clone());
+                    println!("  {} ({})", backup.name, formatted);
                 } else {
-                    println!("  {}", ts_str);
+                    println!("  {}", backup.name);
                 }
             }
         }
@@ -1322,7 +2527,11 @@ fn run_backup(overrides: &Overrides, command: BackupCommand) -> Result<()> {
                     .unwrap_or_default()
                     .as_secs();
                 let pre_restore_backup = backups_dir.join(format!("{}-pre-restore", ts));
-                std::fs::create_dir_all(&pre_restore_backup)?;
+                if effects.perform(Effect::CreateDir {
+                    path: pre_restore_backup.clone(),
+                }) {
+                    std::fs::create_dir_all(&pre_restore_backup)?;
+                }
 
                 for entry in WalkDir::new(&backup_dir).into_iter().flatten() {
                     if entry.file_type().is_dir() {
@@ -1332,16 +2541,24 @@ fn run_backup(overrides: &Overrides, command: BackupCommand) -> Result<()> {
                     let current = paths.home_dir().join(rel);
                     if current.exists() {
                         let dest = pre_restore_backup.join(rel);
+                        if !effects.perform(Effect::CopyFile {
+                            from: current.clone(),
+                            to: dest.clone(),
+                        }) {
+                            continue;
+                        }
                         if let Some(parent) = dest.parent() {
                             std::fs::create_dir_all(parent)?;
                         }
                         std::fs::copy(&current, &dest)?;
                     }
                 }
-                println!(
-                    "backed up current state to {}",
-                    pre_restore_backup.display()
-                );
+                if !effects.dry_run() {
+                    println!(
+                        "backed up current state to {}",
+                        pre_restore_backup.display()
+                    );
+                }
             }
 
             let mut count = 0;
@@ -1351,19 +2568,52 @@ fn run_backup(overrides: &Overrides, command: BackupCommand) -> Result<()> {
                 }
                 let rel = entry.path().strip_prefix(&backup_dir)?;
                 let dest = paths.home_dir().join(rel);
+                if !effects.perform(Effect::CopyFile {
+                    from: entry.path().to_path_buf(),
+                    to: dest.clone(),
+                }) {
+                    continue;
+                }
                 if let Some(parent) = dest.parent() {
                     std::fs::create_dir_all(parent)?;
                 }
                 std::fs::copy(entry.path(), &dest)?;
                 count += 1;
             }
-            println!("restored {} files from {}", count, backup_dir.display());
+            if !effects.dry_run() {
+                println!("restored {} files from {}", count, backup_dir.display());
+            }
         }
     }
 
     Ok(())
 }
 
+/// Backup directories under `backups_dir`, newest first.
+fn list_backups(backups_dir: &Path) -> Result<Vec<BackupEntry>> {
+    if !backups_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut entries: Vec<_> = std::fs::read_dir(backups_dir)
+        .context("read backups directory")?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .collect();
+    entries.sort_by_key(|e| e.file_name());
+    entries.reverse();
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            BackupEntry {
+                timestamp: name.parse::<u64>().ok(),
+                name,
+                path: entry.path(),
+            }
+        })
+        .collect())
+}
+
 fn load_paths(overrides: &Overrides) -> Result<Paths> {
     Paths::new_with_overrides(
         overrides.home_root.as_deref(),
@@ -1390,11 +2640,8 @@ fn with_lock<T>(paths: &Paths, f: impl FnOnce() -> Result<T>) -> Result<T> {
     f()
 }
 
-fn ensure_git_excludes(paths: &Paths, config: &Config) -> Result<()> {
+fn ensure_git_excludes(paths: &Paths, config: &Config, effects: &Effects) -> Result<()> {
     let excludes_path = paths.config_dir().join("gitignore");
-    if let Some(parent) = excludes_path.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
 
     let mut existing = std::collections::BTreeSet::new();
     if excludes_path.exists() {
@@ -1421,16 +2668,29 @@ fn ensure_git_excludes(paths: &Paths, config: &Config) -> Result<()> {
         output.push_str(&line);
         output.push('\n');
     }
-    std::fs::write(&excludes_path, output)?;
+    if effects.perform(Effect::WriteFile {
+        path: excludes_path.clone(),
+    }) {
+        if let Some(parent) = excludes_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&excludes_path, output)?;
+    }
 
-    let git = GitCliBackend::new();
-    git.config_set(
-        &config.repo.git_dir,
-        &config.repo.work_tree,
-        "core.excludesFile",
-        excludes_path.to_string_lossy().as_ref(),
-    )
-    .context("git config core.excludesFile")?;
+    let value = excludes_path.to_string_lossy().to_string();
+    if effects.perform(Effect::GitConfig {
+        key: "core.excludesFile".to_string(),
+        value: value.clone(),
+    }) {
+        let git = GitCliBackend::new();
+        git.config_set(
+            &config.repo.git_dir,
+            &config.repo.work_tree,
+            "core.excludesFile",
+            &value,
+        )
+        .context("git config core.excludesFile")?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,188 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use hometree_core::ipc::DaemonStatus;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+const PREFIX: &str = "hometree_daemon";
+
+/// Render `status` in the Prometheus text exposition format.
+pub fn render(status: &DaemonStatus) -> String {
+    let m = &status.metrics;
+    let mut out = Metrics::default();
+    out.gauge(
+        "start_time_seconds",
+        "Unix time the daemon started.",
+        unix_seconds(&status.running_since),
+    );
+    out.counter(
+        "events_received_total",
+        "Filesystem events received from the watcher.",
+        m.events_received as f64,
+    );
+    out.counter(
+        "paths_staged_total",
+        "Paths staged by successful flushes.",
+        m.paths_staged as f64,
+    );
+    out.counter(
+        "flushes_total",
+        "Successful flushes.",
+        status.total_flushes as f64,
+    );
+    out.counter(
+        "flush_failures_total",
+        "Failed flushes.",
+        m.flush_failures as f64,
+    );
+    out.gauge(
+        "consecutive_flush_failures",
+        "Failed flushes since the last successful one.",
+        Some(m.consecutive_flush_failures as f64),
+    );
+    out.gauge(
+        "failing_since_seconds",
+        "Unix time of the first failure in the current run of failed flushes.",
+        m.failing_since.as_deref().and_then(unix_seconds),
+    );
+    out.gauge(
+        "last_flush_success_seconds",
+        "Unix time of the last successful flush.",
+        status.last_flush_at.as_deref().and_then(unix_seconds),
+    );
+    out.gauge(
+        "last_flush_duration_seconds",
+        "Duration of the last flush attempt.",
+        m.last_flush_duration_ms.map(|ms| ms as f64 / 1000.0),
+    );
+    out.gauge(
+        "retry_backoff_seconds",
+        "Delay before the next flush retry; zero while flushes succeed.",
+        Some(m.retry_backoff_ms as f64 / 1000.0),
+    );
+    out.header(
+        "queue_size",
+        "gauge",
+        "Paths waiting for the next flush, by queue.",
+    );
+    for (queue, size) in [
+        ("managed", m.queue_managed),
+        ("secrets", m.queue_secrets),
+        ("auto_add", m.queue_auto_add),
+        ("renames", m.queue_renames),
+    ] {
+        out.sample("queue_size", &format!("{{queue=\"{queue}\"}}"), size as f64);
+    }
+    out.gauge(
+        "inhibited",
+        "Whether staging is paused or inhibited.",
+        Some(if status.inhibited { 1.0 } else { 0.0 }),
+    );
+    out.gauge(
+        "throttled",
+        "Whether changes are held back by quiet hours, battery power or the flush rate limit.",
+        Some(if status.throttle.is_some() { 1.0 } else { 0.0 }),
+    );
+    out.counter(
+        "inhibited_seconds_total",
+        "Time spent paused or inhibited.",
+        m.inhibited_ms_total as f64 / 1000.0,
+    );
+    out.counter(
+        "snapshots_total",
+        "Automatic snapshots committed.",
+        status.total_snapshots as f64,
+    );
+    if status.push_remote.is_some() {
+        out.gauge(
+            "unpushed_commits",
+            "Commits not yet pushed to the auto-push remote.",
+            Some(status.unpushed_commits as f64),
+        );
+    }
+    out.text
+}
+
+/// Replace `path` atomically so a textfile collector never reads half a file.
+pub fn write_textfile(path: &Path, status: &DaemonStatus) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, render(status))?;
+    fs::rename(&tmp, path)
+}
+
+fn unix_seconds(ts: &str) -> Option<f64> {
+    let parsed = OffsetDateTime::parse(ts, &Rfc3339).ok()?;
+    Some(parsed.unix_timestamp_nanos() as f64 / 1e9)
+}
+
+#[derive(Default)]
+struct Metrics {
+    text: String,
+}
+
+impl Metrics {
+    fn header(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.text, "# HELP {PREFIX}_{name} {help}");
+        let _ = writeln!(self.text, "# TYPE {PREFIX}_{name} {kind}");
+    }
+
+    fn sample(&mut self, name: &str, labels: &str, value: f64) {
+        let _ = writeln!(self.text, "{PREFIX}_{name}{labels} {value}");
+    }
+
+    fn counter(&mut self, name: &str, help: &str, value: f64) {
+        self.header(name, "counter", help);
+        self.sample(name, "", value);
+    }
+
+    /// Gauges without a value are left out entirely.
+    fn gauge(&mut self, name: &str, help: &str, value: Option<f64>) {
+        if let Some(value) = value {
+            self.header(name, "gauge", help);
+            self.sample(name, "", value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use hometree_core::ipc::{DaemonMetrics, DaemonStatus};
+
+    #[test]
+    fn renders_counters_gauges_and_labelled_queues() {
+        let status = DaemonStatus {
+            running_since: "2024-01-01T00:00:00Z".to_string(),
+            total_flushes: 7,
+            last_flush_at: Some("2024-01-01T01:00:00Z".to_string()),
+            metrics: DaemonMetrics {
+                events_received: 42,
+                flush_failures: 3,
+                consecutive_flush_failures: 2,
+                retry_backoff_ms: 800,
+                queue_managed: 5,
+                failing_since: Some("2024-01-01T02:00:00Z".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let text = render(&status);
+        assert!(text.contains("# TYPE hometree_daemon_events_received_total counter\n"));
+        assert!(text.contains("\nhometree_daemon_events_received_total 42\n"));
+        assert!(text.contains("\nhometree_daemon_flushes_total 7\n"));
+        assert!(text.contains("\nhometree_daemon_start_time_seconds 1704067200\n"));
+        assert!(text.contains("\nhometree_daemon_failing_since_seconds 1704074400\n"));
+        assert!(text.contains("\nhometree_daemon_retry_backoff_seconds 0.8\n"));
+        assert!(text.contains("\nhometree_daemon_queue_size{queue=\"managed\"} 5\n"));
+        assert!(text.contains("\nhometree_daemon_inhibited 0\n"));
+        assert!(!text.contains("last_flush_duration_seconds"));
+        assert!(!text.contains("unpushed_commits"));
+    }
+}
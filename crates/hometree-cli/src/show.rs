@@ -0,0 +1,137 @@
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use hometree_core::git::{DiffSpec, GitBackend, GitCliBackend, EMPTY_TREE};
+use hometree_core::secrets::{AgeBackend, SecretsBackend, SecretsManager};
+use hometree_core::ManagedSet;
+
+use crate::diff::{classify, default_pathspecs, status_word, use_color, write_patch};
+use crate::{load_config, resolve_rel_path, ColorWhen, Overrides};
+
+pub(crate) fn run_show(
+    overrides: &Overrides,
+    rev: String,
+    stat: bool,
+    color: ColorWhen,
+) -> Result<()> {
+    let (paths, config) = load_config(overrides)?;
+    let git = GitCliBackend::new();
+    let git_dir = &config.repo.git_dir;
+    let work_tree = &config.repo.work_tree;
+    let commit = git
+        .rev_parse(git_dir, work_tree, &format!("{rev}^{{commit}}"))
+        .with_context(|| format!("resolve {rev}"))?;
+    let parent = git
+        .rev_parse(git_dir, work_tree, &format!("{commit}^"))
+        .unwrap_or_else(|_| EMPTY_TREE.to_string());
+
+    let managed =
+        ManagedSet::from_config(&config, paths.home_dir()).context("build managed set")?;
+    let secrets = SecretsManager::from_config(&config.secrets);
+    let spec = DiffSpec {
+        cached: false,
+        rev: Some(format!("{parent}..{commit}")),
+    };
+    let changes = git
+        .diff_changes(
+            git_dir,
+            work_tree,
+            &spec,
+            &default_pathspecs(&config, &secrets),
+        )
+        .context("git diff")?;
+    let changes = classify(changes, &managed, &secrets);
+    changes.note_hidden();
+
+    let mut stdout = std::io::stdout().lock();
+    let header = git.commit_header(git_dir, &commit).context("git show")?;
+    write!(stdout, "{header}")?;
+    if !header.ends_with("\n\n") {
+        writeln!(stdout)?;
+    }
+    for change in &changes.shown {
+        writeln!(stdout, "{:<9} {}", status_word(change.status), change.path)?;
+    }
+    for (change, plaintext) in &changes.sidecars {
+        match plaintext {
+            Some(plaintext) => writeln!(
+                stdout,
+                "{:<9} {} (secret {})",
+                status_word(change.status),
+                change.path,
+                plaintext.display()
+            )?,
+            None => writeln!(stdout, "{:<9} {}", status_word(change.status), change.path)?,
+        }
+    }
+    if changes.shown.is_empty() && changes.sidecars.is_empty() {
+        return Ok(());
+    }
+    writeln!(stdout)?;
+    write_patch(
+        &mut stdout,
+        &git,
+        git_dir,
+        work_tree,
+        &spec,
+        &changes,
+        stat,
+        use_color(color),
+    )
+}
+
+pub(crate) fn run_cat(
+    overrides: &Overrides,
+    rev: String,
+    path: PathBuf,
+    decrypt: bool,
+) -> Result<()> {
+    let (paths, config) = load_config(overrides)?;
+    let git = GitCliBackend::new();
+    let git_dir = &config.repo.git_dir;
+    let work_tree = &config.repo.work_tree;
+    let rel = resolve_rel_path(paths.home_dir(), &path)?;
+    let commit = git
+        .rev_parse(git_dir, work_tree, &format!("{rev}^{{commit}}"))
+        .with_context(|| format!("resolve {rev}"))?;
+
+    // Rules apply even while secrets are disabled: their plaintext is never printed as-is.
+    let secrets = SecretsManager::from_config(&config.secrets);
+    let rule = secrets
+        .rules()
+        .iter()
+        .find(|rule| secrets.plaintext_path(rule) == rel);
+    let mut stdout = std::io::stdout().lock();
+    let Some(rule) = rule else {
+        let blob = git
+            .show_blob(git_dir, work_tree, &commit, &rel)
+            .with_context(|| format!("read {} at {rev}", rel.display()))?;
+        stdout.write_all(&blob)?;
+        return Ok(());
+    };
+    if !decrypt {
+        return Err(anyhow!(
+            "{} is a secret; pass --decrypt to print its plaintext",
+            rel.display()
+        ));
+    }
+
+    let sidecar = secrets.ciphertext_path(rule);
+    let ciphertext = git
+        .show_blob(git_dir, work_tree, &commit, &sidecar)
+        .with_context(|| format!("read sidecar {} at {rev}", sidecar.display()))?;
+    let backend = AgeBackend::from_config(&config.secrets, work_tree)?;
+    backend.ensure_identities()?;
+    let plaintext = backend
+        .decrypt(&ciphertext)
+        .with_context(|| format!("decrypt {}", sidecar.display()))?;
+    if std::io::stdout().is_terminal() {
+        eprintln!(
+            "warning: printing the plaintext of secret {} to the terminal",
+            rel.display()
+        );
+    }
+    stdout.write_all(plaintext.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use std::fmt::Write as _;
+use std::io;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+
+pub const SERVICE_UNIT: &str = "hometree.service";
+pub const SOCKET_UNIT: &str = "hometree.socket";
+
+/// First file descriptor passed by systemd socket activation.
+const LISTEN_FDS_START: RawFd = 3;
+
+#[derive(Debug, Clone)]
+pub struct UnitOptions {
+    pub exe: PathBuf,
+    /// Socket path of the matching `.socket` unit, when socket activation is used.
+    pub socket: Option<PathBuf>,
+    pub harden: bool,
+    /// Directories the hardened service may write to; everything else is read-only.
+    pub writable: Vec<PathBuf>,
+}
+
+pub fn service_unit(opts: &UnitOptions) -> String {
+    let mut unit = String::from("[Unit]\nDescription=hometree daemon\n");
+    if opts.socket.is_some() {
+        let _ = writeln!(unit, "Requires={SOCKET_UNIT}\nAfter={SOCKET_UNIT}");
+    }
+    let _ = write!(
+        unit,
+        "\n[Service]\nType=simple\nExecStart={} daemon run\nRestart=on-failure\nRestartSec=2\n",
+        quote(&opts.exe.to_string_lossy())
+    );
+    // With socket activation the socket unit owns the runtime directory;
+    // RuntimeDirectory= would delete the socket whenever the service stops.
+    if opts.socket.is_none() {
+        unit.push_str("RuntimeDirectory=hometree\nRuntimeDirectoryMode=0700\n");
+    }
+    unit.push_str("ExecReload=/bin/kill -HUP $MAINPID\n");
+    if opts.harden {
+        unit.push_str(
+            "UMask=0077\n\
+             NoNewPrivileges=yes\n\
+             ProtectSystem=strict\n\
+             ProtectKernelTunables=yes\n\
+             ProtectKernelModules=yes\n\
+             ProtectKernelLogs=yes\n\
+             ProtectControlGroups=yes\n\
+             ProtectClock=yes\n\
+             ProtectHostname=yes\n\
+             RestrictNamespaces=yes\n\
+             RestrictRealtime=yes\n\
+             RestrictSUIDSGID=yes\n\
+             LockPersonality=yes\n\
+             MemoryDenyWriteExecute=yes\n\
+             SystemCallArchitectures=native\n\
+             RestrictAddressFamilies=AF_UNIX AF_INET AF_INET6\n",
+        );
+        for dir in &opts.writable {
+            // `-` skips directories that do not exist yet instead of failing the unit.
+            let _ = writeln!(
+                unit,
+                "ReadWritePaths={}",
+                quote(&format!("-{}", dir.display()))
+            );
+        }
+    }
+    if opts.socket.is_none() {
+        unit.push_str("\n[Install]\nWantedBy=default.target\n");
+    }
+    unit
+}
+
+pub fn socket_unit(socket: &Path) -> String {
+    format!(
+        "[Unit]\nDescription=hometree daemon socket\n\n[Socket]\nListenStream={}\nSocketMode=0600\nDirectoryMode=0700\n\n[Install]\nWantedBy=sockets.target\n",
+        quote(&socket.to_string_lossy())
+    )
+}
+
+fn quote(value: &str) -> String {
+    if value.contains(char::is_whitespace) || value.contains('"') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Take the listening socket passed by systemd, if this process was socket activated.
+/// Clears the activation variables so child processes do not pick them up.
+pub fn activated_listener() -> io::Result<Option<UnixListener>> {
+    let count = activation_fd_count(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+    for var in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        std::env::remove_var(var);
+    }
+    if count == 0 {
+        return Ok(None);
+    }
+    if count > 1 {
+        tracing::warn!("systemd passed {count} sockets; using the first");
+    }
+    // SAFETY: fcntl on a descriptor number has no memory-safety requirements.
+    if unsafe { libc::fcntl(LISTEN_FDS_START, libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: systemd hands over ownership of the descriptors starting at 3.
+    Ok(Some(unsafe { UnixListener::from_raw_fd(LISTEN_FDS_START) }))
+}
+
+/// Number of descriptors passed to `pid`, following sd_listen_fds(3).
+fn activation_fd_count(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> usize {
+    if listen_pid.and_then(|v| v.parse::<u32>().ok()) != Some(pid) {
+        return 0;
+    }
+    listen_fds.and_then(|v| v.parse().ok()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{activation_fd_count, service_unit, socket_unit, UnitOptions};
+    use std::path::PathBuf;
+
+    fn options() -> UnitOptions {
+        UnitOptions {
+            exe: PathBuf::from("/usr/bin/hometree"),
+            socket: None,
+            harden: false,
+            writable: vec![
+                PathBuf::from("/home/u"),
+                PathBuf::from("/srv/hometree state"),
+            ],
+        }
+    }
+
+    #[test]
+    fn activation_requires_matching_pid() {
+        assert_eq!(activation_fd_count(Some("42"), Some("1"), 42), 1);
+        assert_eq!(activation_fd_count(Some("41"), Some("1"), 42), 0);
+        assert_eq!(activation_fd_count(None, Some("1"), 42), 0);
+        assert_eq!(activation_fd_count(Some("42"), Some("x"), 42), 0);
+    }
+
+    #[test]
+    fn plain_service_is_enabled_directly() {
+        let unit = service_unit(&options());
+        assert!(unit.contains("ExecStart=/usr/bin/hometree daemon run\n"));
+        assert!(unit.contains("RuntimeDirectory=hometree\n"));
+        assert!(unit.contains("WantedBy=default.target"));
+        assert!(!unit.contains("ProtectSystem"));
+    }
+
+    #[test]
+    fn socket_activated_service_is_hardened() {
+        let mut opts = options();
+        opts.socket = Some(PathBuf::from("/run/user/1000/hometree/daemon.sock"));
+        opts.harden = true;
+        let unit = service_unit(&opts);
+        assert!(unit.contains("Requires=hometree.socket\n"));
+        assert!(!unit.contains("RuntimeDirectory="));
+        assert!(!unit.contains("[Install]"));
+        assert!(unit.contains("ProtectSystem=strict\n"));
+        assert!(unit.contains("ReadWritePaths=-/home/u\n"));
+        assert!(unit.contains("ReadWritePaths=\"-/srv/hometree state\"\n"));
+
+        let socket = socket_unit(opts.socket.as_deref().unwrap());
+        assert!(socket.contains("ListenStream=/run/user/1000/hometree/daemon.sock\n"));
+        assert!(socket.contains("WantedBy=sockets.target"));
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use hometree_core::config::TimeWindow;
+use time::OffsetDateTime;
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Why the daemon is holding queued changes back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleReason {
+    QuietHours,
+    OnBattery,
+    RateLimited,
+}
+
+impl fmt::Display for ThrottleReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::QuietHours => "quiet_hours",
+            Self::OnBattery => "on_battery",
+            Self::RateLimited => "rate_limited",
+        })
+    }
+}
+
+/// Allows at most `max` flushes in any sliding minute.
+#[derive(Debug)]
+pub struct RateLimit {
+    max: usize,
+    recent: VecDeque<Instant>,
+}
+
+impl RateLimit {
+    pub fn per_minute(max: u32) -> Self {
+        Self {
+            max: max.max(1) as usize,
+            recent: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, now: Instant) {
+        self.recent.push_back(now);
+    }
+
+    /// When the next flush may run, or `None` if it may run now.
+    pub fn blocked_until(&mut self, now: Instant) -> Option<Instant> {
+        while self
+            .recent
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= RATE_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+        if self.recent.len() < self.max {
+            return None;
+        }
+        self.recent.front().map(|t| *t + RATE_WINDOW)
+    }
+}
+
+/// End of the quiet-hours window `wall` falls in, if any.
+pub fn quiet_until(windows: &[TimeWindow], wall: OffsetDateTime) -> Option<OffsetDateTime> {
+    let minute = u16::from(wall.hour()) * 60 + u16::from(wall.minute());
+    let remaining = windows.iter().find_map(|w| w.remaining(minute))?;
+    let start_of_minute = wall.replace_second(0).ok()?.replace_nanosecond(0).ok()?;
+    Some(start_of_minute + time::Duration::minutes(remaining.into()))
+}
+
+/// Whether the machine is discharging a battery with no external power online.
+pub fn on_battery() -> bool {
+    on_battery_in(Path::new(POWER_SUPPLY_DIR))
+}
+
+fn on_battery_in(dir: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return false;
+    };
+    let read = |supply: &Path, name: &str| {
+        fs::read_to_string(supply.join(name))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut discharging = false;
+    for entry in entries.flatten() {
+        let supply = entry.path();
+        match read(&supply, "type").as_str() {
+            "Battery" => discharging |= read(&supply, "status") == "Discharging",
+            _ if read(&supply, "online") == "1" => return false,
+            _ => {}
+        }
+    }
+    discharging
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{on_battery_in, quiet_until, RateLimit};
+    use std::fs;
+    use std::time::{Duration, Instant};
+    use time::macros::datetime;
+
+    #[test]
+    fn rate_limit_uses_a_sliding_minute() {
+        let mut limit = RateLimit::per_minute(2);
+        let start = Instant::now();
+        assert_eq!(limit.blocked_until(start), None);
+        limit.record(start);
+        limit.record(start + Duration::from_secs(10));
+        assert_eq!(
+            limit.blocked_until(start + Duration::from_secs(20)),
+            Some(start + Duration::from_secs(60))
+        );
+        assert_eq!(limit.blocked_until(start + Duration::from_secs(60)), None);
+    }
+
+    #[test]
+    fn quiet_hours_end_at_window_end() {
+        let windows = vec!["22:00-07:00".parse().unwrap()];
+        assert_eq!(
+            quiet_until(&windows, datetime!(2024-03-01 23:15:30 UTC)),
+            Some(datetime!(2024-03-02 07:00 UTC))
+        );
+        assert_eq!(quiet_until(&windows, datetime!(2024-03-01 12:00 UTC)), None);
+    }
+
+    #[test]
+    fn battery_counts_only_without_external_power() {
+        let temp = tempfile::tempdir().unwrap();
+        let supply = |name: &str, files: &[(&str, &str)]| {
+            let dir = temp.path().join(name);
+            fs::create_dir_all(&dir).unwrap();
+            for (file, value) in files {
+                fs::write(dir.join(file), format!("{value}\n")).unwrap();
+            }
+        };
+        assert!(!on_battery_in(temp.path()));
+        supply("BAT0", &[("type", "Battery"), ("status", "Discharging")]);
+        assert!(on_battery_in(temp.path()));
+        supply("AC", &[("type", "Mains"), ("online", "1")]);
+        assert!(!on_battery_in(temp.path()));
+    }
+}
@@ -0,0 +1,573 @@
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use hometree_cli::interactive::{
+    clean_message, display_line, managed_root, parse_diff, FilePatch, MESSAGE_HELP,
+};
+use hometree_core::git::{AddMode, DiffSpec, GitBackend, GitCliBackend};
+use hometree_core::secrets::SecretsManager;
+use hometree_core::{Config, Effects, Paths};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::daemon::DaemonInhibitGuard;
+use crate::{
+    auto_snapshot_message, commit_staged, convert_to_secret, load_config, managed_statuses,
+    untrack_paths, with_lock, Overrides,
+};
+
+const HELP: &str = "j/k move  tab files/hunks  s stage  u unstage  x untrack  e make secret  \
+                    c commit  r refresh  q quit";
+
+pub(crate) fn run_ui(overrides: &Overrides, message: Option<String>) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        return Err(anyhow!("the interactive UI needs a terminal"));
+    }
+    let (paths, config) = load_config(overrides)?;
+    // The daemon would otherwise `git add -u` behind the selection being built.
+    let _inhibit = DaemonInhibitGuard::new(&paths, "ui", Duration::from_secs(3600))?;
+    let mut app = App::new(paths, config, message.unwrap_or_default())?;
+
+    let mut terminal = ratatui::init();
+    let outcome = app.run(&mut terminal);
+    ratatui::restore();
+
+    match outcome? {
+        Some(message) => commit_staged(&app.paths, &app.config, &app.git, &message),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    path: String,
+    index: char,
+    worktree: char,
+    root: String,
+    sidecar: bool,
+}
+
+impl Entry {
+    fn untracked(&self) -> bool {
+        self.worktree == '?'
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Files,
+    Hunks,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pending {
+    Untrack,
+    Secret,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    Browse,
+    Confirm(Pending),
+}
+
+/// Diff of the selected file: work tree against the index, then index against HEAD.
+#[derive(Debug, Default)]
+struct Preview {
+    unstaged: Option<FilePatch>,
+    staged: Option<FilePatch>,
+    note: Option<String>,
+}
+
+impl Preview {
+    /// Every hunk in display order, tagged with whether it is already staged.
+    fn hunks(&self) -> Vec<(bool, usize)> {
+        let count = |patch: &Option<FilePatch>| patch.as_ref().map_or(0, |p| p.hunks.len());
+        (0..count(&self.unstaged))
+            .map(|i| (false, i))
+            .chain((0..count(&self.staged)).map(|i| (true, i)))
+            .collect()
+    }
+}
+
+struct App {
+    paths: Paths,
+    config: Config,
+    git: GitCliBackend,
+    secrets: SecretsManager,
+    entries: Vec<Entry>,
+    selected: usize,
+    preview: Preview,
+    hunk: usize,
+    focus: Focus,
+    mode: Mode,
+    draft: String,
+    status: String,
+}
+
+impl App {
+    fn new(paths: Paths, config: Config, draft: String) -> Result<Self> {
+        let secrets = SecretsManager::from_config(&config.secrets);
+        let mut app = Self {
+            paths,
+            config,
+            git: GitCliBackend::new(),
+            secrets,
+            entries: Vec::new(),
+            selected: 0,
+            preview: Preview::default(),
+            hunk: 0,
+            focus: Focus::Files,
+            mode: Mode::Browse,
+            draft,
+            status: HELP.to_string(),
+        };
+        app.refresh()?;
+        Ok(app)
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<Option<String>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match self.mode.clone() {
+                Mode::Browse => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                    KeyCode::Char('c') => {
+                        let message = self.compose_message(terminal);
+                        terminal.clear()?;
+                        match message {
+                            Ok(Some(message)) => return Ok(Some(message)),
+                            Ok(None) => self.status = "empty message; not committed".to_string(),
+                            Err(err) => self.status = format!("error: {err:#}"),
+                        }
+                    }
+                    code => {
+                        self.report(|app| app.browse_key(code));
+                        // git may have written to the terminal behind the UI.
+                        if matches!(code, KeyCode::Char('s' | 'u' | 'r')) {
+                            terminal.clear()?;
+                        }
+                    }
+                },
+                Mode::Confirm(pending) => {
+                    self.mode = Mode::Browse;
+                    if key.code == KeyCode::Char('y') {
+                        self.report(|app| app.confirm(pending));
+                        terminal.clear()?;
+                    } else {
+                        self.status = "cancelled".to_string();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Run an action and show its error, if any, instead of leaving the UI.
+    fn report(&mut self, action: impl FnOnce(&mut Self) -> Result<()>) {
+        if let Err(err) = action(self) {
+            self.status = format!("error: {err:#}");
+        }
+    }
+
+    fn browse_key(&mut self, code: KeyCode) -> Result<()> {
+        match code {
+            KeyCode::Down | KeyCode::Char('j') => self.step(1)?,
+            KeyCode::Up | KeyCode::Char('k') => self.step(-1)?,
+            KeyCode::Tab | KeyCode::Enter => {
+                self.focus = match self.focus {
+                    Focus::Files if !self.preview.hunks().is_empty() => Focus::Hunks,
+                    _ => Focus::Files,
+                };
+            }
+            KeyCode::Char('s') => self.stage(false)?,
+            KeyCode::Char('u') => self.stage(true)?,
+            KeyCode::Char('x') => self.ask(Pending::Untrack)?,
+            KeyCode::Char('e') => self.ask(Pending::Secret)?,
+            KeyCode::Char('r') => {
+                self.refresh()?;
+                self.status = HELP.to_string();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn current(&self) -> Option<&Entry> {
+        self.entries.get(self.selected)
+    }
+
+    fn step(&mut self, delta: isize) -> Result<()> {
+        let (position, len) = match self.focus {
+            Focus::Files => (self.selected, self.entries.len()),
+            Focus::Hunks => (self.hunk, self.preview.hunks().len()),
+        };
+        if len == 0 {
+            return Ok(());
+        }
+        let next = position.saturating_add_signed(delta).min(len - 1);
+        match self.focus {
+            Focus::Files if next != self.selected => {
+                self.selected = next;
+                self.load_preview()?;
+            }
+            Focus::Files => {}
+            Focus::Hunks => self.hunk = next,
+        }
+        Ok(())
+    }
+
+    /// Stage (or with `unstage`, unstage) the selected file or hunk.
+    fn stage(&mut self, unstage: bool) -> Result<()> {
+        let Some(entry) = self.current().cloned() else {
+            return Ok(());
+        };
+        let git_dir = &self.config.repo.git_dir;
+        let work_tree = &self.config.repo.work_tree;
+        let rel = [PathBuf::from(&entry.path)];
+        match self.focus {
+            Focus::Files if unstage => {
+                with_lock(&self.paths, || {
+                    self.git
+                        .unstage(git_dir, work_tree, &rel)
+                        .context("git reset")
+                })?;
+                self.status = format!("unstaged {}", entry.path);
+            }
+            Focus::Files => {
+                with_lock(&self.paths, || {
+                    self.git
+                        .add(git_dir, work_tree, &rel, AddMode::Paths)
+                        .context("git add")
+                })?;
+                self.status = format!("staged {}", entry.path);
+            }
+            Focus::Hunks => {
+                let Some(&(staged, index)) = self.preview.hunks().get(self.hunk) else {
+                    return Ok(());
+                };
+                if staged != unstage {
+                    self.status = if staged {
+                        "hunk is already staged".to_string()
+                    } else {
+                        "hunk is not staged".to_string()
+                    };
+                    return Ok(());
+                }
+                let patch = if staged {
+                    &self.preview.staged
+                } else {
+                    &self.preview.unstaged
+                };
+                let patch = patch
+                    .as_ref()
+                    .and_then(|patch| patch.hunk_patch(index))
+                    .ok_or_else(|| anyhow!("hunk is gone; press r to refresh"))?;
+                with_lock(&self.paths, || {
+                    self.git
+                        .apply_cached(git_dir, work_tree, &patch, unstage)
+                        .context("git apply --cached")
+                })?;
+                self.status = if unstage {
+                    "unstaged hunk".to_string()
+                } else {
+                    "staged hunk".to_string()
+                };
+            }
+        }
+        self.refresh()
+    }
+
+    fn ask(&mut self, pending: Pending) -> Result<()> {
+        let Some(entry) = self.current() else {
+            return Ok(());
+        };
+        let path = entry.path.clone();
+        if pending == Pending::Secret {
+            if entry.sidecar {
+                return Err(anyhow!("{path} is already a secret sidecar"));
+            }
+            let in_history = self
+                .git
+                .file_in_history(
+                    &self.config.repo.git_dir,
+                    &self.config.repo.work_tree,
+                    Path::new(&path),
+                )
+                .unwrap_or(false);
+            if in_history {
+                return Err(anyhow!(
+                    "{path} is in git history as plaintext; run `hometree secret add {path}` \
+                     to purge it"
+                ));
+            }
+        }
+        let verb = match pending {
+            Pending::Untrack => "untrack",
+            Pending::Secret => "convert to a secret",
+        };
+        self.status = format!("{verb} {path}? [y/N]");
+        self.mode = Mode::Confirm(pending);
+        Ok(())
+    }
+
+    fn confirm(&mut self, pending: Pending) -> Result<()> {
+        let Some(entry) = self.current().cloned() else {
+            return Ok(());
+        };
+        let rel = PathBuf::from(&entry.path);
+        match pending {
+            Pending::Untrack => {
+                untrack_paths(
+                    &self.paths,
+                    &mut self.config,
+                    vec![rel],
+                    &Effects::default(),
+                )?;
+                self.status = format!("untracked {}", entry.path);
+            }
+            Pending::Secret => {
+                convert_to_secret(
+                    &self.paths,
+                    &mut self.config,
+                    &self.git,
+                    &rel,
+                    false,
+                    &Effects::default(),
+                )?;
+                self.secrets = SecretsManager::from_config(&self.config.secrets);
+                self.status = format!("{} is now a secret; its sidecar is staged", entry.path);
+            }
+        }
+        self.refresh()
+    }
+
+    /// Write the commit message in `$EDITOR` (`vi` when unset), as `git commit`
+    /// does; `None` when it was left empty.
+    fn compose_message(&mut self, terminal: &mut DefaultTerminal) -> Result<Option<String>> {
+        let staged = self
+            .git
+            .staged_paths(&self.config.repo.git_dir, &self.config.repo.work_tree)
+            .context("list staged paths")?;
+        if staged.is_empty() {
+            return Err(anyhow!("nothing staged"));
+        }
+        let mut draft = self.draft.clone();
+        if draft.is_empty() {
+            if let Some(template) = &self.config.snapshot.auto_message_template {
+                draft = auto_snapshot_message(template, &staged);
+            }
+        }
+        let file = self.config.repo.git_dir.join("COMMIT_EDITMSG");
+        std::fs::write(&file, format!("{draft}\n{MESSAGE_HELP}"))
+            .with_context(|| format!("write {}", file.display()))?;
+
+        let editor = std::env::var("EDITOR")
+            .ok()
+            .filter(|editor| !editor.trim().is_empty())
+            .unwrap_or_else(|| "vi".to_string());
+        ratatui::restore();
+        // Through the shell so that editors with arguments (`code --wait`) work.
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(format!("{editor} \"$1\""))
+            .arg(&editor)
+            .arg(&file)
+            .status();
+        *terminal = ratatui::init();
+        let status = status.with_context(|| format!("run editor '{editor}'"))?;
+        if !status.success() {
+            return Err(anyhow!("editor '{editor}' exited with {status}"));
+        }
+
+        let text =
+            std::fs::read_to_string(&file).with_context(|| format!("read {}", file.display()))?;
+        let message = clean_message(&text);
+        if message.is_empty() {
+            return Ok(None);
+        }
+        self.draft = message.clone();
+        Ok(Some(message))
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        let previous = self.current().map(|entry| entry.path.clone());
+        let statuses = managed_statuses(&self.paths, &self.config)?;
+        let mut entries: Vec<Entry> = statuses
+            .into_iter()
+            .map(|status| {
+                let rel = Path::new(&status.path);
+                Entry {
+                    root: managed_root(rel, &self.config.manage.paths)
+                        .unwrap_or("other")
+                        .to_string(),
+                    sidecar: self.secrets.enabled() && self.secrets.is_ciphertext_path(rel),
+                    path: status.path,
+                    index: status.index_status,
+                    worktree: status.worktree_status,
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| (&a.root, &a.path).cmp(&(&b.root, &b.path)));
+        self.entries = entries;
+        self.selected = previous
+            .and_then(|path| self.entries.iter().position(|entry| entry.path == path))
+            .unwrap_or(self.selected)
+            .min(self.entries.len().saturating_sub(1));
+        self.load_preview()
+    }
+
+    fn load_preview(&mut self) -> Result<()> {
+        self.preview = Preview::default();
+        let Some(entry) = self.current().cloned() else {
+            self.focus = Focus::Files;
+            return Ok(());
+        };
+        let rel = PathBuf::from(&entry.path);
+        if self.secrets.is_secret_plaintext(&rel) {
+            self.preview.note = Some("secret plaintext is never shown".to_string());
+        } else if entry.sidecar {
+            self.preview.note = Some("secret sidecar; contents not shown".to_string());
+        } else if entry.untracked() {
+            self.preview.note = Some("untracked; s stages the whole file".to_string());
+        } else {
+            self.preview.unstaged = self.file_patch(&rel, false)?;
+            self.preview.staged = self.file_patch(&rel, true)?;
+        }
+        let hunks = self.preview.hunks().len();
+        if hunks == 0 {
+            self.focus = Focus::Files;
+        }
+        self.hunk = self.hunk.min(hunks.saturating_sub(1));
+        Ok(())
+    }
+
+    fn file_patch(&self, rel: &Path, cached: bool) -> Result<Option<FilePatch>> {
+        let spec = DiffSpec { cached, rev: None };
+        let output = self
+            .git
+            .diff(
+                &self.config.repo.git_dir,
+                &self.config.repo.work_tree,
+                &spec,
+                &[rel.to_path_buf()],
+                &["--color=never", "--no-ext-diff"],
+            )
+            .context("git diff")?;
+        Ok(parse_diff(&output).into_iter().next())
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(3)]).areas(frame.area());
+        let [files, preview] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+        self.draw_files(frame, files);
+        self.draw_preview(frame, preview);
+
+        frame.render_widget(
+            Paragraph::new(self.status.clone()).block(Block::default().borders(Borders::ALL)),
+            footer,
+        );
+    }
+
+    fn draw_files(&self, frame: &mut Frame, area: Rect) {
+        let mut items = Vec::new();
+        let mut selected_row = None;
+        let mut root = None;
+        for (i, entry) in self.entries.iter().enumerate() {
+            if root != Some(&entry.root) {
+                root = Some(&entry.root);
+                items.push(ListItem::new(Line::styled(
+                    entry.root.clone(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )));
+            }
+            if i == self.selected {
+                selected_row = Some(items.len());
+            }
+            items.push(ListItem::new(Line::from(vec![
+                Span::styled(entry.index.to_string(), Style::default().fg(Color::Green)),
+                Span::styled(entry.worktree.to_string(), Style::default().fg(Color::Red)),
+                Span::raw(format!(" {}", entry.path)),
+            ])));
+        }
+        if items.is_empty() {
+            items.push(ListItem::new("clean"));
+        }
+        let highlight = match self.focus {
+            Focus::Files => Style::default().add_modifier(Modifier::REVERSED),
+            Focus::Hunks => Style::default().add_modifier(Modifier::BOLD),
+        };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("status"))
+            .highlight_style(highlight);
+        let mut state = ListState::default().with_selected(selected_row);
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_preview(&self, frame: &mut Frame, area: Rect) {
+        let mut lines = Vec::new();
+        let mut selected_line = 0;
+        if let Some(note) = &self.preview.note {
+            lines.push(Line::raw(note.clone()));
+        }
+        let hunks = self.preview.hunks();
+        for (staged, patch) in [
+            (false, &self.preview.unstaged),
+            (true, &self.preview.staged),
+        ] {
+            let Some(patch) = patch else {
+                continue;
+            };
+            let title = if staged { "staged" } else { "unstaged" };
+            lines.push(Line::styled(
+                title,
+                Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            ));
+            if patch.is_binary() || patch.hunks.is_empty() {
+                lines.push(Line::raw("binary or mode-only change"));
+            }
+            for (index, hunk) in patch.hunks.iter().enumerate() {
+                let current =
+                    self.focus == Focus::Hunks && hunks.get(self.hunk) == Some(&(staged, index));
+                if current {
+                    selected_line = lines.len();
+                }
+                let mut style = Style::default().fg(Color::Cyan);
+                if current {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                lines.push(Line::styled(display_line(&hunk.header), style));
+                for line in &hunk.lines {
+                    let color = match line.first() {
+                        Some(b'+') => Color::Green,
+                        Some(b'-') => Color::Red,
+                        _ => Color::Reset,
+                    };
+                    lines.push(Line::styled(display_line(line), Style::default().fg(color)));
+                }
+            }
+        }
+        let scroll = u16::try_from(selected_line.saturating_sub(2)).unwrap_or(u16::MAX);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title("diff"))
+                .scroll((scroll, 0)),
+            area,
+        );
+    }
+}
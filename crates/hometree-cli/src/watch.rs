@@ -1,8 +1,12 @@
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use globset::GlobSet;
+use hometree_core::config::WatchConfig;
 use hometree_core::{Config, ManagedSet, SecretsManager};
-use notify::EventKind;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind};
 
 pub fn should_handle_event(kind: &EventKind) -> bool {
     matches!(
@@ -11,6 +15,46 @@ pub fn should_handle_event(kind: &EventKind) -> bool {
     )
 }
 
+/// Source and destination of a rename whose two halves were matched up.
+pub fn rename_pair(event: &Event) -> Option<(&Path, &Path)> {
+    match (&event.kind, event.paths.as_slice()) {
+        (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) => {
+            Some((from.as_path(), to.as_path()))
+        }
+        _ => None,
+    }
+}
+
+/// Queue a rename, folding chains like `a -> b -> c` into `a -> c`.
+pub fn queue_rename(renames: &mut Vec<(PathBuf, PathBuf)>, from: PathBuf, to: PathBuf) {
+    match renames.iter().position(|(_, dest)| *dest == from) {
+        Some(index) => {
+            let (origin, _) = renames.remove(index);
+            if origin != to {
+                renames.push((origin, to));
+            }
+        }
+        None => renames.push((from, to)),
+    }
+}
+
+/// Pair each index entry at or below `from` with where it lives under `to`.
+pub fn moved_entries(from: &Path, to: &Path, tracked: &[String]) -> Vec<(PathBuf, PathBuf)> {
+    tracked
+        .iter()
+        .filter_map(|entry| {
+            let old = PathBuf::from(entry);
+            let suffix = old.strip_prefix(from).ok()?;
+            let new = if suffix.as_os_str().is_empty() {
+                to.to_path_buf()
+            } else {
+                to.join(suffix)
+            };
+            Some((old, new))
+        })
+        .collect()
+}
+
 pub fn watch_paths(config: &Config) -> Vec<PathBuf> {
     let mut set = std::collections::BTreeSet::new();
     for entry in &config.manage.paths {
@@ -26,6 +70,133 @@ pub fn watch_paths(config: &Config) -> Vec<PathBuf> {
     set.into_iter().map(PathBuf::from).collect()
 }
 
+/// Directory to watch for a glob root, relative to home.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WatchTarget {
+    pub path: PathBuf,
+    pub recursive: bool,
+}
+
+/// `manage.paths` entries that contain glob metacharacters.
+pub fn glob_roots(config: &Config) -> Vec<String> {
+    config
+        .manage
+        .paths
+        .iter()
+        .map(|entry| entry.trim_start_matches("./"))
+        .filter(|entry| !entry.is_empty() && has_glob_meta(entry))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether `pattern` starts with `**`, which would make the home directory
+/// itself a recursive watch.
+pub fn recursive_at_root(pattern: &str) -> bool {
+    pattern
+        .split('/')
+        .find(|c| !c.is_empty() && *c != ".")
+        .is_some_and(|first| first == "**")
+}
+
+/// Expand glob roots into the directories that have to be watched.
+///
+/// Watching starts at the longest literal prefix (or its nearest existing
+/// ancestor). Each wildcard level is watched non-recursively so that matching
+/// directories appearing later are noticed; a `**` component switches to a
+/// recursive watch. Wildcard components only descend one directory level.
+///
+/// Directories at or below one of `watch_roots` are left out: the root's
+/// recursive watch already covers them, and a second, non-recursive watch on
+/// the same path would replace it.
+pub fn glob_watch_targets(
+    home_dir: &Path,
+    patterns: &[String],
+    watch_roots: &[PathBuf],
+) -> BTreeSet<WatchTarget> {
+    let mut targets = BTreeSet::new();
+    for pattern in patterns {
+        expand_glob_pattern(home_dir, pattern, &mut targets);
+    }
+    targets.retain(|target| !watch_roots.iter().any(|root| target.path.starts_with(root)));
+    targets
+}
+
+fn expand_glob_pattern(home_dir: &Path, pattern: &str, targets: &mut BTreeSet<WatchTarget>) {
+    let components: Vec<&str> = pattern
+        .trim_end_matches('/')
+        .split('/')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect();
+    let literal_len = components
+        .iter()
+        .take(components.len().saturating_sub(1))
+        .take_while(|c| !has_glob_meta(c))
+        .count();
+
+    let prefix: PathBuf = components[..literal_len].iter().collect();
+    if !home_dir.join(&prefix).is_dir() {
+        let mut ancestor = prefix.as_path();
+        while let Some(parent) = ancestor.parent() {
+            ancestor = parent;
+            if home_dir.join(ancestor).is_dir() {
+                break;
+            }
+        }
+        targets.insert(WatchTarget {
+            path: ancestor.to_path_buf(),
+            recursive: false,
+        });
+        return;
+    }
+
+    let mut frontier = vec![prefix];
+    let rest = &components[literal_len..];
+    for (idx, component) in rest.iter().enumerate() {
+        let recursive = *component == "**";
+        for dir in &frontier {
+            targets.insert(WatchTarget {
+                path: dir.clone(),
+                recursive,
+            });
+        }
+        if recursive || idx + 1 == rest.len() {
+            return;
+        }
+        frontier = expand_component(home_dir, &frontier, component);
+        if frontier.is_empty() {
+            return;
+        }
+    }
+}
+
+fn expand_component(home_dir: &Path, frontier: &[PathBuf], component: &str) -> Vec<PathBuf> {
+    if !has_glob_meta(component) {
+        return frontier
+            .iter()
+            .map(|dir| dir.join(component))
+            .filter(|dir| home_dir.join(dir).is_dir())
+            .collect();
+    }
+    let matcher = match globset::Glob::new(component) {
+        Ok(glob) => glob.compile_matcher(),
+        Err(_) => return Vec::new(),
+    };
+    let mut out = Vec::new();
+    for dir in frontier {
+        let Ok(entries) = std::fs::read_dir(home_dir.join(dir)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            if is_dir && matcher.is_match(entry.file_name()) {
+                out.push(dir.join(entry.file_name()));
+            }
+        }
+    }
+    out.sort();
+    out
+}
+
 pub fn root_to_pathspec(root: &str) -> String {
     let trimmed = root.trim_start_matches("./");
     if trimmed.is_empty() {
@@ -150,16 +321,70 @@ pub fn build_allowlist(patterns: &[String]) -> anyhow::Result<GlobSet> {
     Ok(builder.build()?)
 }
 
+/// Shortest debounce window the daemon applies, whatever the config says.
+const MIN_DEBOUNCE_MS: u64 = 50;
+
+/// Debounce windows from `[watch]`: the first matching override wins,
+/// otherwise `debounce_ms` applies.
+pub struct DebounceWindows {
+    default: Duration,
+    overrides: GlobSet,
+    windows: Vec<(String, Duration)>,
+}
+
+impl DebounceWindows {
+    pub fn from_config(watch: &WatchConfig) -> anyhow::Result<Self> {
+        let mut builder = globset::GlobSetBuilder::new();
+        let mut windows = Vec::new();
+        for entry in &watch.debounce_overrides {
+            builder.add(globset::Glob::new(entry.pattern.trim())?);
+            windows.push((entry.pattern.clone(), debounce_window(entry.debounce_ms)));
+        }
+        Ok(Self {
+            default: debounce_window(watch.debounce_ms),
+            overrides: builder.build()?,
+            windows,
+        })
+    }
+
+    pub fn default_window(&self) -> Duration {
+        self.default
+    }
+
+    /// The window for `rel`, with the override pattern that chose it.
+    pub fn window_for(&self, rel: &Path) -> (Duration, Option<&str>) {
+        match self.overrides.matches(rel).first() {
+            Some(&index) => {
+                let (pattern, window) = &self.windows[index];
+                (*window, Some(pattern))
+            }
+            None => (self.default, None),
+        }
+    }
+}
+
+fn debounce_window(ms: u64) -> Duration {
+    Duration::from_millis(ms.max(MIN_DEBOUNCE_MS))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{build_allowlist, collect_watch_decisions, decide_watch_action, WatchAction};
-    use hometree_core::config::SecretRule;
+    use super::{
+        build_allowlist, collect_watch_decisions, decide_watch_action, glob_watch_targets,
+        moved_entries, queue_rename, recursive_at_root, rename_pair, DebounceWindows, WatchAction,
+        WatchTarget,
+    };
+    use hometree_core::config::{DebounceOverride, SecretRule};
     use hometree_core::{Config, ManagedSet, Paths, SecretsManager};
+    use notify::event::{ModifyKind, RenameMode};
+    use notify::{Event, EventKind};
     use std::path::Path;
     use std::path::PathBuf;
+    use std::time::Duration;
     use tempfile::TempDir;
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn allowlist_matches_expected_patterns() {
         let list = build_allowlist(&vec![".config/**".to_string(), ".local/bin/*".to_string()])
             .expect("allowlist");
@@ -169,6 +394,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn decide_watch_actions_for_secrets() {
         let temp = TempDir::new().expect("temp");
         let home = temp.path().join("home");
@@ -234,6 +460,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn watch_decisions_produce_staging_lists() {
         let temp = TempDir::new().expect("temp");
         let home = temp.path().join("home");
@@ -279,4 +506,155 @@ mod tests {
             .managed_stage
             .contains(Path::new(".local/share/other.txt")));
     }
+
+    #[test]
+    fn rename_pairs_come_from_matched_events_only() {
+        let both = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(PathBuf::from("/h/a"))
+            .add_path(PathBuf::from("/h/b"));
+        assert_eq!(
+            rename_pair(&both),
+            Some((Path::new("/h/a"), Path::new("/h/b")))
+        );
+        let from = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::From)))
+            .add_path(PathBuf::from("/h/a"));
+        assert_eq!(rename_pair(&from), None);
+    }
+
+    #[test]
+    fn queued_rename_chains_collapse() {
+        let mut renames = Vec::new();
+        queue_rename(&mut renames, PathBuf::from("a"), PathBuf::from("b"));
+        queue_rename(&mut renames, PathBuf::from("b"), PathBuf::from("c"));
+        assert_eq!(renames, vec![(PathBuf::from("a"), PathBuf::from("c"))]);
+        queue_rename(&mut renames, PathBuf::from("c"), PathBuf::from("a"));
+        assert!(renames.is_empty());
+    }
+
+    #[test]
+    fn moved_entries_follow_directory_moves() {
+        let tracked = vec![
+            ".config/app/a.toml".to_string(),
+            ".config/app/sub/b.toml".to_string(),
+        ];
+        assert_eq!(
+            moved_entries(
+                Path::new(".config/app"),
+                Path::new(".config/tool"),
+                &tracked
+            ),
+            vec![
+                (
+                    PathBuf::from(".config/app/a.toml"),
+                    PathBuf::from(".config/tool/a.toml")
+                ),
+                (
+                    PathBuf::from(".config/app/sub/b.toml"),
+                    PathBuf::from(".config/tool/sub/b.toml")
+                ),
+            ]
+        );
+        let file = vec![".zshrc".to_string()];
+        assert_eq!(
+            moved_entries(Path::new(".zshrc"), Path::new(".zshrc.d/main"), &file),
+            vec![(PathBuf::from(".zshrc"), PathBuf::from(".zshrc.d/main"))]
+        );
+    }
+
+    fn target(path: &str, recursive: bool) -> WatchTarget {
+        WatchTarget {
+            path: PathBuf::from(path),
+            recursive,
+        }
+    }
+
+    #[test]
+    fn glob_targets_watch_each_wildcard_level() {
+        let temp = TempDir::new().expect("temp");
+        let home = temp.path();
+        std::fs::create_dir_all(home.join(".config/alpha")).unwrap();
+        std::fs::create_dir_all(home.join(".config/beta")).unwrap();
+        std::fs::write(home.join(".config/file.txt"), "x").unwrap();
+
+        let targets = glob_watch_targets(home, &[".config/*/settings.json".to_string()], &[]);
+        let expected: Vec<WatchTarget> = vec![
+            target(".config", false),
+            target(".config/alpha", false),
+            target(".config/beta", false),
+        ];
+        assert_eq!(targets.into_iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn glob_targets_recurse_at_double_star() {
+        let temp = TempDir::new().expect("temp");
+        let home = temp.path();
+        std::fs::create_dir_all(home.join(".config/app/themes")).unwrap();
+
+        let targets = glob_watch_targets(home, &[".config/*/themes/**".to_string()], &[]);
+        assert!(targets.contains(&target(".config", false)));
+        assert!(targets.contains(&target(".config/app", false)));
+        assert!(targets.contains(&target(".config/app/themes", true)));
+        assert_eq!(targets.len(), 3);
+    }
+
+    #[test]
+    fn glob_targets_fall_back_to_existing_ancestor() {
+        let temp = TempDir::new().expect("temp");
+        let home = temp.path();
+        std::fs::create_dir_all(home.join(".local")).unwrap();
+
+        let targets = glob_watch_targets(home, &[".local/share/app/*.conf".to_string()], &[]);
+        assert_eq!(
+            targets.into_iter().collect::<Vec<_>>(),
+            vec![target(".local", false)]
+        );
+
+        let targets = glob_watch_targets(home, &["*.conf".to_string()], &[]);
+        assert_eq!(
+            targets.into_iter().collect::<Vec<_>>(),
+            vec![target("", false)]
+        );
+    }
+
+    #[test]
+    fn leading_double_star_is_recursive_at_root() {
+        assert!(recursive_at_root("**/*.toml"));
+        assert!(recursive_at_root("./**"));
+        assert!(!recursive_at_root(".config/**"));
+        assert!(!recursive_at_root("*/app/**"));
+        assert!(!recursive_at_root("*.conf"));
+    }
+
+    #[test]
+    fn debounce_windows_match_daemon_clamping() {
+        let temp = TempDir::new().expect("temp");
+        let paths = Paths::new_with_overrides(Some(temp.path()), Some(temp.path())).expect("paths");
+        let mut config = Config::default_with_paths(&paths);
+        config.watch.debounce_ms = 10;
+        config.watch.debounce_overrides = vec![
+            DebounceOverride {
+                pattern: " .config/fast/** ".to_string(),
+                debounce_ms: 0,
+            },
+            DebounceOverride {
+                pattern: ".config/**".to_string(),
+                debounce_ms: 5_000,
+            },
+        ];
+        let windows = DebounceWindows::from_config(&config.watch).unwrap();
+
+        assert_eq!(
+            windows.window_for(Path::new(".config/fast/a")),
+            (Duration::from_millis(50), Some(" .config/fast/** "))
+        );
+        assert_eq!(
+            windows.window_for(Path::new(".config/app.toml")),
+            (Duration::from_secs(5), Some(".config/**"))
+        );
+        assert_eq!(
+            windows.window_for(Path::new(".bashrc")),
+            (Duration::from_millis(50), None)
+        );
+    }
 }
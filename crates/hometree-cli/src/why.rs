@@ -0,0 +1,220 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use hometree_cli::track::decide_track;
+use hometree_cli::watch::{build_allowlist, decide_watch_action, DebounceWindows, WatchAction};
+use hometree_core::git::{GitBackend, GitCliBackend};
+use hometree_core::managed_set::PatternMatch;
+use hometree_core::secrets::SecretsManager;
+use hometree_core::ManagedSet;
+
+use crate::{load_config, resolve_rel_path, Overrides};
+
+pub(crate) fn run_why(overrides: &Overrides, input: PathBuf) -> Result<()> {
+    let (paths, config) = load_config(overrides)?;
+    let home = paths.home_dir();
+    let rel = resolve_rel_path(home, &input)?;
+    let managed = ManagedSet::from_config(&config, home).context("build managed set")?;
+    let secrets = SecretsManager::from_config(&config.secrets);
+    let explanation = managed.explain(&rel);
+
+    println!("path: {}", rel.display());
+    println!("exists: {}", fs_kind(&home.join(&rel)).unwrap_or("missing"));
+
+    println!("manage.paths:");
+    if explanation.paths.is_empty() {
+        println!("  (none)");
+    }
+    for (entry, result) in config.manage.paths.iter().zip(&explanation.paths) {
+        if *entry == result.pattern {
+            println!("  {} {entry}", mark(result));
+        } else {
+            println!("  {} {entry} (as {})", mark(result), result.pattern);
+        }
+    }
+    print_patterns("ignore.patterns", &explanation.ignore);
+    print_patterns("denylist", &explanation.denylist);
+
+    let rule = secrets
+        .rules()
+        .iter()
+        .find(|rule| secrets.plaintext_path(rule) == rel || secrets.ciphertext_path(rule) == rel);
+    let secret = match rule {
+        Some(rule) if secrets.plaintext_path(rule) == rel => format!(
+            "plaintext of a secret rule; only its sidecar {} is stored",
+            secrets.ciphertext_path(rule).display()
+        ),
+        Some(rule) => format!(
+            "sidecar of secret {}",
+            secrets.plaintext_path(rule).display()
+        ),
+        None if secrets.is_ciphertext_path(&rel) => {
+            "has the sidecar suffix but no secret rule".to_string()
+        }
+        None => "no".to_string(),
+    };
+    println!("secret: {secret}");
+
+    let excluded_by = explanation
+        .ignore
+        .iter()
+        .map(|m| ("ignore pattern", m))
+        .chain(explanation.denylist.iter().map(|m| ("denylist pattern", m)))
+        .find(|(_, m)| m.matched);
+    let is_managed = managed.is_managed(&rel);
+    if is_managed {
+        println!("managed: yes");
+    } else if let Some((source, m)) = excluded_by {
+        println!("managed: no, excluded by {source} '{}'", m.pattern);
+    } else {
+        println!("managed: no, no manage.paths entry matches");
+    }
+
+    let track = if secrets.is_secret_plaintext(&rel) {
+        "refused, use `hometree secret add`".to_string()
+    } else {
+        match decide_track(&rel, home, &managed, false) {
+            Ok(decision) if decision.add_to_paths => "would add it to manage.paths".to_string(),
+            Ok(_) => "would stage it (already covered by manage.paths)".to_string(),
+            Err(_) => "refused without --force (ignored or denylisted)".to_string(),
+        }
+    };
+    println!("track: {track}");
+
+    let watch = &config.watch;
+    let allowlist = build_allowlist(&watch.auto_add_allow_patterns)?;
+    let has_allowlist = watch
+        .auto_add_allow_patterns
+        .iter()
+        .any(|p| !p.trim().is_empty());
+    let auto_add_enabled = watch.auto_add_new && has_allowlist;
+    let action = decide_watch_action(&managed, &secrets, &allowlist, auto_add_enabled, &rel);
+
+    let git = GitCliBackend::new();
+    let git_dir = &config.repo.git_dir;
+    let work_tree = &config.repo.work_tree;
+    let tracked = git
+        .tracked_paths(git_dir, work_tree, std::slice::from_ref(&rel))
+        .context("git ls-files")?;
+    let is_tracked = tracked.iter().any(|path| Path::new(path) == rel);
+
+    let daemon = match action {
+        WatchAction::Ignore if secrets.is_ciphertext_path(&rel) => {
+            "ignores it (sidecars are written by hometree itself)".to_string()
+        }
+        WatchAction::Ignore => "ignores it (not managed)".to_string(),
+        WatchAction::SecretPlaintext => {
+            "re-encrypts the sidecar when it changes; the plaintext is never staged".to_string()
+        }
+        // Auto-added paths are staged by path; everything else follows
+        // `auto_stage_tracked_only`.
+        WatchAction::Managed {
+            auto_add: false, ..
+        } if watch.auto_stage_tracked_only && tracked.is_empty() => {
+            "not staged automatically (auto_stage_tracked_only; path is untracked)".to_string()
+        }
+        WatchAction::Managed { .. } => {
+            let windows =
+                DebounceWindows::from_config(watch).context("build debounce overrides")?;
+            match windows.window_for(&rel) {
+                (window, Some(pattern)) => format!(
+                    "stages changes after {}ms (debounce override '{pattern}')",
+                    window.as_millis()
+                ),
+                (window, None) => format!("stages changes after {}ms", window.as_millis()),
+            }
+        }
+    };
+    if watch.enabled {
+        println!("daemon: {daemon}");
+    } else {
+        println!("daemon: {daemon} when enabled (watch.enabled = false)");
+    }
+
+    println!("auto-add:");
+    for pattern in &watch.auto_add_allow_patterns {
+        let matched = glob_matches(pattern, &rel);
+        println!("  {} {pattern}", if matched { "[x]" } else { "[ ]" });
+    }
+    let auto_add = if !watch.auto_add_new {
+        "off (watch.auto_add_new = false)".to_string()
+    } else if !has_allowlist {
+        "off (watch.auto_add_allow_patterns is empty)".to_string()
+    } else if is_tracked {
+        "not needed, already tracked".to_string()
+    } else {
+        match action {
+            WatchAction::Managed { auto_add: true, .. } => {
+                "would add it when it changes".to_string()
+            }
+            WatchAction::Managed { .. } => "no, no allowlist pattern matches".to_string(),
+            _ => "no, not a managed file".to_string(),
+        }
+    };
+    println!("  result: {auto_add}");
+
+    let mut git_state = Vec::new();
+    if is_tracked {
+        git_state.push("tracked in the index".to_string());
+    } else if !tracked.is_empty() {
+        git_state.push(format!("{} tracked file(s) below", tracked.len()));
+    } else {
+        git_state.push("not tracked".to_string());
+    }
+    match git
+        .last_commit_for_path(git_dir, work_tree, &rel)
+        .context("git log")?
+    {
+        Some(commit) => git_state.push(format!(
+            "last committed in {}",
+            &commit[..commit.len().min(12)]
+        )),
+        None => git_state.push("never committed".to_string()),
+    }
+    let statuses = git
+        .status_porcelain(git_dir, work_tree, std::slice::from_ref(&rel), true)
+        .context("git status")?;
+    if let Some(status) = statuses.iter().find(|s| Path::new(&s.path) == rel) {
+        git_state.push(format!(
+            "status {}{}",
+            status.index_status, status.worktree_status
+        ));
+    }
+    println!("git: {}", git_state.join("; "));
+    Ok(())
+}
+
+fn print_patterns(label: &str, matches: &[PatternMatch]) {
+    println!("{label}:");
+    if matches.is_empty() {
+        println!("  (none)");
+    }
+    for result in matches {
+        println!("  {} {}", mark(result), result.pattern);
+    }
+}
+
+fn mark(result: &PatternMatch) -> &'static str {
+    if result.matched {
+        "[x]"
+    } else {
+        "[ ]"
+    }
+}
+
+fn glob_matches(pattern: &str, rel: &Path) -> bool {
+    globset::Glob::new(pattern.trim())
+        .map(|glob| glob.compile_matcher().is_match(rel))
+        .unwrap_or(false)
+}
+
+fn fs_kind(path: &Path) -> Option<&'static str> {
+    let meta = std::fs::symlink_metadata(path).ok()?;
+    Some(if meta.file_type().is_symlink() {
+        "symlink"
+    } else if meta.is_dir() {
+        "directory"
+    } else {
+        "file"
+    })
+}
@@ -1,7 +1,9 @@
 use age::secrecy::ExposeSecret;
 use assert_cmd::prelude::*;
+use hometree_core::config::{BackupPolicy, DebounceOverride};
 use hometree_core::read_generations;
-use hometree_core::{config::BackupPolicy, Config};
+use hometree_core::Config;
+use predicates::prelude::PredicateBooleanExt;
 use predicates::str::contains;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -145,6 +147,93 @@ fn init_track_snapshot_and_deploy_records_generation() {
     assert!(entry.message.is_none());
 }
 
+#[test]
+fn snapshot_auto_expands_message_template() {
+    let temp = TempDir::new().unwrap();
+    let (home, config, data, _state) = base_env(&temp);
+
+    let config_file = home.join(".config/app/config.toml");
+    fs::create_dir_all(config_file.parent().unwrap()).unwrap();
+    fs::write(&config_file, "v1").unwrap();
+
+    cmd(&temp).arg("init").assert().success();
+    let config_path = config.join("hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.snapshot.auto_message_template = Some("auto: {count} on {host}: {files}".to_string());
+    cfg.write_to(&config_path).unwrap();
+
+    cmd(&temp)
+        .args(["track", config_file.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd(&temp).args(["snapshot", "--auto"]).assert().success();
+
+    let output = Command::new("git")
+        .arg("--git-dir")
+        .arg(repo_dir(&data))
+        .args(["log", "-1", "--format=%s"])
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap().trim(),
+        format!("auto: 1 on {TEST_HOST}: .config/app/config.toml")
+    );
+}
+
+#[test]
+fn snapshot_auto_pushes_to_configured_remote() {
+    let temp = TempDir::new().unwrap();
+    let (home, config, data, _state) = base_env(&temp);
+
+    let config_file = home.join(".config/app/config.toml");
+    fs::create_dir_all(config_file.parent().unwrap()).unwrap();
+    fs::write(&config_file, "v1").unwrap();
+
+    cmd(&temp).arg("init").assert().success();
+    let config_path = config.join("hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.snapshot.auto_push_remote = Some("origin".to_string());
+    cfg.write_to(&config_path).unwrap();
+
+    // An unreachable remote leaves the commit queued locally.
+    let remote = temp.path().join("remote.git");
+    cmd(&temp)
+        .args(["remote", "add", "origin", remote.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd(&temp)
+        .args(["track", config_file.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd(&temp)
+        .args(["snapshot", "-m", "offline"])
+        .assert()
+        .success()
+        .stderr(contains("1 commit(s) unpushed"));
+
+    let status = Command::new("git")
+        .args(["init", "--bare", "-q"])
+        .arg(&remote)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    fs::write(&config_file, "v2").unwrap();
+    cmd(&temp)
+        .args(["snapshot", "-m", "online"])
+        .assert()
+        .success()
+        .stdout(contains("pushed to 'origin'"));
+
+    let head = git_rev(&repo_dir(&data), &home, "HEAD");
+    let output = Command::new("git")
+        .arg("--git-dir")
+        .arg(&remote)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), head);
+}
+
 #[test]
 fn rollback_replays_previous_generation() {
     let temp = TempDir::new().unwrap();
@@ -288,6 +377,168 @@ fn verify_reports_clean_for_deployed_tree() {
         .failure();
 }
 
+#[test]
+fn verify_fix_repairs_only_reported_drift() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home-src");
+    let xdg_root = temp.path().join("xdg-root");
+    let app_dir = home.join(".config/app");
+    fs::create_dir_all(&app_dir).unwrap();
+    fs::write(app_dir.join("config.toml"), "v1").unwrap();
+    fs::write(app_dir.join("run.sh"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(app_dir.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(app_dir.join("keep.txt"), "keep").unwrap();
+
+    cmd_with_overrides(&temp, &home, &xdg_root)
+        .arg("init")
+        .assert()
+        .success();
+    cmd_with_overrides(&temp, &home, &xdg_root)
+        .args(["track", app_dir.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd_with_overrides(&temp, &home, &xdg_root)
+        .args(["snapshot", "-m", "first"])
+        .assert()
+        .success();
+
+    fs::write(app_dir.join("config.toml"), "drifted").unwrap();
+    fs::remove_file(app_dir.join("keep.txt")).unwrap();
+    fs::set_permissions(app_dir.join("run.sh"), fs::Permissions::from_mode(0o644)).unwrap();
+    fs::write(app_dir.join("stray.txt"), "stray").unwrap();
+
+    cmd_with_overrides(&temp, &home, &xdg_root)
+        .args(["verify", "--strict", "--fix"])
+        .assert()
+        .failure()
+        .stdout(contains("restored .config/app/config.toml"))
+        .stdout(contains("restored .config/app/keep.txt"))
+        .stdout(contains("mode-fixed .config/app/run.sh"))
+        .stdout(contains("unexpected .config/app/stray.txt"));
+
+    assert_eq!(
+        fs::read_to_string(app_dir.join("config.toml")).unwrap(),
+        "v1"
+    );
+    assert_eq!(
+        fs::read_to_string(app_dir.join("keep.txt")).unwrap(),
+        "keep"
+    );
+    let mode = fs::metadata(app_dir.join("run.sh"))
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o111, 0o111);
+    assert!(app_dir.join("stray.txt").exists());
+
+    cmd_with_overrides(&temp, &home, &xdg_root)
+        .args(["verify", "--strict", "--fix", "--remove-unexpected"])
+        .assert()
+        .success()
+        .stdout(contains("removed .config/app/stray.txt"))
+        .stdout(contains("clean"));
+    assert!(!app_dir.join("stray.txt").exists());
+
+    let state = state_dir(&xdg_root.join("state"));
+    let generations = read_generations(&state).unwrap();
+    let last = generations.last().unwrap();
+    assert_eq!(last.message.as_deref(), Some("verify --fix"));
+    assert_eq!(
+        last.rev,
+        git_rev(&repo_dir(&xdg_root.join("data")), &home, "HEAD")
+    );
+
+    let backups: Vec<_> = fs::read_dir(state.join("backups"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert!(backups.iter().any(|dir| {
+        fs::read_to_string(dir.join(".config/app/config.toml")).ok() == Some("drifted".into())
+    }));
+    assert!(backups
+        .iter()
+        .any(|dir| dir.join(".config/app/stray.txt").exists()));
+}
+
+#[test]
+fn metadata_manifest_round_trips_modes_and_mtime() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = TempDir::new().unwrap();
+    let home_src = temp.path().join("home-src");
+    let home_target = temp.path().join("home-target");
+    let xdg_root = temp.path().join("xdg-root");
+    fs::create_dir_all(&home_target).unwrap();
+    let file_path = home_src.join(".config/app/private.conf");
+    fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+    fs::write(&file_path, "secret-ish").unwrap();
+    fs::set_permissions(&file_path, fs::Permissions::from_mode(0o600)).unwrap();
+    filetime::set_file_mtime(
+        &file_path,
+        filetime::FileTime::from_unix_time(1_500_000_000, 0),
+    )
+    .unwrap();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .arg("init")
+        .assert()
+        .success();
+    let config_path = xdg_root.join("config/hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.metadata.enabled = true;
+    cfg.metadata.mtime = true;
+    cfg.write_to(&config_path).unwrap();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["track", file_path.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["snapshot", "-m", "with metadata"])
+        .assert()
+        .success();
+
+    let manifest: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(home_src.join(".config/hometree/metadata.json")).unwrap(),
+    )
+    .unwrap();
+    let entry = &manifest["entries"][".config/app/private.conf"];
+    assert_eq!(entry["mode"], "0600");
+    assert_eq!(entry["mtime"], 1_500_000_000);
+
+    cmd_with_overrides(&temp, &home_target, &xdg_root)
+        .args(["deploy", "HEAD"])
+        .assert()
+        .success();
+    let deployed = home_target.join(".config/app/private.conf");
+    let meta = fs::metadata(&deployed).unwrap();
+    assert_eq!(meta.permissions().mode() & 0o7777, 0o600);
+    assert_eq!(
+        filetime::FileTime::from_last_modification_time(&meta).unix_seconds(),
+        1_500_000_000
+    );
+    cmd_with_overrides(&temp, &home_target, &xdg_root)
+        .args(["verify", "--strict"])
+        .assert()
+        .success();
+
+    fs::set_permissions(&deployed, fs::Permissions::from_mode(0o604)).unwrap();
+    cmd_with_overrides(&temp, &home_target, &xdg_root)
+        .args(["verify", "--strict"])
+        .assert()
+        .failure()
+        .stdout(contains("metadata-mismatch .config/app/private.conf"));
+    cmd_with_overrides(&temp, &home_target, &xdg_root)
+        .args(["verify", "--strict", "--fix"])
+        .assert()
+        .success()
+        .stdout(contains("metadata-fixed .config/app/private.conf"));
+    let meta = fs::metadata(&deployed).unwrap();
+    assert_eq!(meta.permissions().mode() & 0o7777, 0o600);
+}
+
 #[test]
 fn secrets_sidecar_deploy_and_verify() {
     let temp = TempDir::new().unwrap();
@@ -404,6 +655,97 @@ fn snapshot_rejects_staged_plaintext_secret() {
         .failure();
 }
 
+#[test]
+fn show_and_cat_read_past_revisions() {
+    let temp = TempDir::new().unwrap();
+    let home_src = temp.path().join("home-src");
+    let xdg_root = temp.path().join("xdg-root");
+    fs::create_dir_all(&home_src).unwrap();
+
+    let config_file = home_src.join(".config/app/config.toml");
+    let secret_path = home_src.join(".config/app/secret.txt");
+    fs::create_dir_all(config_file.parent().unwrap()).unwrap();
+    fs::write(&config_file, "v1\n").unwrap();
+    fs::write(&secret_path, "top-secret").unwrap();
+
+    let identity = age::x25519::Identity::generate();
+    let identity_path = temp.path().join("identity.txt");
+    fs::write(
+        &identity_path,
+        identity.to_string().expose_secret().as_bytes(),
+    )
+    .unwrap();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .arg("init")
+        .assert()
+        .success();
+    let config_path = xdg_root.join("config/hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.secrets.recipients = vec![identity.to_public().to_string()];
+    cfg.secrets.identity_files = vec![identity_path];
+    cfg.write_to(&config_path).unwrap();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["track", config_file.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["secret", "add", secret_path.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["snapshot", "-m", "first"])
+        .assert()
+        .success();
+    fs::write(&config_file, "v2\n").unwrap();
+    fs::write(&secret_path, "rotated").unwrap();
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["secret", "refresh"])
+        .assert()
+        .success();
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["snapshot", "-m", "second"])
+        .assert()
+        .success();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["cat", "HEAD~1", ".config/app/config.toml"])
+        .assert()
+        .success()
+        .stdout("v1\n");
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["cat", "HEAD~1", ".config/app/secret.txt"])
+        .assert()
+        .failure()
+        .stderr(contains("pass --decrypt"));
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["cat", "HEAD~1", ".config/app/secret.txt", "--decrypt"])
+        .assert()
+        .success()
+        .stdout("top-secret");
+
+    let output = cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["show", "HEAD", "--color", "never"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("    second"));
+    assert!(stdout.contains("modified  .config/app/config.toml"));
+    assert!(stdout.contains("-v1\n+v2"));
+    assert!(stdout.contains(
+        "secret .config/app/secret.txt: modified (sidecar .config/app/secret.txt.age, contents not shown)"
+    ));
+    assert!(!stdout.contains("rotated"));
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["show", "HEAD~1", "--color", "never"])
+        .assert()
+        .success()
+        .stdout(contains("added     .config/app/config.toml"));
+}
+
 #[test]
 fn plan_deploy_outputs_expected_actions() {
     let temp = TempDir::new().unwrap();
@@ -439,6 +781,64 @@ fn plan_deploy_outputs_expected_actions() {
         .stdout(contains("create .config/app/config.toml"));
 }
 
+#[test]
+fn format_json_wraps_results_in_versioned_envelope() {
+    let temp = TempDir::new().unwrap();
+    let (home, _config, _data, _state) = base_env(&temp);
+
+    let config_file = home.join(".config/app/config.toml");
+    fs::create_dir_all(config_file.parent().unwrap()).unwrap();
+    fs::write(&config_file, "v1").unwrap();
+
+    cmd(&temp).arg("init").assert().success();
+    cmd(&temp)
+        .args(["track", config_file.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+
+    let json = |args: &[&str]| -> serde_json::Value {
+        let output = cmd(&temp)
+            .args(args)
+            .args(["--format", "json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{args:?} failed");
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+
+    let status = json(&["status"]);
+    assert_eq!(status["schema_version"], 1);
+    assert_eq!(status["command"], "status");
+    assert_eq!(status["result"]["clean"], false);
+    assert_eq!(
+        status["result"]["entries"][0]["path"],
+        ".config/app/config.toml"
+    );
+
+    cmd(&temp)
+        .args(["snapshot", "-m", "first"])
+        .assert()
+        .success();
+
+    let log = json(&["log"]);
+    let commit = &log["result"]["commits"][0];
+    assert_eq!(commit["message"], "first");
+    assert_eq!(commit["files"][0]["status"], "added");
+
+    let deploy = json(&["deploy", "HEAD"]);
+    assert_eq!(deploy["command"], "deploy");
+    assert_eq!(deploy["result"]["host"], TEST_HOST);
+
+    let remotes = json(&["remote", "list"]);
+    assert_eq!(remotes["result"]["remotes"], serde_json::json!([]));
+
+    cmd(&temp)
+        .args(["--format", "json", "snapshot", "-m", "second"])
+        .assert()
+        .failure()
+        .stderr(contains("--format json is not supported"));
+}
+
 #[test]
 fn untrack_removes_from_paths() {
     let temp = TempDir::new().unwrap();
@@ -468,3 +868,1239 @@ fn untrack_removes_from_paths() {
     let cfg_after_untrack = fs::read_to_string(config.join("hometree/config.toml")).unwrap();
     assert!(!cfg_after_untrack.contains(".gitconfig"));
 }
+
+#[test]
+fn undo_reverts_track_and_untrack() {
+    let temp = TempDir::new().unwrap();
+    let (home, config, _data, _state) = base_env(&temp);
+    let config_file = config.join("hometree/config.toml");
+
+    let dotfile = home.join(".gitconfig");
+    fs::write(&dotfile, "ok").unwrap();
+    cmd(&temp).arg("init").assert().success();
+    let cfg_initial = fs::read_to_string(&config_file).unwrap();
+
+    cmd(&temp)
+        .args(["track", dotfile.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd(&temp)
+        .args(["untrack", dotfile.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+
+    cmd(&temp)
+        .args(["undo", "--list"])
+        .assert()
+        .success()
+        .stdout(contains("untrack").and(contains("track")));
+
+    cmd(&temp)
+        .arg("undo")
+        .assert()
+        .success()
+        .stdout(contains("undid `untrack"));
+    assert!(fs::read_to_string(&config_file)
+        .unwrap()
+        .contains(".gitconfig"));
+    cmd(&temp)
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(contains(".gitconfig"));
+
+    cmd(&temp)
+        .arg("undo")
+        .assert()
+        .success()
+        .stdout(contains("undid `track"));
+    assert_eq!(fs::read_to_string(&config_file).unwrap(), cfg_initial);
+    cmd(&temp)
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(contains(".gitconfig").not());
+
+    cmd(&temp)
+        .arg("undo")
+        .assert()
+        .success()
+        .stdout(contains("no more operations to undo"));
+}
+
+#[test]
+fn dry_run_reports_effects_without_changing_anything() {
+    let temp = TempDir::new().unwrap();
+    let (home, config, data, _state) = base_env(&temp);
+    let config_file = config.join("hometree/config.toml");
+    let repo = repo_dir(&data);
+
+    let dotfile = home.join(".gitconfig");
+    fs::write(&dotfile, "ok").unwrap();
+    cmd(&temp).arg("init").assert().success();
+    let cfg_before = fs::read_to_string(&config_file).unwrap();
+
+    cmd(&temp)
+        .args(["--dry-run", "track", dotfile.to_string_lossy().as_ref()])
+        .assert()
+        .success()
+        .stdout(contains("manage.paths += \".gitconfig\""))
+        .stdout(contains("would stage .gitconfig"))
+        .stdout(contains("dry run; nothing changed"))
+        .stdout(contains("tracked").not());
+    assert_eq!(fs::read_to_string(&config_file).unwrap(), cfg_before);
+    cmd(&temp)
+        .args(["undo", "--list"])
+        .assert()
+        .success()
+        .stdout(contains("no operations to undo"));
+
+    cmd(&temp)
+        .args(["track", dotfile.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd(&temp)
+        .args(["snapshot", "-m", "first", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(contains("would commit \"first\""));
+    let head = Command::new("git")
+        .arg("--git-dir")
+        .arg(&repo)
+        .args(["rev-parse", "--verify", "-q", "HEAD"])
+        .output()
+        .unwrap();
+    assert!(!head.status.success());
+
+    cmd(&temp)
+        .args(["snapshot", "-m", "first"])
+        .assert()
+        .success();
+    fs::write(&dotfile, "changed").unwrap();
+    cmd(&temp)
+        .args(["--dry-run", "snapshot", "-m", "second"])
+        .assert()
+        .success()
+        .stdout(contains("would stage .gitconfig"));
+    cmd(&temp)
+        .args(["--dry-run", "deploy", "HEAD"])
+        .assert()
+        .success()
+        .stdout(contains("would deploy HEAD"))
+        .stdout(contains(format!("would write {}", dotfile.display())));
+    assert_eq!(fs::read_to_string(&dotfile).unwrap(), "changed");
+
+    cmd(&temp)
+        .args(["--dry-run", "status"])
+        .assert()
+        .failure()
+        .stderr(contains("--dry-run is not supported"));
+}
+
+#[test]
+fn secret_audit_tracks_rotation_metadata() {
+    let temp = TempDir::new().unwrap();
+    let home_src = temp.path().join("home-src");
+    let xdg_root = temp.path().join("xdg-root");
+    fs::create_dir_all(&home_src).unwrap();
+
+    let secret_path = home_src.join(".config/app/secret.txt");
+    fs::create_dir_all(secret_path.parent().unwrap()).unwrap();
+    fs::write(&secret_path, "top-secret").unwrap();
+
+    let identity = age::x25519::Identity::generate();
+    let recipient = identity.to_public().to_string();
+    let identity_path = temp.path().join("identity.txt");
+    fs::write(
+        &identity_path,
+        identity.to_string().expose_secret().as_bytes(),
+    )
+    .unwrap();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .arg("init")
+        .assert()
+        .success();
+
+    let config_path = xdg_root.join("config/hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.secrets.enabled = true;
+    cfg.secrets.recipients = vec![recipient];
+    cfg.secrets.identity_files = vec![identity_path.clone()];
+    cfg.write_to(&config_path).unwrap();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["secret", "add", secret_path.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["snapshot", "-m", "secret"])
+        .assert()
+        .success();
+
+    let output = cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["secret", "status", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let status: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let entry = &status[0];
+    assert_eq!(entry["path"], "<redacted>");
+    assert_eq!(entry["recipients"].as_array().unwrap().len(), 1);
+    let repo = repo_dir(&xdg_root.join("data"));
+    assert_eq!(entry["commit"], git_rev(&repo, &home_src, "HEAD"));
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["secret", "audit"])
+        .assert()
+        .success()
+        .stdout(contains("clean"));
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["secret", "audit", "--max-age-days", "0", "--show-paths"])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(contains("stale(0d) .config/app/secret.txt"))
+        .stderr("");
+
+    let rotated = age::x25519::Identity::generate().to_public().to_string();
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.secrets.recipients = vec![rotated];
+    cfg.write_to(&config_path).unwrap();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["secret", "audit"])
+        .assert()
+        .failure()
+        .stdout(contains("recipient-mismatch <redacted>"));
+}
+
+#[test]
+fn secret_rekey_follows_recipients_dir() {
+    let temp = TempDir::new().unwrap();
+    let home_src = temp.path().join("home-src");
+    let xdg_root = temp.path().join("xdg-root");
+    fs::create_dir_all(&home_src).unwrap();
+
+    let secret_path = home_src.join(".config/app/secret.txt");
+    fs::create_dir_all(secret_path.parent().unwrap()).unwrap();
+    fs::write(&secret_path, "top-secret").unwrap();
+
+    let identity = age::x25519::Identity::generate();
+    let identity_path = temp.path().join("identity.txt");
+    fs::write(
+        &identity_path,
+        identity.to_string().expose_secret().as_bytes(),
+    )
+    .unwrap();
+    let keys_dir = home_src.join(".config/hometree/recipients");
+    fs::create_dir_all(&keys_dir).unwrap();
+    fs::write(
+        keys_dir.join("laptop"),
+        format!("# laptop\n{}\n", identity.to_public()),
+    )
+    .unwrap();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .arg("init")
+        .assert()
+        .success();
+
+    let config_path = xdg_root.join("config/hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.secrets.enabled = true;
+    cfg.secrets.recipients_dir = Some(".config/hometree/recipients".to_string());
+    cfg.secrets.identity_files = vec![identity_path.clone()];
+    cfg.write_to(&config_path).unwrap();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["secret", "add", secret_path.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["secret", "rekey"])
+        .assert()
+        .success()
+        .stdout(contains("nothing to rekey"));
+
+    let desktop = age::x25519::Identity::generate();
+    fs::write(
+        keys_dir.join("desktop"),
+        format!("{}\n", desktop.to_public()),
+    )
+    .unwrap();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["secret", "audit"])
+        .assert()
+        .failure()
+        .stdout(contains("recipient-mismatch"));
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["secret", "rekey"])
+        .assert()
+        .success()
+        .stdout(contains("rekeyed 1 secret(s)"));
+    cmd_with_overrides(&temp, &home_src, &xdg_root)
+        .args(["secret", "audit"])
+        .assert()
+        .success();
+
+    let ciphertext = fs::read(home_src.join(".config/app/secret.txt.age")).unwrap();
+    let decryptor = age::Decryptor::new(&ciphertext[..]).unwrap();
+    let mut reader = decryptor
+        .decrypt(std::iter::once(&desktop as &dyn age::Identity))
+        .unwrap();
+    let mut plaintext = String::new();
+    std::io::Read::read_to_string(&mut reader, &mut plaintext).unwrap();
+    assert_eq!(plaintext, "top-secret");
+}
+
+/// Foreground daemon that is killed when dropped.
+struct Daemon(std::process::Child);
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// `cmd` with the runtime dir used by `start_daemon`.
+fn daemon_cmd(temp: &TempDir) -> Command {
+    let mut cmd = cmd(temp);
+    cmd.env("HOMETREE_RUNTIME_DIR", temp.path().join("run"));
+    cmd
+}
+
+fn start_daemon(temp: &TempDir) -> Daemon {
+    let runtime = temp.path().join("run");
+    let child = daemon_cmd(temp)
+        .args(["daemon", "run"])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let daemon = Daemon(child);
+    let socket = runtime.join("hometree/daemon.sock");
+    wait_until("daemon socket", || socket.exists().then_some(()));
+    daemon
+}
+
+fn wait_until<T>(what: &str, mut check: impl FnMut() -> Option<T>) -> T {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    loop {
+        if let Some(value) = check() {
+            return value;
+        }
+        assert!(
+            std::time::Instant::now() < deadline,
+            "timed out waiting for {what}"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+}
+
+fn staged_changes(repo: &Path, work_tree: &Path) -> Vec<String> {
+    let output = Command::new("git")
+        .arg("--git-dir")
+        .arg(repo)
+        .arg("--work-tree")
+        .arg(work_tree)
+        .args(["diff", "--cached", "--no-renames", "--name-status"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.replace('\t', " "))
+        .collect()
+}
+
+fn wait_for_staged(repo: &Path, work_tree: &Path, expected: &[&str]) {
+    let mut last = Vec::new();
+    let staged = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        wait_until("staged changes", || {
+            last = staged_changes(repo, work_tree);
+            (last == expected).then_some(())
+        })
+    }));
+    assert!(staged.is_ok(), "expected {expected:?}, staged {last:?}");
+}
+
+/// Snapshot `files` under `.config/app` and enable the watcher for that root.
+fn setup_watched_app(temp: &TempDir, files: &[&str]) -> PathBuf {
+    let (home, config, _data, _state) = base_env(temp);
+    let app = home.join(".config/app");
+    for file in files {
+        let path = app.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, format!("{file} v1")).unwrap();
+    }
+
+    cmd(temp).arg("init").assert().success();
+    cmd(temp)
+        .args(["track", app.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd(temp)
+        .args(["snapshot", "-m", "initial"])
+        .assert()
+        .success();
+
+    let config_path = config.join("hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    // The default config entry lives outside this test's HOME.
+    cfg.manage
+        .paths
+        .retain(|path| path.starts_with(".config/app"));
+    cfg.watch.enabled = true;
+    cfg.watch.debounce_ms = 50;
+    cfg.write_to(&config_path).unwrap();
+    app
+}
+
+#[test]
+fn daemon_stages_editor_saves_as_edits() {
+    let temp = TempDir::new().unwrap();
+    let (home, _config, data, _state) = base_env(&temp);
+    let app = setup_watched_app(&temp, &["atomic.toml", "backup.toml"]);
+    let _daemon = start_daemon(&temp);
+
+    // Write a temp file, then rename it over the original.
+    fs::write(app.join(".atomic.toml.swp"), "atomic v2").unwrap();
+    fs::rename(app.join(".atomic.toml.swp"), app.join("atomic.toml")).unwrap();
+    // Move the original aside, write a new one, then drop the backup.
+    fs::rename(app.join("backup.toml"), app.join("backup.toml~")).unwrap();
+    fs::write(app.join("backup.toml"), "backup v2").unwrap();
+    fs::remove_file(app.join("backup.toml~")).unwrap();
+
+    wait_for_staged(
+        &repo_dir(&data),
+        &home,
+        &["M .config/app/atomic.toml", "M .config/app/backup.toml"],
+    );
+}
+
+#[test]
+fn daemon_stages_both_sides_of_moves() {
+    let temp = TempDir::new().unwrap();
+    let (home, _config, data, _state) = base_env(&temp);
+    let app = setup_watched_app(
+        &temp,
+        &["old.toml", "themes/dark.toml", "themes/light.toml"],
+    );
+    let _daemon = start_daemon(&temp);
+
+    fs::rename(app.join("old.toml"), app.join("new.toml")).unwrap();
+    fs::rename(app.join("themes"), app.join("colors")).unwrap();
+
+    wait_for_staged(
+        &repo_dir(&data),
+        &home,
+        &[
+            "A .config/app/colors/dark.toml",
+            "A .config/app/colors/light.toml",
+            "A .config/app/new.toml",
+            "D .config/app/old.toml",
+            "D .config/app/themes/dark.toml",
+            "D .config/app/themes/light.toml",
+        ],
+    );
+}
+
+#[test]
+fn daemon_reports_status_json_and_metrics() {
+    let temp = TempDir::new().unwrap();
+    let (_home, config, _data, _state) = base_env(&temp);
+    let app = setup_watched_app(&temp, &["app.toml"]);
+    let textfile = temp.path().join("textfile/hometree.prom");
+    let config_path = config.join("hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.watch.metrics_textfile = Some(textfile.clone());
+    cfg.write_to(&config_path).unwrap();
+    let _daemon = start_daemon(&temp);
+
+    fs::write(app.join("app.toml"), "v2").unwrap();
+    let status = wait_until("a flush", || {
+        let output = daemon_cmd(&temp)
+            .args(["daemon", "status", "--json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let status: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (status["metrics"]["paths_staged"].as_u64() >= Some(1)).then_some(status)
+    });
+    assert!(status["total_flushes"].as_u64().unwrap() >= 1);
+    assert!(status["metrics"]["events_received"].as_u64().unwrap() >= 1);
+    assert_eq!(status["metrics"]["consecutive_flush_failures"], 0);
+    assert!(matches!(
+        status["watch_strategies"][".config/app"].as_str(),
+        Some("native" | "poll")
+    ));
+
+    daemon_cmd(&temp)
+        .args(["daemon", "metrics"])
+        .assert()
+        .success()
+        .stdout(contains("# TYPE hometree_daemon_flushes_total counter"))
+        .stdout(contains("hometree_daemon_queue_size{queue=\"managed\"}"));
+    let written = wait_until("metrics textfile", || fs::read_to_string(&textfile).ok());
+    assert!(written.contains("hometree_daemon_paths_staged_total"));
+}
+
+#[test]
+fn daemon_foreground_spellings_still_parse() {
+    let temp = TempDir::new().unwrap();
+    for args in [
+        ["daemon", "foreground", "--help"],
+        ["watch", "foreground", "--help"],
+        ["daemon", "run", "--help"],
+    ] {
+        cmd(&temp).args(args).assert().success();
+    }
+}
+
+#[test]
+fn daemon_install_writes_hardened_socket_units() {
+    let temp = TempDir::new().unwrap();
+    let (home, config, _data, _state) = base_env(&temp);
+    cmd(&temp).arg("init").assert().success();
+    let unit_dir = config.join("systemd/user");
+
+    daemon_cmd(&temp)
+        .args(["daemon", "install-systemd", "--socket"])
+        .assert()
+        .success();
+    let service = fs::read_to_string(unit_dir.join("hometree.service")).unwrap();
+    assert!(service.contains("Requires=hometree.socket\n"));
+    assert!(service.contains("ProtectSystem=strict\n"));
+    assert!(service.contains(&format!("ReadWritePaths=-{}\n", home.display())));
+    assert!(!service.contains("RuntimeDirectory="));
+    let socket = fs::read_to_string(unit_dir.join("hometree.socket")).unwrap();
+    let socket_path = temp.path().join("run/hometree/daemon.sock");
+    assert!(socket.contains(&format!("ListenStream={}\n", socket_path.display())));
+
+    daemon_cmd(&temp)
+        .args(["daemon", "install-systemd", "--no-harden"])
+        .assert()
+        .success();
+    let service = fs::read_to_string(unit_dir.join("hometree.service")).unwrap();
+    assert!(!service.contains("ProtectSystem"));
+    assert!(service.contains("WantedBy=default.target"));
+    assert!(!unit_dir.join("hometree.socket").exists());
+}
+
+#[test]
+fn daemon_accepts_socket_from_systemd() {
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixListener;
+    use std::os::unix::process::CommandExt;
+
+    let temp = TempDir::new().unwrap();
+    setup_watched_app(&temp, &["app.toml"]);
+    let socket_dir = temp.path().join("run/hometree");
+    fs::create_dir_all(&socket_dir).unwrap();
+    let socket_path = socket_dir.join("daemon.sock");
+    let listener = UnixListener::bind(&socket_path).unwrap();
+    let fd = listener.as_raw_fd();
+
+    // LISTEN_PID must name the daemon itself, so set it from a shell that execs it.
+    let daemon = daemon_cmd(&temp);
+    let mut command = Command::new("sh");
+    command
+        .args(["-c", "LISTEN_PID=$$ LISTEN_FDS=1 exec \"$0\" daemon run"])
+        .arg(daemon.get_program())
+        .envs(
+            daemon
+                .get_envs()
+                .filter_map(|(key, value)| Some((key.to_owned(), value?.to_owned()))),
+        )
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    // SAFETY: dup2 and fcntl are async-signal-safe.
+    unsafe {
+        command.pre_exec(move || {
+            // dup2 onto itself keeps close-on-exec set, so clear it explicitly.
+            if libc::dup2(fd, 3) == -1 || libc::fcntl(3, libc::F_SETFD, 0) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let _daemon = Daemon(command.spawn().unwrap());
+    drop(listener);
+
+    wait_until("daemon status", || {
+        daemon_cmd(&temp)
+            .args(["daemon", "status", "--json"])
+            .output()
+            .unwrap()
+            .status
+            .success()
+            .then_some(())
+    });
+    // The daemon must not have replaced the socket it was handed.
+    assert!(socket_path.exists());
+}
+
+#[test]
+fn daemon_queues_during_quiet_hours() {
+    let temp = TempDir::new().unwrap();
+    let (home, config, data, _state) = base_env(&temp);
+    let app = setup_watched_app(&temp, &["app.toml"]);
+    let config_path = config.join("hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    // Together these cover the whole day.
+    cfg.watch.quiet_hours = vec!["00:00-12:00".to_string(), "12:00-00:00".to_string()];
+    cfg.write_to(&config_path).unwrap();
+    let _daemon = start_daemon(&temp);
+
+    fs::write(app.join("app.toml"), "v2").unwrap();
+    let status = wait_until("queued change", || {
+        let output = daemon_cmd(&temp)
+            .args(["daemon", "status", "--json"])
+            .output()
+            .unwrap();
+        let status: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (status["queue_size"].as_u64() >= Some(1)).then_some(status)
+    });
+    assert_eq!(status["throttle"], "quiet_hours");
+    assert!(status["throttled_until"].is_string());
+    daemon_cmd(&temp)
+        .args(["daemon", "flush"])
+        .assert()
+        .success();
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert!(staged_changes(&repo_dir(&data), &home).is_empty());
+
+    cfg.watch.quiet_hours.clear();
+    cfg.write_to(&config_path).unwrap();
+    daemon_cmd(&temp)
+        .args(["daemon", "reload"])
+        .assert()
+        .success();
+    wait_for_staged(&repo_dir(&data), &home, &["M .config/app/app.toml"]);
+}
+
+#[test]
+fn daemon_applies_debounce_overrides() {
+    let temp = TempDir::new().unwrap();
+    let (home, config, data, _state) = base_env(&temp);
+    let app = setup_watched_app(&temp, &["app.toml", "state.json"]);
+    let config_path = config.join("hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.watch.debounce_overrides = vec![DebounceOverride {
+        pattern: ".config/app/*.json".to_string(),
+        debounce_ms: 60_000,
+    }];
+    cfg.write_to(&config_path).unwrap();
+    let _daemon = start_daemon(&temp);
+
+    fs::write(app.join("state.json"), "v2").unwrap();
+    fs::write(app.join("app.toml"), "v2").unwrap();
+    wait_for_staged(&repo_dir(&data), &home, &["M .config/app/app.toml"]);
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    assert_eq!(
+        staged_changes(&repo_dir(&data), &home),
+        ["M .config/app/app.toml"]
+    );
+
+    // An explicit flush does not wait for longer windows.
+    daemon_cmd(&temp)
+        .args(["daemon", "flush"])
+        .assert()
+        .success();
+    wait_for_staged(
+        &repo_dir(&data),
+        &home,
+        &["M .config/app/app.toml", "M .config/app/state.json"],
+    );
+}
+
+#[test]
+fn diff_shows_managed_changes_and_hides_secrets() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home-src");
+    let xdg_root = temp.path().join("xdg-root");
+    let app = home.join(".config/app");
+    fs::create_dir_all(&app).unwrap();
+    fs::write(app.join("app.toml"), "theme = \"dark\"\n").unwrap();
+    fs::write(app.join("secret.txt"), "top-secret").unwrap();
+    let identity = age::x25519::Identity::generate();
+    let identity_path = temp.path().join("identity.txt");
+    fs::write(
+        &identity_path,
+        identity.to_string().expose_secret().as_bytes(),
+    )
+    .unwrap();
+    let hometree = || cmd_with_overrides(&temp, &home, &xdg_root);
+
+    hometree().arg("init").assert().success();
+    let config_path = xdg_root.join("config/hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.secrets.enabled = true;
+    cfg.secrets.recipients = vec![identity.to_public().to_string()];
+    cfg.secrets.identity_files = vec![identity_path];
+    cfg.write_to(&config_path).unwrap();
+    hometree()
+        .args(["track", app.join("app.toml").to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    hometree()
+        .args([
+            "secret",
+            "add",
+            app.join("secret.txt").to_string_lossy().as_ref(),
+        ])
+        .assert()
+        .success();
+    hometree()
+        .args(["snapshot", "-m", "initial"])
+        .assert()
+        .success();
+
+    fs::write(app.join("app.toml"), "theme = \"light\"\n").unwrap();
+    hometree()
+        .args(["diff", "--color", "never"])
+        .assert()
+        .success()
+        .stdout(contains("-theme = \"dark\""))
+        .stdout(contains("+theme = \"light\""));
+    hometree()
+        .args(["diff", "--stat"])
+        .assert()
+        .success()
+        .stdout(contains(".config/app/app.toml | 2 +-"));
+    hometree()
+        .args(["diff", "--staged"])
+        .assert()
+        .success()
+        .stdout("");
+
+    // A rotated secret shows up only as a sidecar summary, even when its plaintext is staged.
+    fs::write(app.join("secret.txt"), "rotated-secret").unwrap();
+    hometree().args(["secret", "refresh"]).assert().success();
+    git_add_force(
+        &repo_dir(&xdg_root.join("data")),
+        &home,
+        Path::new(".config/app/secret.txt"),
+    );
+    let output = hometree()
+        .args(["diff", "--staged", "--color", "never"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("top-secret") && !stdout.contains("rotated-secret"));
+    assert!(stdout.contains(
+        "secret .config/app/secret.txt: modified (sidecar .config/app/secret.txt.age, contents not shown)"
+    ));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("1 secret plaintext path(s) not shown"));
+    hometree()
+        .args(["diff", "--name-only", "HEAD", "--", ".config/app/app.toml"])
+        .assert()
+        .success()
+        .stdout(".config/app/app.toml\n");
+}
+
+#[test]
+fn import_from_chezmoi_reports_and_snapshots() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = TempDir::new().unwrap();
+    let (home, config_root, data, _state) = base_env(&temp);
+    let source = home.join(".local/share/chezmoi");
+    fs::create_dir_all(source.join("private_dot_config/app")).unwrap();
+    fs::write(source.join("dot_bashrc"), "alias ll='ls -l'\n").unwrap();
+    fs::write(
+        source.join("private_dot_config/app/executable_run.sh"),
+        "#!/bin/sh\n",
+    )
+    .unwrap();
+    fs::write(source.join("dot_gitconfig.tmpl"), "{{ .email }}\n").unwrap();
+    fs::write(source.join("run_once_install.sh"), "#!/bin/sh\n").unwrap();
+    fs::write(source.join("encrypted_dot_netrc.age"), "age").unwrap();
+    fs::write(home.join(".netrc"), "machine example.com\n").unwrap();
+    let identity = age::x25519::Identity::generate();
+
+    cmd(&temp).arg("init").assert().success();
+    cmd(&temp)
+        .args(["import", "--from", "chezmoi", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(contains("add    .bashrc <- dot_bashrc"))
+        .stdout(contains(
+            "add    .config/app/run.sh <- private_dot_config/app/executable_run.sh",
+        ))
+        .stdout(contains(
+            "secret .netrc <- encrypted_dot_netrc.age (decrypted copy taken from HOME)",
+        ))
+        .stdout(contains(
+            "skip   run_once_install.sh: chezmoi scripts are not imported",
+        ))
+        .stdout(contains(
+            "skip   dot_gitconfig.tmpl: a template and not applied in HOME",
+        ))
+        .stdout(contains("note: importing secrets needs age recipients"))
+        .stdout(contains("dry run; nothing changed"));
+    assert!(!home.join(".bashrc").exists());
+    cmd(&temp)
+        .args(["import", "--from", "chezmoi"])
+        .assert()
+        .failure()
+        .stderr(contains("importing secrets needs age recipients"));
+
+    let config_path = config_root.join("hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.secrets.recipients = vec![identity.to_public().to_string()];
+    cfg.write_to(&config_path).unwrap();
+    cmd(&temp)
+        .args(["import", "--from", "chezmoi"])
+        .assert()
+        .success()
+        .stdout(contains("3 path(s) to import (1 secret)"));
+
+    let script = home.join(".config/app/run.sh");
+    assert_eq!(
+        fs::metadata(&script).unwrap().permissions().mode() & 0o777,
+        0o755
+    );
+    let cfg = Config::load_from(&config_path).unwrap();
+    assert!(cfg.manage.paths.contains(&".bashrc".to_string()));
+    assert!(cfg.secrets.enabled);
+    assert_eq!(cfg.secrets.rules[0].path, ".netrc");
+    let files = Command::new("git")
+        .arg("--git-dir")
+        .arg(repo_dir(&data))
+        .args(["ls-tree", "-r", "--name-only", "HEAD"])
+        .output()
+        .unwrap();
+    let files = String::from_utf8(files.stdout).unwrap();
+    assert!(files.contains(".bashrc\n"));
+    assert!(files.contains(".config/app/run.sh\n"));
+    assert!(files.contains(".netrc.age\n"));
+    assert!(!files.contains(".netrc\n"));
+    let log = Command::new("git")
+        .arg("--git-dir")
+        .arg(repo_dir(&data))
+        .args(["log", "-1", "--format=%s"])
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&log.stdout).trim(),
+        "import from chezmoi"
+    );
+}
+
+#[test]
+fn import_from_yadm_keeps_selected_alternates() {
+    let temp = TempDir::new().unwrap();
+    let (home, config_root, data, _state) = base_env(&temp);
+    let yadm_repo = temp.path().join("yadm.git");
+    fs::create_dir_all(home.join(".config/app")).unwrap();
+    fs::create_dir_all(home.join(".config/yadm")).unwrap();
+    fs::write(home.join(".profile"), "export EDITOR=vi\n").unwrap();
+    fs::write(
+        home.join(".config/app/app.toml##os.Linux"),
+        "linux = true\n",
+    )
+    .unwrap();
+    fs::write(home.join(".config/app/app.toml##os.Darwin"), "mac = true\n").unwrap();
+    std::os::unix::fs::symlink("app.toml##os.Linux", home.join(".config/app/app.toml")).unwrap();
+    fs::write(home.join(".config/yadm/encrypt"), ".netrc\n").unwrap();
+    fs::write(home.join(".netrc"), "machine example.com\n").unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .arg("--git-dir")
+            .arg(&yadm_repo)
+            .arg("--work-tree")
+            .arg(&home)
+            .args(args)
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_AUTHOR_NAME", "yadm")
+            .env("GIT_AUTHOR_EMAIL", "yadm@example.com")
+            .env("GIT_COMMITTER_NAME", "yadm")
+            .env("GIT_COMMITTER_EMAIL", "yadm@example.com")
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    let status = Command::new("git")
+        .args(["init", "-q", "--bare"])
+        .arg(&yadm_repo)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    git(&[
+        "add",
+        ".profile",
+        ".config/app/app.toml##os.Linux",
+        ".config/app/app.toml##os.Darwin",
+        ".config/yadm/encrypt",
+    ]);
+    git(&["commit", "-q", "-m", "yadm"]);
+
+    cmd(&temp).arg("init").assert().success();
+    let identity = age::x25519::Identity::generate();
+    let config_path = config_root.join("hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.secrets.recipients = vec![identity.to_public().to_string()];
+    cfg.write_to(&config_path).unwrap();
+
+    cmd(&temp)
+        .args(["import", "--from", "yadm", yadm_repo.to_string_lossy().as_ref()])
+        .assert()
+        .success()
+        .stdout(contains(
+            "add    .config/app/app.toml <- .config/app/app.toml##os.Linux (yadm alternate ##os.Linux)",
+        ))
+        .stdout(contains("secret .netrc <- encrypt: .netrc"))
+        .stdout(contains(
+            "skip   .config/app/app.toml##os.Darwin: alternate ##os.Darwin is not selected",
+        ))
+        .stdout(contains("skip   .config/yadm/encrypt: yadm's own configuration"));
+
+    let app = home.join(".config/app/app.toml");
+    assert!(!fs::symlink_metadata(&app).unwrap().file_type().is_symlink());
+    assert_eq!(fs::read_to_string(&app).unwrap(), "linux = true\n");
+    let files = Command::new("git")
+        .arg("--git-dir")
+        .arg(repo_dir(&data))
+        .args(["ls-tree", "-r", "--name-only", "HEAD"])
+        .output()
+        .unwrap();
+    let files = String::from_utf8(files.stdout).unwrap();
+    assert_eq!(files, ".config/app/app.toml\n.netrc.age\n.profile\n");
+}
+
+#[test]
+fn doctor_reports_problems_with_fixes() {
+    let temp = TempDir::new().unwrap();
+    let (home, config_root, data, state) = base_env(&temp);
+    daemon_cmd(&temp)
+        .arg("doctor")
+        .assert()
+        .failure()
+        .stdout(contains("skip  lock: no state directory yet"));
+    assert!(!state_dir(&state).exists());
+    daemon_cmd(&temp).arg("init").assert().success();
+    daemon_cmd(&temp)
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(contains("ok    repo:"))
+        .stdout(contains("skip  recipients: secrets are disabled"))
+        .stdout(contains("ok    daemon: not running"));
+
+    let config_path = config_root.join("hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.repo.work_tree = temp.path().join("old-home");
+    fs::create_dir_all(&cfg.repo.work_tree).unwrap();
+    cfg.secrets.enabled = true;
+    cfg.secrets.recipients = vec!["not-a-key".to_string()];
+    cfg.secrets.identity_files = vec![temp.path().join("missing-identity.txt")];
+    cfg.secrets.rules = vec![hometree_core::config::SecretRule {
+        path: ".config/app/token".to_string(),
+        ciphertext: None,
+        mode: None,
+    }];
+    cfg.write_to(&config_path).unwrap();
+    fs::create_dir_all(home.join(".config/app")).unwrap();
+    fs::write(home.join(".config/app/token"), "plaintext").unwrap();
+    git_add_force(&repo_dir(&data), &home, Path::new(".config/app/token"));
+    fs::write(
+        state_dir(&state).join("inhibit.json"),
+        r#"{"reason":"deploy","pid":999999999,"expires_at":"2999-01-01T00:00:00Z","epoch":0}"#,
+    )
+    .unwrap();
+    let runtime = temp.path().join("run/hometree");
+    fs::create_dir_all(&runtime).unwrap();
+    fs::write(runtime.join("daemon.sock"), "").unwrap();
+
+    daemon_cmd(&temp)
+        .arg("doctor")
+        .assert()
+        .failure()
+        .stdout(contains("is not HOME"))
+        .stdout(contains(format!(
+            "fix: set repo.work_tree = \"{}\"",
+            home.display()
+        )))
+        .stdout(contains("FAIL  identities: cannot read identity file"))
+        .stdout(contains(
+            "FAIL  recipients: config validation error: invalid age recipient: not-a-key",
+        ))
+        .stdout(contains(
+            "FAIL  plaintext: secret plaintext tracked in the index: .config/app/token",
+        ))
+        .stdout(contains("but process 999999999 has exited"))
+        .stdout(contains("WARN  daemon:"));
+
+    let output = daemon_cmd(&temp)
+        .args(["doctor", "--json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(output["schema_version"], 1);
+    let report = &output["result"];
+    assert_eq!(report["ok"], false);
+    let status_of = |name: &str| {
+        report["checks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|check| check["name"] == name)
+            .map(|check| check["status"].as_str().unwrap().to_string())
+    };
+    assert_eq!(status_of("plaintext").as_deref(), Some("fail"));
+    assert_eq!(status_of("inhibit").as_deref(), Some("warn"));
+    assert_eq!(status_of("git").as_deref(), Some("ok"));
+}
+
+#[test]
+fn doctor_does_not_activate_a_stopped_socket_unit() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = TempDir::new().unwrap();
+    let (_home, config, _data, _state) = base_env(&temp);
+    daemon_cmd(&temp).arg("init").assert().success();
+    let unit_dir = config.join("systemd/user");
+    fs::create_dir_all(&unit_dir).unwrap();
+    fs::write(unit_dir.join("hometree.socket"), "[Socket]\n").unwrap();
+    let bin = temp.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let systemctl = bin.join("systemctl");
+    fs::write(
+        &systemctl,
+        "#!/bin/sh\ncase \"$3\" in hometree.socket) echo active ;; *) echo inactive ;; esac\n",
+    )
+    .unwrap();
+    fs::set_permissions(&systemctl, fs::Permissions::from_mode(0o755)).unwrap();
+    // Stands in for the socket systemd listens on.
+    let runtime = temp.path().join("run/hometree");
+    fs::create_dir_all(&runtime).unwrap();
+    let listener = std::os::unix::net::UnixListener::bind(runtime.join("daemon.sock")).unwrap();
+    listener.set_nonblocking(true).unwrap();
+
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap());
+    daemon_cmd(&temp)
+        .env("PATH", path)
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(contains(
+            "ok    daemon: socket-activated; hometree.socket starts it",
+        ));
+    assert_eq!(
+        listener.accept().unwrap_err().kind(),
+        std::io::ErrorKind::WouldBlock
+    );
+}
+
+#[test]
+fn why_explains_managed_and_ignored_paths() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home-src");
+    let xdg_root = temp.path().join("xdg-root");
+    let app = home.join(".config/app");
+    fs::create_dir_all(&app).unwrap();
+    fs::write(app.join("app.toml"), "theme = \"dark\"\n").unwrap();
+    fs::write(app.join("api-token"), "abc").unwrap();
+    let hometree = || cmd_with_overrides(&temp, &home, &xdg_root);
+
+    hometree().arg("init").assert().success();
+    hometree()
+        .args(["track", app.join("app.toml").to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    hometree()
+        .args(["snapshot", "-m", "initial"])
+        .assert()
+        .success();
+
+    hometree()
+        .args(["why", ".config/app/app.toml"])
+        .assert()
+        .success()
+        .stdout(contains("path: .config/app/app.toml"))
+        .stdout(contains("exists: file"))
+        .stdout(contains("[x] .config/app/app.toml"))
+        .stdout(contains("managed: yes"))
+        .stdout(contains("secret: no"))
+        .stdout(contains("result: off (watch.auto_add_new = false)"))
+        .stdout(contains("git: tracked in the index; last committed in "));
+
+    hometree()
+        .args(["why", app.join("api-token").to_string_lossy().as_ref()])
+        .assert()
+        .success()
+        .stdout(contains("[x] **/*token*"))
+        .stdout(contains(
+            "managed: no, excluded by ignore pattern '**/*token*'",
+        ))
+        .stdout(contains("track: refused without --force"))
+        .stdout(contains("git: not tracked; never committed"));
+
+    hometree()
+        .args(["why", ".config/other/missing.conf"])
+        .assert()
+        .success()
+        .stdout(contains("exists: missing"))
+        .stdout(contains("managed: no, no manage.paths entry matches"))
+        .stdout(contains("track: would add it to manage.paths"));
+}
+
+#[test]
+fn why_reports_untracked_managed_paths_are_not_auto_staged() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home-src");
+    let xdg_root = temp.path().join("xdg-root");
+    let app = home.join(".config/app");
+    fs::create_dir_all(&app).unwrap();
+    fs::write(app.join("app.toml"), "theme = \"dark\"\n").unwrap();
+    fs::write(app.join("extra.toml"), "x = 1\n").unwrap();
+    let hometree = || cmd_with_overrides(&temp, &home, &xdg_root);
+
+    hometree().arg("init").assert().success();
+    let config_path = xdg_root.join("config/hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.manage.paths = vec![".config/app".to_string()];
+    cfg.write_to(&config_path).unwrap();
+    hometree()
+        .args(["track", app.join("app.toml").to_string_lossy().as_ref()])
+        .assert()
+        .success();
+
+    hometree()
+        .args(["why", ".config/app/app.toml"])
+        .assert()
+        .success()
+        .stdout(contains("daemon: stages changes after"));
+    hometree()
+        .args(["why", ".config/app/extra.toml"])
+        .assert()
+        .success()
+        .stdout(contains("managed: yes"))
+        .stdout(contains(
+            "daemon: not staged automatically (auto_stage_tracked_only; path is untracked)",
+        ));
+
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.watch.auto_stage_tracked_only = false;
+    cfg.write_to(&config_path).unwrap();
+    hometree()
+        .args(["why", ".config/app/extra.toml"])
+        .assert()
+        .success()
+        .stdout(contains("daemon: stages changes after"));
+}
+
+#[test]
+fn export_and_init_from_bundle_restores_commit_and_secrets() {
+    let temp = TempDir::new().unwrap();
+    let home_src = temp.path().join("home-src");
+    let xdg_src = temp.path().join("xdg-src");
+    let home_target = temp.path().join("home-target");
+    let xdg_target = temp.path().join("xdg-target");
+    fs::create_dir_all(&home_src).unwrap();
+    fs::create_dir_all(&home_target).unwrap();
+
+    let app_config = home_src.join(".config/app/config.toml");
+    let secret_path = home_src.join(".config/app/token");
+    fs::create_dir_all(app_config.parent().unwrap()).unwrap();
+    fs::write(&app_config, "v1").unwrap();
+    fs::write(&secret_path, "top-secret").unwrap();
+
+    let identity = age::x25519::Identity::generate();
+    let identity_path = temp.path().join("identity.txt");
+    fs::write(
+        &identity_path,
+        identity.to_string().expose_secret().as_bytes(),
+    )
+    .unwrap();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_src)
+        .arg("init")
+        .assert()
+        .success();
+    let config_path = xdg_src.join("config/hometree/config.toml");
+    let mut cfg = Config::load_from(&config_path).unwrap();
+    cfg.secrets.enabled = true;
+    cfg.secrets.recipients = vec![identity.to_public().to_string()];
+    cfg.secrets.identity_files = vec![identity_path];
+    cfg.write_to(&config_path).unwrap();
+
+    cmd_with_overrides(&temp, &home_src, &xdg_src)
+        .args(["track", app_config.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd_with_overrides(&temp, &home_src, &xdg_src)
+        .args(["secret", "add", secret_path.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd_with_overrides(&temp, &home_src, &xdg_src)
+        .args(["snapshot", "-m", "first"])
+        .assert()
+        .success();
+    fs::write(&app_config, "v2").unwrap();
+    cmd_with_overrides(&temp, &home_src, &xdg_src)
+        .args(["track", app_config.to_string_lossy().as_ref()])
+        .assert()
+        .success();
+    cmd_with_overrides(&temp, &home_src, &xdg_src)
+        .args(["snapshot", "-m", "second"])
+        .assert()
+        .success();
+    let first = git_rev(&xdg_src.join("data/hometree/repo.git"), &home_src, "HEAD~1");
+
+    let archive = temp.path().join("home.htx");
+    cmd_with_overrides(&temp, &home_src, &xdg_src)
+        .args(["export", "HEAD~1", "-o", archive.to_string_lossy().as_ref()])
+        .assert()
+        .success()
+        .stdout(contains("1 encrypted secret(s)"));
+
+    // A damaged archive is rejected before anything is restored.
+    let tampered = temp.path().join("tampered.htx");
+    let mut bytes = fs::read(&archive).unwrap();
+    *bytes.last_mut().unwrap() ^= 0x01;
+    fs::write(&tampered, bytes).unwrap();
+    let xdg_bad = temp.path().join("xdg-bad");
+    cmd_with_overrides(&temp, &home_target, &xdg_bad)
+        .args(["init", "--from-bundle", tampered.to_string_lossy().as_ref()])
+        .assert()
+        .failure()
+        .stderr(contains("checksum mismatch for config.toml"));
+    assert!(!xdg_bad.join("data/hometree/repo.git").exists());
+    assert!(!home_target.join(".config/app/config.toml").exists());
+
+    cmd_with_overrides(&temp, &home_target, &xdg_target)
+        .args(["init", "--from-bundle", archive.to_string_lossy().as_ref()])
+        .assert()
+        .success()
+        .stdout(contains("verified"))
+        .stdout(contains("deployed"));
+
+    let repo = xdg_target.join("data/hometree/repo.git");
+    assert_eq!(git_rev(&repo, &home_target, "HEAD"), first);
+    assert_eq!(
+        fs::read_to_string(home_target.join(".config/app/config.toml")).unwrap(),
+        "v1"
+    );
+    assert_eq!(
+        fs::read_to_string(home_target.join(".config/app/token")).unwrap(),
+        "top-secret"
+    );
+    let restored = Config::load_from(&xdg_target.join("config/hometree/config.toml")).unwrap();
+    assert_eq!(restored.repo.work_tree, home_target.canonicalize().unwrap());
+    assert_eq!(restored.repo.git_dir, repo.canonicalize().unwrap());
+
+    cmd_with_overrides(&temp, &home_target, &xdg_target)
+        .arg("status")
+        .assert()
+        .success();
+}
@@ -1,5 +1,7 @@
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
@@ -15,6 +17,8 @@ pub struct Config {
     pub snapshot: SnapshotConfig,
     #[serde(default)]
     pub secrets: SecretsConfig,
+    #[serde(default)]
+    pub metadata: MetadataConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,11 +45,116 @@ pub struct WatchConfig {
     pub auto_stage_tracked_only: bool,
     pub auto_add_new: bool,
     pub auto_add_allow_patterns: Vec<String>,
+    /// Rescan interval for roots that fall back to polling.
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Prometheus textfile the daemon keeps up to date.
+    #[serde(default)]
+    pub metrics_textfile: Option<PathBuf>,
+    /// Debounce windows for paths that need a different one; the first match wins.
+    #[serde(default)]
+    pub debounce_overrides: Vec<DebounceOverride>,
+    #[serde(default)]
+    pub max_flushes_per_minute: Option<u32>,
+    /// Local-time windows such as `22:00-07:00` during which changes are only queued.
+    #[serde(default)]
+    pub quiet_hours: Vec<String>,
+    /// Only queue changes while the machine runs on battery.
+    #[serde(default)]
+    pub pause_on_battery: bool,
+}
+
+fn default_poll_interval_ms() -> u64 {
+    5000
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebounceOverride {
+    /// Glob relative to HOME.
+    pub pattern: String,
+    pub debounce_ms: u64,
+}
+
+/// A daily `HH:MM-HH:MM` window in local time; it may wrap past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    start: u16,
+    end: u16,
+}
+
+impl TimeWindow {
+    /// Minutes from `minute_of_day` until the window ends, or `None` when outside it.
+    pub fn remaining(&self, minute_of_day: u16) -> Option<u16> {
+        let inside = if self.start < self.end {
+            (self.start..self.end).contains(&minute_of_day)
+        } else {
+            minute_of_day >= self.start || minute_of_day < self.end
+        };
+        inside.then(|| (self.end + MINUTES_PER_DAY - minute_of_day) % MINUTES_PER_DAY)
+    }
+}
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+impl FromStr for TimeWindow {
+    type Err = crate::error::HometreeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            crate::error::HometreeError::Config(format!(
+                "invalid time window '{s}': expected HH:MM-HH:MM"
+            ))
+        };
+        let parse_time = |part: &str| -> Option<u16> {
+            let (hours, minutes) = part.trim().split_once(':')?;
+            let hours: u16 = hours.parse().ok()?;
+            let minutes: u16 = minutes.parse().ok()?;
+            (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+        };
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let start = parse_time(start).ok_or_else(invalid)?;
+        let end = parse_time(end).ok_or_else(invalid)?;
+        if start == end {
+            return Err(invalid());
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SnapshotConfig {
     pub auto_message_template: Option<String>,
+    /// Daemon commits staged changes at most this often.
+    #[serde(default)]
+    pub interval_minutes: Option<u64>,
+    /// Daemon commits once nothing has been staged for this long.
+    #[serde(default)]
+    pub quiet_minutes: Option<u64>,
+    /// Five-field cron expression, evaluated in local time.
+    #[serde(default)]
+    pub schedule: Option<String>,
+    /// Remote pushed to after each snapshot; the daemon retries failed pushes.
+    #[serde(default)]
+    pub auto_push_remote: Option<String>,
+}
+
+impl SnapshotConfig {
+    pub fn auto_enabled(&self) -> bool {
+        self.interval_minutes.is_some() || self.quiet_minutes.is_some() || self.schedule.is_some()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,9 +164,27 @@ pub struct SecretsConfig {
     pub backend: String,
     pub sidecar_suffix: String,
     pub recipients: Vec<String>,
+    /// Files listing one recipient per line; relative paths resolve against the work tree.
+    pub recipients_files: Vec<PathBuf>,
+    /// Directory inside the work tree whose files each list recipients.
+    pub recipients_dir: Option<String>,
     pub identity_files: Vec<PathBuf>,
     pub rules: Vec<SecretRule>,
     pub backup_policy: BackupPolicy,
+    /// Age in days after which `secret audit` reports a sidecar as stale.
+    pub stale_after_days: u64,
+}
+
+/// Optional manifest of file metadata that git itself does not record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetadataConfig {
+    pub enabled: bool,
+    /// Manifest location relative to the work tree.
+    pub manifest: String,
+    pub mtime: bool,
+    /// Record `user.*` xattrs and POSIX ACLs (`system.posix_acl_*`).
+    pub xattrs: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -85,9 +212,23 @@ impl Default for SecretsConfig {
             backend: "age".to_string(),
             sidecar_suffix: ".age".to_string(),
             recipients: Vec::new(),
+            recipients_files: Vec::new(),
+            recipients_dir: None,
             identity_files: Vec::new(),
             rules: Vec::new(),
             backup_policy: BackupPolicy::Encrypt,
+            stale_after_days: 365,
+        }
+    }
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            manifest: ".config/hometree/metadata.json".to_string(),
+            mtime: false,
+            xattrs: false,
         }
     }
 }
@@ -123,11 +264,22 @@ impl Config {
                 auto_stage_tracked_only: true,
                 auto_add_new: false,
                 auto_add_allow_patterns: Vec::new(),
+                poll_interval_ms: default_poll_interval_ms(),
+                metrics_textfile: None,
+                debounce_overrides: Vec::new(),
+                max_flushes_per_minute: None,
+                quiet_hours: Vec::new(),
+                pause_on_battery: false,
             },
             snapshot: SnapshotConfig {
                 auto_message_template: None,
+                interval_minutes: None,
+                quiet_minutes: None,
+                schedule: None,
+                auto_push_remote: None,
             },
             secrets: SecretsConfig::default(),
+            metadata: MetadataConfig::default(),
         }
     }
 
@@ -189,6 +341,17 @@ impl Config {
                 )));
             }
         }
+        for entry in &self.watch.debounce_overrides {
+            globset::Glob::new(&entry.pattern)?;
+        }
+        if self.watch.max_flushes_per_minute == Some(0) {
+            return Err(crate::error::HometreeError::Config(
+                "watch.max_flushes_per_minute must be at least 1".to_string(),
+            ));
+        }
+        for window in &self.watch.quiet_hours {
+            window.parse::<TimeWindow>()?;
+        }
         if self.secrets.enabled {
             if self.secrets.backend != "age" {
                 return Err(crate::error::HometreeError::Config(format!(
@@ -201,6 +364,52 @@ impl Config {
                     "secrets.sidecar_suffix cannot be empty".to_string(),
                 ));
             }
+            if let Some(dir) = &self.secrets.recipients_dir {
+                let dir_path = Path::new(dir);
+                if dir.trim().is_empty()
+                    || dir_path.is_absolute()
+                    || dir_path
+                        .components()
+                        .any(|c| matches!(c, std::path::Component::ParentDir))
+                {
+                    return Err(crate::error::HometreeError::Config(format!(
+                        "secrets.recipients_dir must be a relative path inside the work tree: {dir}"
+                    )));
+                }
+            }
+        }
+        if self.snapshot.interval_minutes == Some(0) || self.snapshot.quiet_minutes == Some(0) {
+            return Err(crate::error::HometreeError::Config(
+                "snapshot.interval_minutes and snapshot.quiet_minutes must be at least 1"
+                    .to_string(),
+            ));
+        }
+        if self
+            .snapshot
+            .auto_push_remote
+            .as_ref()
+            .is_some_and(|remote| remote.trim().is_empty())
+        {
+            return Err(crate::error::HometreeError::Config(
+                "snapshot.auto_push_remote cannot be empty".to_string(),
+            ));
+        }
+        if let Some(schedule) = &self.snapshot.schedule {
+            schedule.parse::<crate::snapshot::Schedule>()?;
+        }
+        if self.metadata.enabled {
+            let manifest = Path::new(&self.metadata.manifest);
+            if self.metadata.manifest.trim().is_empty()
+                || manifest.is_absolute()
+                || manifest
+                    .components()
+                    .any(|c| matches!(c, std::path::Component::ParentDir))
+            {
+                return Err(crate::error::HometreeError::Config(format!(
+                    "metadata.manifest must be a relative path inside the work tree: {}",
+                    self.metadata.manifest
+                )));
+            }
         }
         Ok(())
     }
@@ -250,7 +459,7 @@ fn is_overly_broad_pattern(pattern: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::{is_overly_broad_pattern, Config};
+    use super::{is_overly_broad_pattern, Config, TimeWindow};
     use crate::paths::Paths;
 
     #[test]
@@ -263,6 +472,21 @@ mod tests {
         assert!(!cfg.ignore.patterns.is_empty());
     }
 
+    #[test]
+    fn time_windows_may_wrap_midnight() {
+        let night: TimeWindow = "22:00-07:00".parse().unwrap();
+        assert_eq!(night.to_string(), "22:00-07:00");
+        assert_eq!(night.remaining(23 * 60), Some(8 * 60));
+        assert_eq!(night.remaining(6 * 60 + 59), Some(1));
+        assert_eq!(night.remaining(7 * 60), None);
+        let lunch: TimeWindow = "12:00-13:30".parse().unwrap();
+        assert_eq!(lunch.remaining(12 * 60), Some(90));
+        assert_eq!(lunch.remaining(13 * 60 + 30), None);
+        assert!("25:00-07:00".parse::<TimeWindow>().is_err());
+        assert!("07:00".parse::<TimeWindow>().is_err());
+        assert!("07:00-07:00".parse::<TimeWindow>().is_err());
+    }
+
     #[test]
     fn overly_broad_patterns_rejected() {
         assert!(is_overly_broad_pattern("*"));
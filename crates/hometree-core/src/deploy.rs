@@ -17,6 +17,8 @@ use crate::error::Result;
 use crate::generations::{append_generation, GenerationEntry};
 use crate::git::{GitBackend, TreeEntry};
 use crate::lock::acquire_lock;
+use crate::metadata::{apply_manifest, read_manifest_at};
+use crate::plaintext::Plaintext;
 use crate::secrets::{AgeBackend, SecretsBackend, SecretsManager};
 use crate::{Config, ManagedSet, Paths};
 
@@ -50,7 +52,10 @@ pub fn deploy_with_options(
         None
     };
     let secrets_backend = if secrets.enabled() {
-        Some(AgeBackend::from_config(&config.secrets)?)
+        Some(AgeBackend::from_config(
+            &config.secrets,
+            &config.repo.work_tree,
+        )?)
     } else {
         None
     };
@@ -91,6 +96,10 @@ pub fn deploy_with_options(
         &target_entries,
     )?;
 
+    if let Some(manifest) = read_manifest_at(config, git, &resolved)? {
+        apply_manifest(paths.home_dir(), &manifest, target_entries.keys())?;
+    }
+
     apply_secrets(
         paths.home_dir(),
         &secrets,
@@ -104,17 +113,7 @@ pub fn deploy_with_options(
     let target_paths: BTreeSet<PathBuf> = target_entries.keys().cloned().collect();
     delete_missing(paths.home_dir(), &current_paths, &target_paths)?;
 
-    let entry = GenerationEntry {
-        timestamp: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs(),
-        rev: resolved,
-        message: None,
-        host: std::env::var("HOSTNAME").unwrap_or_default(),
-        user: std::env::var("USER").unwrap_or_default(),
-        config_hash: None,
-    };
+    let entry = generation_entry(resolved, None);
     append_generation(paths.state_dir(), &entry)?;
     Ok(entry)
 }
@@ -128,7 +127,21 @@ pub fn rollback(
     deploy(config, paths, git, rev)
 }
 
-fn create_backup_dir(paths: &Paths) -> Result<PathBuf> {
+pub(crate) fn generation_entry(rev: String, message: Option<String>) -> GenerationEntry {
+    GenerationEntry {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        rev,
+        message,
+        host: std::env::var("HOSTNAME").unwrap_or_default(),
+        user: std::env::var("USER").unwrap_or_default(),
+        config_hash: None,
+    }
+}
+
+pub(crate) fn create_backup_dir(paths: &Paths) -> Result<PathBuf> {
     let ts = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
@@ -229,7 +242,11 @@ fn has_glob_meta(pattern: &str) -> bool {
     pattern.contains('*') || pattern.contains('?') || pattern.contains('[') || pattern.contains('{')
 }
 
-fn backup_current(backup_dir: &Path, home_dir: &Path, current: &BTreeSet<PathBuf>) -> Result<()> {
+pub(crate) fn backup_current(
+    backup_dir: &Path,
+    home_dir: &Path,
+    current: &BTreeSet<PathBuf>,
+) -> Result<()> {
     for rel in current {
         let src = home_dir.join(rel);
         let dest = backup_dir.join(rel);
@@ -255,7 +272,7 @@ fn backup_current(backup_dir: &Path, home_dir: &Path, current: &BTreeSet<PathBuf
     Ok(())
 }
 
-fn backup_secrets(
+pub(crate) fn backup_secrets(
     backup_dir: &Path,
     home_dir: &Path,
     secrets: &SecretsManager,
@@ -283,8 +300,8 @@ fn backup_secrets(
             crate::config::BackupPolicy::Encrypt => {
                 let backend = backend
                     .ok_or_else(|| std::io::Error::other("secrets backend missing for backup"))?;
-                let plaintext = fs::read(&plaintext_abs)?;
-                let ciphertext = backend.encrypt(&plaintext)?;
+                let plaintext = Plaintext::read_file(&plaintext_abs)?;
+                let ciphertext = backend.encrypt(plaintext.as_bytes())?;
                 let ciphertext_rel = secrets.ciphertext_path(rule);
                 let dest = backup_dir.join(ciphertext_rel);
                 if let Some(parent) = dest.parent() {
@@ -298,7 +315,7 @@ fn backup_secrets(
     Ok(())
 }
 
-fn apply_secrets(
+pub(crate) fn apply_secrets(
     home_dir: &Path,
     secrets: &SecretsManager,
     backend: Option<&AgeBackend>,
@@ -341,7 +358,7 @@ fn apply_secrets(
             options.mode(mode);
         }
         let mut file = options.open(&dest)?;
-        file.write_all(&plaintext)?;
+        file.write_all(plaintext.as_bytes())?;
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
@@ -453,7 +470,7 @@ fn restore_metadata(path: &Path, meta: &PreservedMetadata) {
 #[cfg(not(unix))]
 fn restore_metadata(_path: &Path, _meta: &PreservedMetadata) {}
 
-fn apply_target(
+pub(crate) fn apply_target(
     home_dir: &Path,
     git: &impl GitBackend,
     git_dir: &Path,
@@ -727,6 +744,15 @@ mod tests {
             unimplemented!()
         }
 
+        fn last_commit_for_path(
+            &self,
+            _git_dir: &Path,
+            _work_tree: &Path,
+            _path: &Path,
+        ) -> Result<Option<String>> {
+            unimplemented!()
+        }
+
         fn reset(&self, _git_dir: &Path, _work_tree: &Path, _rev: &str) -> Result<()> {
             unimplemented!()
         }
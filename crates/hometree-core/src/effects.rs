@@ -0,0 +1,241 @@
+//! Side effects of mutating commands, recorded so that `--dry-run` can report
+//! them instead of carrying them out.
+//!
+//! Commands run their usual logic and ask [`Effects::perform`] before every
+//! file write, index change, config change or history rewrite. In a dry run the
+//! effect is recorded and skipped; otherwise it is performed as before.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use crate::Config;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    /// Create or replace a file.
+    WriteFile {
+        path: PathBuf,
+    },
+    CopyFile {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    RemoveFile {
+        path: PathBuf,
+    },
+    CreateDir {
+        path: PathBuf,
+    },
+    /// Rewrite `config.toml`; `changes` come from [`config_changes`].
+    Config {
+        path: PathBuf,
+        changes: Vec<String>,
+    },
+    /// `git add` of paths relative to the work tree.
+    Stage {
+        paths: Vec<PathBuf>,
+    },
+    /// `git rm --cached` of paths relative to the work tree.
+    Unstage {
+        paths: Vec<PathBuf>,
+    },
+    ResetIndex {
+        rev: String,
+    },
+    Commit {
+        message: String,
+    },
+    GitConfig {
+        key: String,
+        value: String,
+    },
+    /// Remove every version of `path` from history.
+    RewriteHistory {
+        path: PathBuf,
+    },
+    InitRepo {
+        path: PathBuf,
+    },
+    Clone {
+        url: String,
+        path: PathBuf,
+    },
+    Pull {
+        remote: String,
+    },
+    Push {
+        remote: String,
+    },
+    Deploy {
+        rev: String,
+    },
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Effect::WriteFile { path } => write!(f, "write {}", path.display()),
+            Effect::CopyFile { from, to } => {
+                write!(f, "copy {} -> {}", from.display(), to.display())
+            }
+            Effect::RemoveFile { path } => write!(f, "remove {}", path.display()),
+            Effect::CreateDir { path } => write!(f, "create directory {}", path.display()),
+            Effect::Config { path, changes } => {
+                write!(f, "change {}", path.display())?;
+                if !changes.is_empty() {
+                    write!(f, ": {}", changes.join("; "))?;
+                }
+                Ok(())
+            }
+            Effect::Stage { paths } => write!(f, "stage {}", join_paths(paths)),
+            Effect::Unstage { paths } => write!(f, "unstage {}", join_paths(paths)),
+            Effect::ResetIndex { rev } => write!(f, "reset the index to {rev}"),
+            Effect::Commit { message } => write!(f, "commit {message:?}"),
+            Effect::GitConfig { key, value } => write!(f, "set git config {key}={value}"),
+            Effect::RewriteHistory { path } => {
+                write!(f, "rewrite history to purge {}", path.display())
+            }
+            Effect::InitRepo { path } => {
+                write!(f, "initialize a bare repository at {}", path.display())
+            }
+            Effect::Clone { url, path } => write!(f, "clone {url} into {}", path.display()),
+            Effect::Pull { remote } => write!(f, "pull from '{remote}'"),
+            Effect::Push { remote } => write!(f, "push to '{remote}'"),
+            Effect::Deploy { rev } => write!(f, "deploy {rev}"),
+        }
+    }
+}
+
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Gate for side effects; see the module docs.
+#[derive(Debug, Default)]
+pub struct Effects {
+    dry_run: bool,
+    recorded: RefCell<Vec<Effect>>,
+}
+
+impl Effects {
+    pub fn new(dry_run: bool) -> Self {
+        Self {
+            dry_run,
+            recorded: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Returns whether the caller should carry out `effect`; in a dry run it
+    /// is recorded instead.
+    pub fn perform(&self, effect: Effect) -> bool {
+        if self.dry_run {
+            self.recorded.borrow_mut().push(effect);
+        }
+        !self.dry_run
+    }
+
+    pub fn take(&self) -> Vec<Effect> {
+        std::mem::take(&mut self.recorded.borrow_mut())
+    }
+}
+
+/// Human-readable differences between two configs, one per changed key, e.g.
+/// `manage.paths += ".gitconfig"`.
+pub fn config_changes(before: &Config, after: &Config) -> Vec<String> {
+    let mut changes = Vec::new();
+    if let (Ok(before), Ok(after)) = (serde_json::to_value(before), serde_json::to_value(after)) {
+        diff_values("", &before, &after, &mut changes);
+    }
+    changes
+}
+
+fn diff_values(key: &str, before: &Value, after: &Value, out: &mut Vec<String>) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+            for name in keys {
+                let child = if key.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{key}.{name}")
+                };
+                diff_values(
+                    &child,
+                    before.get(name).unwrap_or(&Value::Null),
+                    after.get(name).unwrap_or(&Value::Null),
+                    out,
+                );
+            }
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            for item in after.iter().filter(|item| !before.contains(item)) {
+                out.push(format!("{key} += {item}"));
+            }
+            for item in before.iter().filter(|item| !after.contains(item)) {
+                out.push(format!("{key} -= {item}"));
+            }
+        }
+        _ if before != after => out.push(format!("{key} = {after} (was {before})")),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Paths;
+    use tempfile::TempDir;
+
+    #[test]
+    fn dry_run_records_instead_of_performing() {
+        let effects = Effects::new(true);
+        let effect = Effect::Stage {
+            paths: vec![PathBuf::from(".gitconfig")],
+        };
+        assert!(!effects.perform(effect.clone()));
+        assert_eq!(effects.take(), vec![effect]);
+        assert!(effects.take().is_empty());
+
+        let effects = Effects::default();
+        assert!(effects.perform(Effect::Deploy {
+            rev: "HEAD".to_string()
+        }));
+        assert!(effects.take().is_empty());
+    }
+
+    #[test]
+    fn config_changes_lists_changed_keys() {
+        let temp = TempDir::new().unwrap();
+        let paths =
+            Paths::new_with_overrides(Some(temp.path()), Some(&temp.path().join("xdg"))).unwrap();
+        let before = Config::default_with_paths(&paths);
+        let mut after = before.clone();
+        after.manage.paths.push(".gitconfig".to_string());
+        after.secrets.enabled = !before.secrets.enabled;
+
+        let changes = config_changes(&before, &after);
+        assert_eq!(
+            changes,
+            vec![
+                "manage.paths += \".gitconfig\"".to_string(),
+                format!(
+                    "secrets.enabled = {} (was {})",
+                    after.secrets.enabled, before.secrets.enabled
+                ),
+            ]
+        );
+        assert!(config_changes(&before, &before).is_empty());
+    }
+}
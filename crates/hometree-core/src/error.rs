@@ -20,6 +20,12 @@ pub enum HometreeError {
     InvalidPath(PathBuf),
     #[error("config validation error: {0}")]
     Config(String),
+    #[error("daemon ipc error: {0}")]
+    Ipc(String),
+    #[error("invalid export archive: {0}")]
+    Export(String),
+    #[error("cannot undo: {0}")]
+    Undo(String),
 }
 
 pub type Result<T> = std::result::Result<T, HometreeError>;
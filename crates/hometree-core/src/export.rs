@@ -0,0 +1,209 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{HometreeError, Result};
+use crate::secret_state::sha256_hex;
+
+const MAGIC: &str = "hometree-export";
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Archive entry holding the git bundle of the exported commit.
+pub const REPO_BUNDLE_ENTRY: &str = "repo.bundle";
+/// Archive entry holding the config.toml in effect at export time.
+pub const CONFIG_ENTRY: &str = "config.toml";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportEntry {
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub version: u32,
+    /// Revision as given on the command line.
+    pub rev: String,
+    /// Commit the revision resolved to; the bundle must restore exactly this.
+    pub commit: String,
+    /// Branch name the commit is restored onto.
+    pub branch: String,
+    pub created_at: u64,
+    /// Encrypted secret sidecars committed at `commit`.
+    #[serde(default)]
+    pub secrets: Vec<String>,
+    #[serde(default)]
+    pub entries: Vec<ExportEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportArchive {
+    pub manifest: ExportManifest,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl ExportArchive {
+    pub fn file(&self, name: &str) -> Option<&[u8]> {
+        self.files
+            .iter()
+            .find(|(entry, _)| entry == name)
+            .map(|(_, data)| data.as_slice())
+    }
+}
+
+/// Write an archive: a magic line, a one-line JSON manifest, then the entry
+/// payloads back to back. `manifest.entries` is filled in from `files`.
+pub fn write_export(
+    path: &Path,
+    mut manifest: ExportManifest,
+    files: &[(&str, &[u8])],
+) -> Result<ExportManifest> {
+    manifest.version = EXPORT_VERSION;
+    manifest.entries = files
+        .iter()
+        .map(|(name, data)| ExportEntry {
+            name: (*name).to_string(),
+            size: data.len() as u64,
+            sha256: sha256_hex(data),
+        })
+        .collect();
+
+    let mut out = format!("{MAGIC} {EXPORT_VERSION}\n").into_bytes();
+    out.extend(serde_json::to_vec(&manifest)?);
+    out.push(b'\n');
+    for (_, data) in files {
+        out.extend_from_slice(data);
+    }
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    // Append rather than replace the extension so `home.tmp` next to
+    // `home.htx` is left alone.
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, &out)?;
+    fs::rename(&tmp, path)?;
+    Ok(manifest)
+}
+
+/// Read an archive and check every entry against the sizes and checksums
+/// recorded in its manifest.
+pub fn read_export(path: &Path) -> Result<ExportArchive> {
+    let data = fs::read(path)?;
+    let (header, rest) = split_line(&data).ok_or_else(|| invalid("missing header"))?;
+    let version = std::str::from_utf8(header)
+        .ok()
+        .and_then(|line| line.strip_prefix(MAGIC))
+        .and_then(|version| version.trim().parse::<u32>().ok())
+        .ok_or_else(|| invalid("not a hometree export"))?;
+    if version != EXPORT_VERSION {
+        return Err(invalid(format!("unsupported version {version}")));
+    }
+    let (manifest_line, mut payload) =
+        split_line(rest).ok_or_else(|| invalid("missing manifest"))?;
+    let manifest: ExportManifest = serde_json::from_slice(manifest_line)
+        .map_err(|err| invalid(format!("unreadable manifest: {err}")))?;
+
+    let mut files = Vec::new();
+    for entry in &manifest.entries {
+        let size = usize::try_from(entry.size).map_err(|_| invalid("entry too large"))?;
+        if payload.len() < size {
+            return Err(invalid(format!("{} is truncated", entry.name)));
+        }
+        let (body, tail) = payload.split_at(size);
+        if sha256_hex(body) != entry.sha256 {
+            return Err(invalid(format!("checksum mismatch for {}", entry.name)));
+        }
+        files.push((entry.name.clone(), body.to_vec()));
+        payload = tail;
+    }
+    if !payload.is_empty() {
+        return Err(invalid(format!(
+            "{} unexpected trailing byte(s)",
+            payload.len()
+        )));
+    }
+    Ok(ExportArchive { manifest, files })
+}
+
+fn split_line(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let pos = data.iter().position(|b| *b == b'\n')?;
+    Some((&data[..pos], &data[pos + 1..]))
+}
+
+fn invalid(message: impl Into<String>) -> HometreeError {
+    HometreeError::Export(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn manifest() -> ExportManifest {
+        ExportManifest {
+            version: 0,
+            rev: "HEAD".to_string(),
+            commit: "0123abcd".to_string(),
+            branch: "main".to_string(),
+            created_at: 1_700_000_000,
+            secrets: vec![".ssh/id_ed25519.age".to_string()],
+            entries: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let dir = TempDir::new().expect("tempdir");
+        let path = dir.path().join("out/home.htx");
+        let neighbour = dir.path().join("out/home.tmp");
+        fs::create_dir_all(dir.path().join("out")).unwrap();
+        fs::write(&neighbour, b"keep").unwrap();
+        let written = write_export(
+            &path,
+            manifest(),
+            &[(REPO_BUNDLE_ENTRY, b"bundle\nbytes"), (CONFIG_ENTRY, b"")],
+        )
+        .expect("write");
+        assert_eq!(written.version, EXPORT_VERSION);
+        assert_eq!(written.entries.len(), 2);
+
+        let archive = read_export(&path).expect("read");
+        assert_eq!(archive.manifest, written);
+        assert_eq!(archive.file(REPO_BUNDLE_ENTRY), Some(&b"bundle\nbytes"[..]));
+        assert_eq!(archive.file(CONFIG_ENTRY), Some(&b""[..]));
+        assert!(archive.file("missing").is_none());
+        assert_eq!(fs::read(&neighbour).unwrap(), b"keep");
+    }
+
+    #[test]
+    fn read_export_rejects_damaged_archives() {
+        let dir = TempDir::new().expect("tempdir");
+        let path = dir.path().join("home.htx");
+        write_export(&path, manifest(), &[(REPO_BUNDLE_ENTRY, b"bundle bytes")]).expect("write");
+        let good = fs::read(&path).expect("read");
+
+        let mut flipped = good.clone();
+        *flipped.last_mut().unwrap() ^= 0x01;
+        fs::write(&path, &flipped).unwrap();
+        let err = read_export(&path).unwrap_err().to_string();
+        assert!(err.contains("checksum mismatch for repo.bundle"), "{err}");
+
+        fs::write(&path, &good[..good.len() - 3]).unwrap();
+        let err = read_export(&path).unwrap_err().to_string();
+        assert!(err.contains("truncated"), "{err}");
+
+        let mut extra = good.clone();
+        extra.extend_from_slice(b"xx");
+        fs::write(&path, &extra).unwrap();
+        let err = read_export(&path).unwrap_err().to_string();
+        assert!(err.contains("trailing"), "{err}");
+
+        fs::write(&path, b"PK\x03\x04 not ours\n").unwrap();
+        let err = read_export(&path).unwrap_err().to_string();
+        assert!(err.contains("not a hometree export"), "{err}");
+    }
+}
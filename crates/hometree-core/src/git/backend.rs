@@ -1,5 +1,7 @@
 use std::path::{Path, PathBuf};
 
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FileStatus {
     pub path: String,
@@ -45,6 +47,14 @@ pub enum GitError {
     Io(#[from] std::io::Error),
 }
 
+/// What `git diff` compares: the index (or `rev`) against the work tree, or with
+/// `cached`, `rev` (default `HEAD`) against the index. `rev` may be an `a..b` range.
+#[derive(Debug, Clone, Default)]
+pub struct DiffSpec {
+    pub cached: bool,
+    pub rev: Option<String>,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum AddMode {
     /// Stage only tracked changes (equivalent to git add -u).
@@ -131,24 +141,40 @@ pub trait GitBackend {
 
     fn pull(&self, git_dir: &Path, work_tree: &Path, remote: &str) -> GitResult<String>;
 
+    /// Return the most recent commit that touched `path`, or `None` if it was never committed.
+    fn last_commit_for_path(
+        &self,
+        git_dir: &Path,
+        work_tree: &Path,
+        path: &Path,
+    ) -> GitResult<Option<String>>;
+
     /// Reset the index to match a given revision without touching the work tree.
     /// Equivalent to `git reset <rev>` (mixed reset).
     fn reset(&self, git_dir: &Path, work_tree: &Path, rev: &str) -> GitResult<()>;
 }
 
+/// One stage-0 entry of the index, as `git ls-files -s` lists it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub mode: String,
+    pub oid: String,
+    pub path: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TreeEntry {
     pub mode: String,
     pub path: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct RemoteInfo {
     pub name: String,
     pub url: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct LogEntry {
     pub hash: String,
     pub date: String,
@@ -156,13 +182,14 @@ pub struct LogEntry {
     pub files: Vec<FileChange>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct FileChange {
     pub status: FileChangeStatus,
     pub path: String,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum FileChangeStatus {
     Added,
     Modified,
@@ -1,14 +1,21 @@
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 use super::backend::{
-    AddMode, BranchInfo, FileChange, FileChangeStatus, FileStatus, GitBackend, GitError, GitResult,
-    LogEntry, RemoteInfo, StatusCode, TreeEntry,
+    AddMode, BranchInfo, DiffSpec, FileChange, FileChangeStatus, FileStatus, GitBackend, GitError,
+    GitResult, IndexEntry, LogEntry, RemoteInfo, StatusCode, TreeEntry,
 };
 
 const FIELD_DELIM: &str = "\x1e";
 const COMMIT_DELIM: &str = "\x1f";
 
+/// Object id of the empty tree, the "parent" a root commit is diffed against.
+pub const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Ref an exported commit travels under inside a git bundle.
+const EXPORT_REF: &str = "refs/hometree/export";
+
 #[derive(Debug, Default, Clone)]
 pub struct GitCliBackend;
 
@@ -403,6 +410,25 @@ impl GitBackend for GitCliBackend {
         self.run_command(git_dir, work_tree, &["pull", remote])
     }
 
+    fn last_commit_for_path(
+        &self,
+        git_dir: &Path,
+        _work_tree: &Path,
+        path: &Path,
+    ) -> GitResult<Option<String>> {
+        let output = self.run_command_bare(
+            git_dir,
+            &["log", "-1", "--format=%H", "--", &path.to_string_lossy()],
+        );
+        match output {
+            Ok(out) if out.trim().is_empty() => Ok(None),
+            Ok(out) => Ok(Some(out.trim().to_string())),
+            // A repository without commits has no history for any path.
+            Err(GitError::CommandFailed(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     fn reset(&self, git_dir: &Path, work_tree: &Path, rev: &str) -> GitResult<()> {
         self.run_command(git_dir, work_tree, &["reset", rev])?;
         Ok(())
@@ -410,6 +436,143 @@ impl GitBackend for GitCliBackend {
 }
 
 impl GitCliBackend {
+    /// Paths with changes staged in the index, relative to the work tree.
+    pub fn staged_paths(&self, git_dir: &Path, work_tree: &Path) -> GitResult<Vec<String>> {
+        let output = self.run_command(
+            git_dir,
+            work_tree,
+            &["diff", "--cached", "--name-only", "--no-renames", "-z"],
+        )?;
+        Ok(output
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Index entries at or below `paths`, relative to the work tree.
+    pub fn tracked_paths(
+        &self,
+        git_dir: &Path,
+        work_tree: &Path,
+        paths: &[PathBuf],
+    ) -> GitResult<Vec<String>> {
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut args = vec![
+            "ls-files".to_string(),
+            "-z".to_string(),
+            "--full-name".to_string(),
+            "--".to_string(),
+        ];
+        args.extend(
+            paths
+                .iter()
+                .map(|path| format!(":(top,literal){}", path.to_string_lossy())),
+        );
+        let output = self.run_command_owned(git_dir, work_tree, &args)?;
+        Ok(output
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Files changed under `pathspecs` for `spec`, without rename detection.
+    pub fn diff_changes(
+        &self,
+        git_dir: &Path,
+        work_tree: &Path,
+        spec: &DiffSpec,
+        pathspecs: &[PathBuf],
+    ) -> GitResult<Vec<FileChange>> {
+        if pathspecs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pathspecs = pathspecs
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned());
+        let args = diff_args(spec, &["--name-status", "-z"], pathspecs);
+        let output = self.run_command_owned(git_dir, work_tree, &args)?;
+        let mut fields = output.split('\0').filter(|field| !field.is_empty());
+        let mut changes = Vec::new();
+        while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+            changes.push(FileChange {
+                status: parse_file_change_status(status),
+                path: path.to_string(),
+            });
+        }
+        Ok(changes)
+    }
+
+    /// Raw `git diff` output for exactly `paths`; `options` go before the revisions.
+    pub fn diff(
+        &self,
+        git_dir: &Path,
+        work_tree: &Path,
+        spec: &DiffSpec,
+        paths: &[PathBuf],
+        options: &[&str],
+    ) -> GitResult<Vec<u8>> {
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+        let output = Command::new("git")
+            .current_dir(work_tree)
+            .args(["--git-dir", git_dir.to_string_lossy().as_ref()])
+            .args(["--work-tree", work_tree.to_string_lossy().as_ref()])
+            .args(diff_args(
+                spec,
+                options,
+                paths
+                    .iter()
+                    .map(|path| format!(":(top,literal){}", path.to_string_lossy())),
+            ))
+            .output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::CommandFailed(stderr.to_string()));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Number of commits on `HEAD` that `remote` does not have yet, based on the
+    /// remote-tracking branch. Counts every commit when nothing was pushed before.
+    pub fn unpushed_count(
+        &self,
+        git_dir: &Path,
+        work_tree: &Path,
+        remote: &str,
+    ) -> GitResult<usize> {
+        if self
+            .run_command(git_dir, work_tree, &["rev-parse", "--verify", "-q", "HEAD"])
+            .is_err()
+        {
+            return Ok(0);
+        }
+        let branch =
+            self.run_command(git_dir, work_tree, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+        let tracking = format!("refs/remotes/{remote}/{}", branch.trim());
+        let range = if self
+            .run_command(
+                git_dir,
+                work_tree,
+                &["rev-parse", "--verify", "-q", &tracking],
+            )
+            .is_ok()
+        {
+            format!("{tracking}..HEAD")
+        } else {
+            "HEAD".to_string()
+        };
+        let count = self.run_command(git_dir, work_tree, &["rev-list", "--count", &range])?;
+        count
+            .trim()
+            .parse()
+            .map_err(|_| GitError::CommandFailed(format!("unexpected rev-list output: {count}")))
+    }
+
     pub fn remove_cached(&self, git_dir: &Path, work_tree: &Path, path: &Path) -> GitResult<()> {
         let pathspec = format!(":(top){}", path.to_string_lossy());
         let output = Command::new("git")
@@ -426,6 +589,170 @@ impl GitCliBackend {
         Ok(())
     }
 
+    /// Header of commit `rev` as `git show -s` prints it: hash, author, date and
+    /// the full message.
+    pub fn commit_header(&self, git_dir: &Path, rev: &str) -> GitResult<String> {
+        self.run_command_bare(
+            git_dir,
+            &[
+                "show",
+                "-s",
+                "--no-color",
+                "--format=medium",
+                "--end-of-options",
+                rev,
+            ],
+        )
+    }
+
+    /// Move `paths` in the index back to their `HEAD` version, or drop them from
+    /// the index when there is no commit yet. The work tree is left alone.
+    pub fn unstage(&self, git_dir: &Path, work_tree: &Path, paths: &[PathBuf]) -> GitResult<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+        let has_head = self
+            .run_command(git_dir, work_tree, &["rev-parse", "--verify", "-q", "HEAD"])
+            .is_ok();
+        let mut args: Vec<String> = if has_head {
+            vec!["reset".into(), "-q".into(), "HEAD".into(), "--".into()]
+        } else {
+            vec![
+                "rm".into(),
+                "--cached".into(),
+                "-q".into(),
+                "-r".into(),
+                "--".into(),
+            ]
+        };
+        args.extend(literal_pathspecs(paths));
+        self.run_command_owned(git_dir, work_tree, &args)?;
+        Ok(())
+    }
+
+    /// Index entries under `pathspecs`; empty when none of them is in the index.
+    pub fn index_entries(
+        &self,
+        git_dir: &Path,
+        work_tree: &Path,
+        pathspecs: &[PathBuf],
+    ) -> GitResult<Vec<IndexEntry>> {
+        if pathspecs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut args = vec![
+            "ls-files".to_string(),
+            "-s".into(),
+            "-z".into(),
+            "--".into(),
+        ];
+        args.extend(literal_pathspecs(pathspecs));
+        let output = self.run_command_owned(git_dir, work_tree, &args)?;
+        let mut entries = Vec::new();
+        for record in output.split('\0').filter(|record| !record.is_empty()) {
+            let (meta, path) = record
+                .split_once('\t')
+                .ok_or_else(|| GitError::ParseError(format!("invalid ls-files entry: {record}")))?;
+            let mut fields = meta.split(' ');
+            if let (Some(mode), Some(oid), Some("0")) =
+                (fields.next(), fields.next(), fields.next())
+            {
+                entries.push(IndexEntry {
+                    mode: mode.to_string(),
+                    oid: oid.to_string(),
+                    path: path.to_string(),
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Make the index under `pathspecs` hold exactly `entries` again. Entries
+    /// whose object no longer exists are skipped and returned.
+    pub fn restore_index(
+        &self,
+        git_dir: &Path,
+        work_tree: &Path,
+        pathspecs: &[PathBuf],
+        entries: &[IndexEntry],
+    ) -> GitResult<Vec<IndexEntry>> {
+        if !pathspecs.is_empty() {
+            let mut args = vec![
+                "rm".to_string(),
+                "-r".into(),
+                "-q".into(),
+                "--cached".into(),
+                "--ignore-unmatch".into(),
+                "--".into(),
+            ];
+            args.extend(literal_pathspecs(pathspecs));
+            self.run_command_owned(git_dir, work_tree, &args)?;
+        }
+        let (present, missing): (Vec<_>, Vec<_>) = entries.iter().cloned().partition(|entry| {
+            self.run_command_bare(git_dir, &["cat-file", "-e", &entry.oid])
+                .is_ok()
+        });
+        if present.is_empty() {
+            return Ok(missing);
+        }
+        let mut info = String::new();
+        for entry in &present {
+            info.push_str(&format!("{} {}\t{}\0", entry.mode, entry.oid, entry.path));
+        }
+        let mut child = Command::new("git")
+            .current_dir(work_tree)
+            .args(["--git-dir", git_dir.to_string_lossy().as_ref()])
+            .args(["--work-tree", work_tree.to_string_lossy().as_ref()])
+            .args(["update-index", "-z", "--index-info"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(info.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::CommandFailed(stderr.to_string()));
+        }
+        Ok(missing)
+    }
+
+    /// Apply `patch` to the index only (`git apply --cached`); `reverse` takes a
+    /// staged change back out.
+    pub fn apply_cached(
+        &self,
+        git_dir: &Path,
+        work_tree: &Path,
+        patch: &[u8],
+        reverse: bool,
+    ) -> GitResult<()> {
+        let mut cmd = Command::new("git");
+        cmd.current_dir(work_tree)
+            .args(["--git-dir", git_dir.to_string_lossy().as_ref()])
+            .args(["--work-tree", work_tree.to_string_lossy().as_ref()])
+            .args(["apply", "--cached", "--whitespace=nowarn"]);
+        if reverse {
+            cmd.arg("--reverse");
+        }
+        let mut child = cmd
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(patch)?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::CommandFailed(stderr.to_string()));
+        }
+        Ok(())
+    }
+
     pub fn file_in_history(
         &self,
         git_dir: &Path,
@@ -509,6 +836,88 @@ impl GitCliBackend {
 
         Ok(())
     }
+
+    /// Branch HEAD points at, or `None` when HEAD is detached.
+    pub fn current_branch(&self, git_dir: &Path) -> GitResult<Option<String>> {
+        match self.run_command_bare(git_dir, &["symbolic-ref", "--quiet", "--short", "HEAD"]) {
+            Ok(out) => Ok(Some(out.trim().to_string())),
+            Err(GitError::CommandFailed(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write a git bundle of `rev` and its history to `out`, returning the
+    /// commit `rev` resolved to. The bundle carries it as `refs/hometree/export`.
+    pub fn create_bundle(&self, git_dir: &Path, rev: &str, out: &Path) -> GitResult<String> {
+        let commit = self
+            .run_command_bare(
+                git_dir,
+                &["rev-parse", "--verify", &format!("{rev}^{{commit}}")],
+            )?
+            .trim()
+            .to_string();
+        // git refuses to bundle a bare object id, so point a scratch ref at it.
+        self.run_command_bare(git_dir, &["update-ref", EXPORT_REF, &commit])?;
+        let created = self.run_command_bare(
+            git_dir,
+            &[
+                "bundle",
+                "create",
+                out.to_string_lossy().as_ref(),
+                EXPORT_REF,
+            ],
+        );
+        let _ = self.run_command_bare(git_dir, &["update-ref", "-d", EXPORT_REF]);
+        created?;
+        Ok(commit)
+    }
+
+    /// Verify a bundle written by [`Self::create_bundle`] and fetch it onto
+    /// `branch`, which becomes HEAD. Returns the commit now at HEAD.
+    pub fn fetch_bundle(&self, git_dir: &Path, bundle: &Path, branch: &str) -> GitResult<String> {
+        let branch_ref = format!("refs/heads/{branch}");
+        self.run_command_bare(git_dir, &["check-ref-format", &branch_ref])?;
+        let bundle = bundle.to_string_lossy();
+        self.run_command_bare(git_dir, &["bundle", "verify", "--quiet", bundle.as_ref()])?;
+        self.run_command_bare(
+            git_dir,
+            &[
+                "fetch",
+                "--quiet",
+                bundle.as_ref(),
+                &format!("{EXPORT_REF}:{branch_ref}"),
+            ],
+        )?;
+        self.run_command_bare(git_dir, &["symbolic-ref", "HEAD", &branch_ref])?;
+        Ok(self
+            .run_command_bare(git_dir, &["rev-parse", "--verify", "HEAD^{commit}"])?
+            .trim()
+            .to_string())
+    }
+}
+
+fn literal_pathspecs(paths: &[PathBuf]) -> impl Iterator<Item = String> + '_ {
+    paths
+        .iter()
+        .map(|path| format!(":(top,literal){}", path.to_string_lossy()))
+}
+
+fn diff_args(
+    spec: &DiffSpec,
+    options: &[&str],
+    pathspecs: impl Iterator<Item = String>,
+) -> Vec<String> {
+    let mut args = vec!["diff".to_string(), "--no-renames".to_string()];
+    args.extend(options.iter().map(|option| option.to_string()));
+    if spec.cached {
+        args.push("--cached".to_string());
+    }
+    // `--end-of-options` keeps a revision from being read as an option.
+    args.push("--end-of-options".to_string());
+    args.extend(spec.rev.clone());
+    args.push("--".to_string());
+    args.extend(pathspecs);
+    args
 }
 
 fn parse_status_code(c: char) -> StatusCode {
@@ -740,6 +1149,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::octal_escapes)]
     fn test_parse_multiple_statuses() {
         let output =
             "? src/new1.rs\0? src/new2.rs\01 .M N... 100644 100644 100644 abc def src/lib.rs\0";
@@ -751,6 +1161,46 @@ mod tests {
         assert_eq!(result[2].path, "src/lib.rs");
     }
 
+    #[test]
+    fn test_last_commit_for_path() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let git_dir = temp.path().join("repo.git");
+        let work_tree = temp.path().join("home");
+        std::fs::create_dir_all(&work_tree).unwrap();
+        let git = GitCliBackend::new();
+        git.init_repo(&git_dir).unwrap();
+        let file = Path::new(".profile");
+        assert_eq!(
+            git.last_commit_for_path(&git_dir, &work_tree, file)
+                .unwrap(),
+            None
+        );
+
+        std::fs::write(work_tree.join(file), "export EDITOR=vi\n").unwrap();
+        git.add(&git_dir, &work_tree, &[file.to_path_buf()], AddMode::Paths)
+            .unwrap();
+        let committed = Command::new("git")
+            .args(["--git-dir", git_dir.to_str().unwrap()])
+            .args(["--work-tree", work_tree.to_str().unwrap()])
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+            .args(["commit", "-q", "-m", "add profile"])
+            .status()
+            .unwrap();
+        assert!(committed.success());
+
+        let head = git.rev_parse(&git_dir, &work_tree, "HEAD").unwrap();
+        assert_eq!(
+            git.last_commit_for_path(&git_dir, &work_tree, file)
+                .unwrap(),
+            Some(head)
+        );
+        assert_eq!(
+            git.last_commit_for_path(&git_dir, &work_tree, Path::new(".bashrc"))
+                .unwrap(),
+            None
+        );
+    }
+
     #[test]
     fn test_parse_branch_header_lines() {
         let output = "# branch.oid 1234567890abcdef\0# branch.head main\0# branch.upstream origin/main\0# branch.ab +2 -3\0";
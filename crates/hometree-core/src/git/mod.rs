@@ -2,7 +2,7 @@ mod backend;
 mod cli;
 
 pub use backend::{
-    AddMode, BranchInfo, FileChange, FileChangeStatus, FileStatus, GitBackend, GitError, GitResult,
-    LogEntry, RemoteInfo, StatusCode, TreeEntry,
+    AddMode, BranchInfo, DiffSpec, FileChange, FileChangeStatus, FileStatus, GitBackend, GitError,
+    GitResult, IndexEntry, LogEntry, RemoteInfo, StatusCode, TreeEntry,
 };
-pub use cli::GitCliBackend;
+pub use cli::{GitCliBackend, EMPTY_TREE};
@@ -0,0 +1,523 @@
+//! Daemon IPC protocol: newline-delimited JSON over a Unix socket.
+//!
+//! A connection starts with [`Request::Hello`]; the daemon answers with the
+//! protocol version both sides will speak. Any number of requests may follow on
+//! the same connection, except [`Request::Subscribe`], which turns the
+//! connection into a stream of [`EventRecord`] lines.
+//!
+//! Version 1 had no handshake and served one request per connection. Its
+//! request lines are still valid version 2 requests, so a daemon treats a
+//! connection that skips `hello` as version 1.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, Read, Write};
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{HometreeError, Result};
+use crate::paths::Paths;
+
+/// Protocol version spoken by this build.
+pub const PROTOCOL_VERSION: u32 = 2;
+/// Oldest protocol version this build still accepts.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+/// Maximum length of a single request or response line.
+pub const MAX_LINE: usize = 64 * 1024;
+/// How long a client waits for the daemon to answer a request it serves directly.
+pub const CLIENT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+/// How long a client waits for requests the daemon's event loop acts on; the
+/// loop may be busy staging or committing.
+pub const CONTROL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+/// Error message for a daemon that accepted a connection but did not answer in time.
+pub const NOT_RESPONDING: &str = "daemon not responding";
+pub const SOCKET_FILENAME: &str = "daemon.sock";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    /// Version handshake (v2+).
+    Hello {
+        version: u32,
+    },
+    Ping,
+    Status,
+    Pause {
+        #[serde(default)]
+        ttl_ms: Option<u64>,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    Resume,
+    Flush,
+    Reload,
+    Shutdown,
+    /// Stream [`EventRecord`]s until the client disconnects.
+    Subscribe,
+}
+
+impl Request {
+    /// How long a client should wait for the response.
+    pub fn timeout(&self) -> std::time::Duration {
+        match self {
+            Request::Hello { .. } | Request::Ping | Request::Status | Request::Subscribe => {
+                CLIENT_TIMEOUT
+            }
+            _ => CONTROL_TIMEOUT,
+        }
+    }
+
+    /// Parse a request line, reporting unknown commands the way v1 did.
+    pub fn parse(line: &str) -> std::result::Result<Self, String> {
+        match serde_json::from_str(line) {
+            Ok(request) => Ok(request),
+            Err(err) => {
+                let cmd = serde_json::from_str::<serde_json::Value>(line)
+                    .ok()
+                    .and_then(|v| v.get("cmd").and_then(|c| c.as_str()).map(str::to_string));
+                Err(match cmd {
+                    Some(cmd) if !KNOWN_COMMANDS.contains(&cmd.as_str()) => {
+                        format!("unknown command: {cmd}")
+                    }
+                    _ => format!("invalid request: {err}"),
+                })
+            }
+        }
+    }
+}
+
+const KNOWN_COMMANDS: &[&str] = &[
+    "hello",
+    "ping",
+    "status",
+    "pause",
+    "resume",
+    "flush",
+    "reload",
+    "shutdown",
+    "subscribe",
+];
+
+/// Response envelope, unchanged since v1.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response<T = serde_json::Value> {
+    pub ok: bool,
+    pub result: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> Response<T> {
+    pub fn ok(result: T) -> Self {
+        Self {
+            ok: true,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn err(error: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            result: None,
+            error: Some(error.into()),
+        }
+    }
+
+    pub fn into_result(self) -> Result<Option<T>> {
+        if self.ok {
+            Ok(self.result)
+        } else {
+            Err(HometreeError::Ipc(
+                self.error.unwrap_or_else(|| "unknown error".to_string()),
+            ))
+        }
+    }
+}
+
+/// Result of [`Request::Hello`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+    /// Version both sides speak from now on.
+    pub version: u32,
+    pub min_version: u32,
+    pub max_version: u32,
+    pub daemon_version: String,
+}
+
+/// Pick the version to speak with a client that offered `client_version`.
+pub fn negotiate(client_version: u32) -> std::result::Result<Hello, String> {
+    if client_version < MIN_PROTOCOL_VERSION {
+        return Err(format!(
+            "unsupported protocol version {client_version}; daemon supports {MIN_PROTOCOL_VERSION}..={PROTOCOL_VERSION}"
+        ));
+    }
+    Ok(Hello {
+        version: client_version.min(PROTOCOL_VERSION),
+        min_version: MIN_PROTOCOL_VERSION,
+        max_version: PROTOCOL_VERSION,
+        daemon_version: env!("CARGO_PKG_VERSION").to_string(),
+    })
+}
+
+/// Result of [`Request::Status`]. Fields added after v1 default when missing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub running_since: String,
+    pub config_path: String,
+    pub watch_roots: Vec<String>,
+    /// `native` or `poll` for each entry of `watch_roots`.
+    #[serde(default)]
+    pub watch_strategies: BTreeMap<String, String>,
+    pub queue_size: usize,
+    pub inhibited: bool,
+    pub inhibit_reason: Option<String>,
+    pub last_flush_at: Option<String>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<String>,
+    pub total_flushes: u64,
+    #[serde(default)]
+    pub last_snapshot_at: Option<String>,
+    #[serde(default)]
+    pub total_snapshots: u64,
+    #[serde(default)]
+    pub push_remote: Option<String>,
+    #[serde(default)]
+    pub unpushed_commits: usize,
+    #[serde(default)]
+    pub last_push_attempt_at: Option<String>,
+    #[serde(default)]
+    pub last_push_error: Option<String>,
+    /// `quiet_hours`, `on_battery` or `rate_limited` while changes are only queued.
+    #[serde(default)]
+    pub throttle: Option<String>,
+    #[serde(default)]
+    pub throttled_until: Option<String>,
+    #[serde(default)]
+    pub metrics: DaemonMetrics,
+}
+
+/// Counters and gauges behind `daemon metrics`. Counters reset when the daemon restarts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DaemonMetrics {
+    pub events_received: u64,
+    pub paths_staged: u64,
+    pub flush_failures: u64,
+    pub consecutive_flush_failures: u64,
+    /// Delay before the next flush retry; zero while flushes succeed.
+    pub retry_backoff_ms: u64,
+    pub queue_managed: usize,
+    pub queue_secrets: usize,
+    pub queue_auto_add: usize,
+    pub queue_renames: usize,
+    pub inhibited_ms_total: u64,
+    pub last_flush_duration_ms: Option<u64>,
+    /// First failure of the current run of failed flushes.
+    pub failing_since: Option<String>,
+}
+
+/// One line of the [`Request::Subscribe`] feed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub ts: String,
+    #[serde(flatten)]
+    pub event: DaemonEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DaemonEvent {
+    Staged {
+        paths: Vec<String>,
+    },
+    Flushed {
+        staged: usize,
+    },
+    Error {
+        message: String,
+    },
+    Inhibit {
+        inhibited: bool,
+        reason: Option<String>,
+    },
+    AutoAddSkipped {
+        path: String,
+        reason: String,
+    },
+    Snapshot {
+        message: String,
+    },
+    Pushed {
+        remote: String,
+    },
+    /// Changes are only queued while `reason` is set.
+    Throttle {
+        reason: Option<String>,
+        until: Option<String>,
+    },
+    /// An event kind this build does not know about yet.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Socket path under the runtime dir, if one is available.
+pub fn socket_path(paths: &Paths) -> Option<PathBuf> {
+    paths.runtime_dir().map(|dir| dir.join(SOCKET_FILENAME))
+}
+
+/// Read one line of at most [`MAX_LINE`] bytes plus its newline, without
+/// buffering more than that; `None` at end of stream.
+pub fn read_line(reader: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = Vec::new();
+    if reader
+        .by_ref()
+        .take(MAX_LINE as u64 + 1)
+        .read_until(b'\n', &mut line)?
+        == 0
+    {
+        return Ok(None);
+    }
+    if line.len() > MAX_LINE && !line.ends_with(b"\n") {
+        return Err(HometreeError::Ipc("message too large".to_string()));
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|_| HometreeError::Ipc("message is not valid UTF-8".to_string()))
+}
+
+/// Read one message; `None` at end of stream.
+pub fn read_message<T: DeserializeOwned>(reader: &mut impl BufRead) -> Result<Option<T>> {
+    match read_line(reader)? {
+        Some(line) => Ok(Some(serde_json::from_str(line.trim_end())?)),
+        None => Ok(None),
+    }
+}
+
+pub fn write_message<T: Serialize>(writer: &mut impl Write, message: &T) -> Result<()> {
+    let json = serde_json::to_string(message)?;
+    if json.len() > MAX_LINE {
+        return Err(HometreeError::Ipc("message too large".to_string()));
+    }
+    writer.write_all(json.as_bytes())?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+pub use client::{Client, EventStream};
+
+#[cfg(unix)]
+mod client {
+    use std::io::{BufReader, BufWriter};
+    use std::os::unix::net::UnixStream;
+    use std::path::{Path, PathBuf};
+
+    use serde::de::DeserializeOwned;
+
+    use super::{
+        read_line, read_message, write_message, DaemonStatus, EventRecord, Hello, Request,
+        Response, CLIENT_TIMEOUT, MIN_PROTOCOL_VERSION, NOT_RESPONDING, PROTOCOL_VERSION,
+    };
+    use crate::error::{HometreeError, Result};
+
+    /// Connection to a running daemon.
+    pub struct Client {
+        reader: BufReader<UnixStream>,
+        writer: BufWriter<UnixStream>,
+        socket: PathBuf,
+        version: u32,
+        /// v1 daemons close the connection after each response.
+        stale: bool,
+    }
+
+    impl Client {
+        /// Connect and negotiate a protocol version.
+        pub fn connect(socket: &Path) -> Result<Self> {
+            let mut client = Self::open(socket)?;
+            let hello: Response<Hello> = client.round_trip(&Request::Hello {
+                version: PROTOCOL_VERSION,
+            })?;
+            client.version = match hello.into_result() {
+                Ok(Some(hello)) => hello.version,
+                Ok(None) => return Err(HometreeError::Ipc("missing hello result".to_string())),
+                // A v1 daemon does not know `hello` and closes the connection
+                // after one request.
+                Err(HometreeError::Ipc(msg)) if msg == "unknown command: hello" => {
+                    client.version = 1;
+                    client.stale = true;
+                    return Ok(client);
+                }
+                Err(err) => return Err(err),
+            };
+            if client.version < MIN_PROTOCOL_VERSION {
+                return Err(HometreeError::Ipc(format!(
+                    "daemon speaks protocol {}, this client needs {MIN_PROTOCOL_VERSION} or newer",
+                    client.version
+                )));
+            }
+            Ok(client)
+        }
+
+        fn open(socket: &Path) -> Result<Self> {
+            let stream = UnixStream::connect(socket)?;
+            stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+            stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+            Ok(Self {
+                reader: BufReader::new(stream.try_clone()?),
+                writer: BufWriter::new(stream),
+                socket: socket.to_path_buf(),
+                version: PROTOCOL_VERSION,
+                stale: false,
+            })
+        }
+
+        /// Negotiated protocol version.
+        pub fn version(&self) -> u32 {
+            self.version
+        }
+
+        /// Send `request` and return its result payload.
+        pub fn request<T: DeserializeOwned>(&mut self, request: &Request) -> Result<Option<T>> {
+            if matches!(request, Request::Subscribe) {
+                return Err(HometreeError::Ipc(
+                    "use Client::subscribe for event streams".to_string(),
+                ));
+            }
+            let response: Response<T> = self.round_trip(request)?;
+            // v1 daemons answer one request per connection.
+            self.stale = self.version == 1;
+            response.into_result()
+        }
+
+        /// Send a request whose result carries no data.
+        pub fn command(&mut self, request: &Request) -> Result<()> {
+            self.request::<serde_json::Value>(request).map(|_| ())
+        }
+
+        pub fn status(&mut self) -> Result<DaemonStatus> {
+            self.request(&Request::Status)?
+                .ok_or_else(|| HometreeError::Ipc("missing status".to_string()))
+        }
+
+        /// Switch the connection to the event feed.
+        pub fn subscribe(mut self) -> Result<EventStream> {
+            let response: Response = self.round_trip(&Request::Subscribe)?;
+            response.into_result()?;
+            // Events may be minutes apart.
+            self.reader.get_ref().set_read_timeout(None)?;
+            Ok(EventStream {
+                reader: self.reader,
+            })
+        }
+
+        fn round_trip<T: DeserializeOwned>(&mut self, request: &Request) -> Result<Response<T>> {
+            if self.stale {
+                let fresh = Self::open(&self.socket)?;
+                self.reader = fresh.reader;
+                self.writer = fresh.writer;
+                self.stale = false;
+            }
+            self.reader
+                .get_ref()
+                .set_read_timeout(Some(request.timeout()))?;
+            write_message(&mut self.writer, request)
+                .and_then(|()| read_message(&mut self.reader))
+                .map_err(not_responding)?
+                .ok_or_else(|| HometreeError::Ipc("daemon closed the connection".to_string()))
+        }
+    }
+
+    fn not_responding(err: HometreeError) -> HometreeError {
+        match err {
+            HometreeError::Io(io)
+                if matches!(
+                    io.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                HometreeError::Ipc(NOT_RESPONDING.to_string())
+            }
+            err => err,
+        }
+    }
+
+    /// Iterator over events from [`Client::subscribe`]; ends when the daemon closes the socket.
+    pub struct EventStream {
+        reader: BufReader<UnixStream>,
+    }
+
+    impl EventStream {
+        /// Next event line exactly as the daemon sent it.
+        pub fn next_line(&mut self) -> Result<Option<String>> {
+            Ok(read_line(&mut self.reader)?.map(|line| line.trim_end().to_string()))
+        }
+    }
+
+    impl Iterator for EventStream {
+        type Item = Result<EventRecord>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            read_message(&mut self.reader).transpose()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{negotiate, read_line, Request, MAX_LINE, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+    use std::io::{BufReader, Cursor, Read};
+
+    #[test]
+    fn negotiate_clamps_to_supported_range() {
+        assert_eq!(
+            negotiate(PROTOCOL_VERSION).unwrap().version,
+            PROTOCOL_VERSION
+        );
+        assert_eq!(
+            negotiate(PROTOCOL_VERSION + 1).unwrap().version,
+            PROTOCOL_VERSION
+        );
+        assert_eq!(
+            negotiate(MIN_PROTOCOL_VERSION).unwrap().version,
+            MIN_PROTOCOL_VERSION
+        );
+        assert!(negotiate(MIN_PROTOCOL_VERSION - 1).is_err());
+    }
+
+    #[test]
+    fn parse_reports_unknown_commands() {
+        assert_eq!(Request::parse(r#"{"cmd":"ping"}"#), Ok(Request::Ping));
+        assert_eq!(
+            Request::parse(r#"{"cmd":"frobnicate"}"#),
+            Err("unknown command: frobnicate".to_string())
+        );
+        assert!(Request::parse(r#"{"cmd":"hello"}"#)
+            .unwrap_err()
+            .starts_with("invalid request"));
+    }
+
+    #[test]
+    fn read_line_stops_at_the_limit() {
+        // An endless line must fail without buffering it all.
+        let mut endless = BufReader::new(std::io::repeat(b'a'));
+        assert!(read_line(&mut endless)
+            .unwrap_err()
+            .to_string()
+            .contains("message too large"));
+
+        let longest = format!("{}\nnext\n", "a".repeat(MAX_LINE));
+        let mut reader = Cursor::new(longest.as_bytes());
+        assert_eq!(read_line(&mut reader).unwrap().unwrap().len(), MAX_LINE + 1);
+        assert_eq!(read_line(&mut reader).unwrap().as_deref(), Some("next\n"));
+        assert_eq!(read_line(&mut reader).unwrap(), None);
+
+        let mut unfinished = Cursor::new(b"{\"cmd\":\"ping\"}".as_slice()).take(64);
+        let mut unfinished = BufReader::new(&mut unfinished);
+        assert_eq!(
+            read_line(&mut unfinished).unwrap().as_deref(),
+            Some("{\"cmd\":\"ping\"}")
+        );
+    }
+}
@@ -1,26 +1,46 @@
 pub mod config;
 pub mod deploy;
+pub mod effects;
 pub mod error;
+pub mod export;
 pub mod generations;
 pub mod git;
 pub mod inhibit;
+pub mod ipc;
 pub mod lock;
 pub mod managed_set;
+pub mod metadata;
+pub mod oplog;
+pub mod output;
 pub mod paths;
+pub mod plaintext;
 pub mod plan;
+pub mod repair;
+pub mod secret_state;
 pub mod secrets;
+pub mod snapshot;
 pub mod verify;
 
 pub use config::Config;
 pub use deploy::{deploy, deploy_with_options, rollback, DeployOptions};
+pub use effects::{config_changes, Effect, Effects};
 pub use error::{HometreeError, Result};
+pub use export::{read_export, write_export, ExportArchive, ExportManifest};
 pub use generations::{append_generation, read_generations, GenerationEntry};
 pub use inhibit::{
     active_inhibit, clear_inhibit, inhibit_path, read_inhibit, write_inhibit, InhibitMarker,
 };
 pub use lock::{acquire_lock, lock_path};
 pub use managed_set::ManagedSet;
+pub use metadata::{capture_manifest, write_manifest, MetadataManifest};
+pub use oplog::{read_operations, undo_last, Operation, OperationRecorder, UndoOutcome};
 pub use paths::Paths;
+pub use plaintext::Plaintext;
 pub use plan::{plan_deploy, DeployPlan, PlanAction, PlanEntry};
-pub use secrets::{AgeBackend, SecretsBackend, SecretsManager};
+pub use repair::{repair, RepairOptions, RepairReport};
+pub use secret_state::{
+    audit_secrets, read_secret_state, write_secret_state, SecretAuditReport, SecretState,
+};
+pub use secrets::{effective_recipients, AgeBackend, SecretsBackend, SecretsManager};
+pub use snapshot::{render_message, MessageContext, Schedule};
 pub use verify::{verify, VerifyOptions, VerifyReport};
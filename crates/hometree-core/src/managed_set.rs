@@ -7,6 +7,29 @@ pub struct ManagedSet {
     paths: GlobSet,
     ignore_patterns: GlobSet,
     denylist_patterns: GlobSet,
+    /// Pattern strings in the order they were added, for `explain`.
+    sources: PatternSources,
+}
+
+struct PatternSources {
+    paths: Vec<String>,
+    ignore: Vec<String>,
+    denylist: Vec<String>,
+}
+
+/// Whether one configured pattern matches a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternMatch {
+    pub pattern: String,
+    pub matched: bool,
+}
+
+/// Pattern-by-pattern evaluation behind `ManagedSet::is_managed`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub paths: Vec<PatternMatch>,
+    pub ignore: Vec<PatternMatch>,
+    pub denylist: Vec<PatternMatch>,
 }
 
 impl ManagedSet {
@@ -35,17 +58,42 @@ impl ManagedSet {
         K: IntoIterator<Item = String>,
         L: IntoIterator<Item = String>,
     {
-        let paths = build_globset(paths)?;
-        let ignore_patterns = build_globset(ignore_patterns)?;
-        let denylist_patterns = build_globset(denylist_patterns)?;
+        let sources = PatternSources {
+            paths: paths.into_iter().collect(),
+            ignore: ignore_patterns.into_iter().collect(),
+            denylist: denylist_patterns.into_iter().collect(),
+        };
+        let paths = build_globset(sources.paths.iter().cloned())?;
+        let ignore_patterns = build_globset(sources.ignore.iter().cloned())?;
+        let denylist_patterns = build_globset(sources.denylist.iter().cloned())?;
 
         Ok(Self {
             paths,
             ignore_patterns,
             denylist_patterns,
+            sources,
         })
     }
 
+    pub fn explain(&self, path: &Path) -> Explanation {
+        let evaluate = |set: &GlobSet, patterns: &[String]| {
+            let matched = set.matches(path);
+            patterns
+                .iter()
+                .enumerate()
+                .map(|(index, pattern)| PatternMatch {
+                    pattern: pattern.clone(),
+                    matched: matched.contains(&index),
+                })
+                .collect()
+        };
+        Explanation {
+            paths: evaluate(&self.paths, &self.sources.paths),
+            ignore: evaluate(&self.ignore_patterns, &self.sources.ignore),
+            denylist: evaluate(&self.denylist_patterns, &self.sources.denylist),
+        }
+    }
+
     pub fn is_managed(&self, path: &Path) -> bool {
         let matches_path = self.paths.is_match(path);
         let is_ignored = self.ignore_patterns.is_match(path);
@@ -128,6 +176,28 @@ mod tests {
         assert!(!managed_set.is_managed(&PathBuf::from("non_managed.txt")));
     }
 
+    #[test]
+    fn explain_reports_each_pattern() {
+        let managed_set = ManagedSet::new(
+            normalize_paths(&["foo/".to_string(), "bar.txt".to_string()], None),
+            vec!["foo/*.log".to_string()],
+            vec!["**/*.bak".to_string()],
+        )
+        .unwrap();
+        let explanation = managed_set.explain(Path::new("foo/app.log"));
+        let matched = |matches: &[PatternMatch]| {
+            matches
+                .iter()
+                .filter(|m| m.matched)
+                .map(|m| m.pattern.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matched(&explanation.paths), ["foo/**"]);
+        assert_eq!(explanation.paths.len(), 2);
+        assert_eq!(matched(&explanation.ignore), ["foo/*.log"]);
+        assert!(matched(&explanation.denylist).is_empty());
+    }
+
     #[test]
     fn test_config_ignore_patterns_apply() {
         let paths = normalize_paths(&[".config/".to_string()], None);
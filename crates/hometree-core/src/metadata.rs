@@ -0,0 +1,431 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::MetadataConfig;
+use crate::deploy::collect_current_paths;
+use crate::error::{HometreeError, Result};
+use crate::git::GitBackend;
+use crate::secrets::SecretsManager;
+use crate::{Config, ManagedSet, Paths};
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Per-path metadata committed next to the tree, keyed by path relative to home.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataManifest {
+    pub version: u32,
+    #[serde(default)]
+    pub entries: BTreeMap<String, PathMetadata>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathMetadata {
+    /// Permission bits (including setuid/setgid/sticky) in octal, e.g. `"0600"`.
+    pub mode: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<i64>,
+    /// Extended attribute values, hex encoded.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub xattrs: BTreeMap<String, String>,
+}
+
+impl PathMetadata {
+    pub fn capture(path: &Path, options: &MetadataConfig) -> Result<Self> {
+        let meta = fs::metadata(path)?;
+        let mtime = if options.mtime {
+            Some(filetime::FileTime::from_last_modification_time(&meta).unix_seconds())
+        } else {
+            None
+        };
+        let xattrs = if options.xattrs {
+            read_xattrs(path)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            mode: format!("{:04o}", permission_bits(&meta)),
+            mtime,
+            xattrs,
+        })
+    }
+
+    pub fn mode_bits(&self) -> Result<u32> {
+        u32::from_str_radix(&self.mode, 8)
+            .ok()
+            .filter(|mode| *mode <= 0o7777)
+            .ok_or_else(|| {
+                HometreeError::Config(format!("invalid mode in manifest: {}", self.mode))
+            })
+    }
+
+    /// Apply the recorded mode, mtime and xattrs to `path`.
+    pub fn apply(&self, path: &Path) -> Result<()> {
+        set_permission_bits(path, self.mode_bits()?)?;
+        for (name, value) in &self.xattrs {
+            let value = decode_hex(value).ok_or_else(|| {
+                HometreeError::Config(format!("invalid xattr value in manifest: {name}"))
+            })?;
+            write_xattr(path, name, &value)?;
+        }
+        if let Some(mtime) = self.mtime {
+            filetime::set_file_mtime(path, filetime::FileTime::from_unix_time(mtime, 0))?;
+        }
+        Ok(())
+    }
+
+    /// Whether `path` still carries everything recorded here. Only recorded
+    /// xattrs are compared, so attributes added later are not drift.
+    pub fn matches(&self, path: &Path) -> Result<bool> {
+        let meta = fs::metadata(path)?;
+        if permission_bits(&meta) != self.mode_bits()? {
+            return Ok(false);
+        }
+        if let Some(mtime) = self.mtime {
+            if filetime::FileTime::from_last_modification_time(&meta).unix_seconds() != mtime {
+                return Ok(false);
+            }
+        }
+        if !self.xattrs.is_empty() {
+            let actual = read_xattrs(path)?;
+            if self
+                .xattrs
+                .iter()
+                .any(|(name, value)| actual.get(name) != Some(value))
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Capture metadata for every regular managed file currently on disk.
+pub fn capture_manifest(config: &Config, paths: &Paths) -> Result<MetadataManifest> {
+    let managed = ManagedSet::from_config(config, paths.home_dir())?;
+    let secrets = SecretsManager::from_config(&config.secrets);
+    let secrets_ref = if secrets.enabled() {
+        Some(&secrets)
+    } else {
+        None
+    };
+    let current = collect_current_paths(
+        &managed,
+        secrets_ref,
+        paths.home_dir(),
+        &config.manage.paths,
+    )?;
+    let manifest_rel = PathBuf::from(&config.metadata.manifest);
+
+    let mut manifest = MetadataManifest {
+        version: MANIFEST_VERSION,
+        entries: BTreeMap::new(),
+    };
+    for rel in current {
+        if rel == manifest_rel {
+            continue;
+        }
+        let abs = paths.home_dir().join(&rel);
+        match fs::symlink_metadata(&abs) {
+            Ok(meta) if meta.file_type().is_file() => {}
+            _ => continue,
+        }
+        manifest.entries.insert(
+            rel.to_string_lossy().to_string(),
+            PathMetadata::capture(&abs, &config.metadata)?,
+        );
+    }
+    Ok(manifest)
+}
+
+/// Write the manifest into the work tree and return its relative path.
+pub fn write_manifest(config: &Config, manifest: &MetadataManifest) -> Result<PathBuf> {
+    let rel = PathBuf::from(&config.metadata.manifest);
+    let abs = config.repo.work_tree.join(&rel);
+    if let Some(parent) = abs.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut contents = serde_json::to_string_pretty(manifest)?;
+    contents.push('\n');
+    fs::write(abs, contents)?;
+    Ok(rel)
+}
+
+/// Read the manifest committed at `rev`; `None` when disabled or not committed.
+pub fn read_manifest_at(
+    config: &Config,
+    git: &impl GitBackend,
+    rev: &str,
+) -> Result<Option<MetadataManifest>> {
+    if !config.metadata.enabled {
+        return Ok(None);
+    }
+    let blob = match git.show_blob(
+        &config.repo.git_dir,
+        &config.repo.work_tree,
+        rev,
+        Path::new(&config.metadata.manifest),
+    ) {
+        Ok(blob) => blob,
+        Err(_) => return Ok(None),
+    };
+    let manifest: MetadataManifest = serde_json::from_slice(&blob)?;
+    if manifest.version > MANIFEST_VERSION {
+        return Err(HometreeError::Config(format!(
+            "metadata manifest version {} is newer than supported ({MANIFEST_VERSION})",
+            manifest.version
+        )));
+    }
+    Ok(Some(manifest))
+}
+
+/// Apply recorded metadata to the given regular files under `home_dir`.
+pub(crate) fn apply_manifest<'a>(
+    home_dir: &Path,
+    manifest: &MetadataManifest,
+    rels: impl IntoIterator<Item = &'a PathBuf>,
+) -> Result<()> {
+    for rel in rels {
+        let Some(recorded) = manifest.entries.get(rel.to_string_lossy().as_ref()) else {
+            continue;
+        };
+        let abs = home_dir.join(rel);
+        match fs::symlink_metadata(&abs) {
+            Ok(meta) if meta.file_type().is_file() => recorded.apply(&abs)?,
+            _ => continue,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn permission_bits(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn permission_bits(meta: &fs::Metadata) -> u32 {
+    if meta.permissions().readonly() {
+        0o444
+    } else {
+        0o644
+    }
+}
+
+#[cfg(unix)]
+fn set_permission_bits(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_permission_bits(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+/// Namespaces worth carrying between machines: user attributes and ACLs.
+/// Security labels (`security.*`) are host specific and deliberately skipped.
+#[cfg(target_os = "linux")]
+fn is_portable_xattr(name: &str) -> bool {
+    name.starts_with("user.") || name == "system.posix_acl_access"
+}
+
+#[cfg(target_os = "linux")]
+fn read_xattrs(path: &Path) -> Result<BTreeMap<String, String>> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| HometreeError::InvalidPath(path.to_path_buf()))?;
+    let mut out = BTreeMap::new();
+
+    // SAFETY: a null buffer with size 0 asks for the required length only.
+    let len = unsafe { libc::llistxattr(c_path.as_ptr(), std::ptr::null_mut(), 0) };
+    if len < 0 {
+        let err = std::io::Error::last_os_error();
+        return match err.raw_os_error() {
+            Some(libc::ENOTSUP) => Ok(out),
+            _ => Err(err.into()),
+        };
+    }
+    let mut names = vec![0u8; len as usize];
+    // SAFETY: `names` is writable for `names.len()` bytes.
+    let len = unsafe {
+        libc::llistxattr(
+            c_path.as_ptr(),
+            names.as_mut_ptr() as *mut libc::c_char,
+            names.len(),
+        )
+    };
+    if len < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    names.truncate(len as usize);
+
+    for raw in names.split(|b| *b == 0).filter(|n| !n.is_empty()) {
+        let name = String::from_utf8_lossy(raw).to_string();
+        if !is_portable_xattr(&name) {
+            continue;
+        }
+        let c_name = CString::new(raw).map_err(|_| HometreeError::InvalidPath(path.into()))?;
+        // SAFETY: size query as above.
+        let size =
+            unsafe { libc::lgetxattr(c_path.as_ptr(), c_name.as_ptr(), std::ptr::null_mut(), 0) };
+        if size < 0 {
+            continue;
+        }
+        let mut value = vec![0u8; size as usize];
+        // SAFETY: `value` is writable for `value.len()` bytes.
+        let size = unsafe {
+            libc::lgetxattr(
+                c_path.as_ptr(),
+                c_name.as_ptr(),
+                value.as_mut_ptr() as *mut libc::c_void,
+                value.len(),
+            )
+        };
+        if size < 0 {
+            continue;
+        }
+        value.truncate(size as usize);
+        out.insert(name, encode_hex(&value));
+    }
+    Ok(out)
+}
+
+#[cfg(target_os = "linux")]
+fn write_xattr(path: &Path, name: &str, value: &[u8]) -> Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| HometreeError::InvalidPath(path.to_path_buf()))?;
+    let c_name = CString::new(name)
+        .map_err(|_| HometreeError::Config(format!("invalid xattr name: {name}")))?;
+    // SAFETY: pointers are valid for the given lengths for the duration of the call.
+    let rc = unsafe {
+        libc::lsetxattr(
+            c_path.as_ptr(),
+            c_name.as_ptr(),
+            value.as_ptr() as *const libc::c_void,
+            value.len(),
+            0,
+        )
+    };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_xattrs(_path: &Path) -> Result<BTreeMap<String, String>> {
+    Ok(BTreeMap::new())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn write_xattr(_path: &Path, _name: &str, _value: &[u8]) -> Result<()> {
+    Ok(())
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn hex_round_trip() {
+        let bytes = [0u8, 1, 0xab, 0xff];
+        assert_eq!(decode_hex(&encode_hex(&bytes)).unwrap(), bytes);
+        assert!(decode_hex("abc").is_none());
+        assert!(decode_hex("zz").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn capture_and_apply_mode_and_mtime() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().expect("tempdir");
+        let path = temp.path().join("config");
+        fs::write(&path, "x").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        filetime::set_file_mtime(&path, filetime::FileTime::from_unix_time(1_000_000, 0)).unwrap();
+        let options = MetadataConfig {
+            enabled: true,
+            mtime: true,
+            ..MetadataConfig::default()
+        };
+        let recorded = PathMetadata::capture(&path, &options).expect("capture");
+        assert_eq!(recorded.mode, "0640");
+        assert_eq!(recorded.mtime, Some(1_000_000));
+        assert!(recorded.matches(&path).unwrap());
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        filetime::set_file_mtime(&path, filetime::FileTime::from_unix_time(2_000_000, 0)).unwrap();
+        assert!(!recorded.matches(&path).unwrap());
+
+        recorded.apply(&path).expect("apply");
+        assert!(recorded.matches(&path).unwrap());
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o640);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn user_xattrs_are_captured_and_restored() {
+        let temp = TempDir::new().expect("tempdir");
+        let path = temp.path().join("tagged");
+        fs::write(&path, "x").unwrap();
+        if write_xattr(&path, "user.hometree.test", b"v1").is_err() {
+            // Filesystem without user xattr support.
+            return;
+        }
+        let options = MetadataConfig {
+            enabled: true,
+            xattrs: true,
+            ..MetadataConfig::default()
+        };
+        let recorded = PathMetadata::capture(&path, &options).expect("capture");
+        assert_eq!(
+            recorded
+                .xattrs
+                .get("user.hometree.test")
+                .map(String::as_str),
+            Some("7631")
+        );
+
+        write_xattr(&path, "user.hometree.test", b"v2").unwrap();
+        assert!(!recorded.matches(&path).unwrap());
+        recorded.apply(&path).expect("apply");
+        assert!(recorded.matches(&path).unwrap());
+    }
+
+    #[test]
+    fn invalid_mode_is_rejected() {
+        let recorded = PathMetadata {
+            mode: "99999".to_string(),
+            mtime: None,
+            xattrs: BTreeMap::new(),
+        };
+        assert!(recorded.mode_bits().is_err());
+    }
+}
@@ -0,0 +1,333 @@
+//! Journal of mutating CLI operations (`track`, `untrack`, `secret add`) so
+//! that `hometree undo` can put things back.
+//!
+//! Each operation records the files it rewrites (their previous contents and a
+//! hash of what it wrote) and the index entries under the paths it touched.
+//! Secret plaintext is never recorded: only `config.toml`, the excludes file,
+//! the secret state and sidecars are.
+
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{HometreeError, Result};
+use crate::git::{GitCliBackend, IndexEntry};
+use crate::secret_state::sha256_hex;
+use crate::Paths;
+
+/// Older operations are dropped once the journal holds this many.
+pub const MAX_OPERATIONS: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRecord {
+    pub path: PathBuf,
+    /// Contents before the operation; `None` when the file did not exist.
+    pub before: Option<Vec<u8>>,
+    /// Hash of the contents the operation left; `None` when it left no file.
+    pub after_sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operation {
+    pub timestamp: u64,
+    /// The command line, e.g. `track .config/app`.
+    pub command: String,
+    pub files: Vec<FileRecord>,
+    /// Index paths the operation touched, relative to the work tree.
+    pub pathspecs: Vec<PathBuf>,
+    /// Index entries under `pathspecs` before the operation.
+    pub index_before: Vec<IndexEntry>,
+    /// Index entries under `pathspecs` the operation left; undo refuses if
+    /// they changed since, as it would discard whatever was staged later.
+    #[serde(default)]
+    pub index_after: Option<Vec<IndexEntry>>,
+    /// History was rewritten to purge plaintext; that part cannot be undone.
+    #[serde(default)]
+    pub history_purged: bool,
+}
+
+/// Captures the state an operation is about to change; [`Self::finish`]
+/// appends the operation to the journal once the change is done.
+pub struct OperationRecorder {
+    operation: Operation,
+    git: GitCliBackend,
+    git_dir: PathBuf,
+    work_tree: PathBuf,
+}
+
+impl OperationRecorder {
+    pub fn begin(
+        git: &GitCliBackend,
+        git_dir: &Path,
+        work_tree: &Path,
+        command: impl Into<String>,
+        files: &[PathBuf],
+        pathspecs: Vec<PathBuf>,
+    ) -> Result<Self> {
+        let files = files
+            .iter()
+            .map(|path| {
+                Ok(FileRecord {
+                    path: path.clone(),
+                    before: read_optional(path)?,
+                    after_sha256: None,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let index_before = git.index_entries(git_dir, work_tree, &pathspecs)?;
+        Ok(Self {
+            operation: Operation {
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                command: command.into(),
+                files,
+                pathspecs,
+                index_before,
+                index_after: None,
+                history_purged: false,
+            },
+            git: git.clone(),
+            git_dir: git_dir.to_path_buf(),
+            work_tree: work_tree.to_path_buf(),
+        })
+    }
+
+    pub fn set_history_purged(&mut self) {
+        self.operation.history_purged = true;
+    }
+
+    pub fn finish(mut self, paths: &Paths) -> Result<()> {
+        for file in &mut self.operation.files {
+            file.after_sha256 = read_optional(&file.path)?.map(|data| sha256_hex(&data));
+        }
+        self.operation.index_after = Some(self.git.index_entries(
+            &self.git_dir,
+            &self.work_tree,
+            &self.operation.pathspecs,
+        )?);
+        append_operation(paths, &self.operation)
+    }
+}
+
+pub fn oplog_path(paths: &Paths) -> PathBuf {
+    paths.state_dir().join("operations.jsonl")
+}
+
+pub fn read_operations(paths: &Paths) -> Result<Vec<Operation>> {
+    let path = oplog_path(paths);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let reader = BufReader::new(OpenOptions::new().read(true).open(path)?);
+    let mut operations = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        operations.push(serde_json::from_str(&line)?);
+    }
+    Ok(operations)
+}
+
+fn write_operations(paths: &Paths, operations: &[Operation]) -> Result<()> {
+    let path = oplog_path(paths);
+    fs::create_dir_all(paths.state_dir())?;
+    let mut out = String::new();
+    for operation in operations {
+        out.push_str(&serde_json::to_string(operation)?);
+        out.push('\n');
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    fs::write(&tmp, out)?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+fn append_operation(paths: &Paths, operation: &Operation) -> Result<()> {
+    let mut operations = read_operations(paths)?;
+    if operations.len() >= MAX_OPERATIONS {
+        operations.drain(..=operations.len() - MAX_OPERATIONS);
+        operations.push(operation.clone());
+        return write_operations(paths, &operations);
+    }
+    fs::create_dir_all(paths.state_dir())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(oplog_path(paths))?;
+    writeln!(file, "{}", serde_json::to_string(operation)?)?;
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct UndoOutcome {
+    pub operation: Operation,
+    /// Index entries that could not be restored because their object is gone,
+    /// typically plaintext purged from history.
+    pub lost: Vec<IndexEntry>,
+}
+
+/// Revert the most recent operation and drop it from the journal.
+///
+/// Refuses when a recorded file or the index under the operation's paths
+/// changed since the operation, unless `force`.
+pub fn undo_last(
+    paths: &Paths,
+    git: &GitCliBackend,
+    git_dir: &Path,
+    work_tree: &Path,
+    force: bool,
+) -> Result<Option<UndoOutcome>> {
+    let mut operations = read_operations(paths)?;
+    let Some(operation) = operations.pop() else {
+        return Ok(None);
+    };
+    if !force {
+        for file in &operation.files {
+            let current = read_optional(&file.path)?.map(|data| sha256_hex(&data));
+            if current != file.after_sha256 {
+                return Err(HometreeError::Undo(format!(
+                    "{} changed after `{}`; use --force to overwrite it",
+                    file.path.display(),
+                    operation.command
+                )));
+            }
+        }
+        if let Some(after) = &operation.index_after {
+            let current = git.index_entries(git_dir, work_tree, &operation.pathspecs)?;
+            if &current != after {
+                return Err(HometreeError::Undo(format!(
+                    "changes under {} were staged after `{}`; use --force to discard them",
+                    describe_paths(&operation.pathspecs),
+                    operation.command
+                )));
+            }
+        }
+    }
+
+    for file in &operation.files {
+        match &file.before {
+            Some(data) => {
+                if let Some(parent) = file.path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&file.path, data)?;
+            }
+            None if file.path.exists() => fs::remove_file(&file.path)?,
+            None => {}
+        }
+    }
+    let lost = git.restore_index(
+        git_dir,
+        work_tree,
+        &operation.pathspecs,
+        &operation.index_before,
+    )?;
+    write_operations(paths, &operations)?;
+    Ok(Some(UndoOutcome { operation, lost }))
+}
+
+fn describe_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn read_optional(path: &Path) -> Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{AddMode, GitBackend};
+    use tempfile::TempDir;
+
+    #[test]
+    fn undo_restores_files_and_index() {
+        let temp = TempDir::new().unwrap();
+        let home = temp.path().join("home");
+        let xdg = temp.path().join("xdg");
+        fs::create_dir_all(&home).unwrap();
+        let paths = Paths::new_with_overrides(Some(&home), Some(&xdg)).unwrap();
+        let git_dir = temp.path().join("repo.git");
+        let git = GitCliBackend::new();
+        git.init_repo(&git_dir).unwrap();
+
+        let config = temp.path().join("config.toml");
+        fs::write(&config, "before").unwrap();
+        fs::write(home.join("app.toml"), "v1").unwrap();
+        let rel = vec![PathBuf::from("app.toml")];
+
+        let recorder = OperationRecorder::begin(
+            &git,
+            &git_dir,
+            &home,
+            "track app.toml",
+            std::slice::from_ref(&config),
+            rel.clone(),
+        )
+        .unwrap();
+        fs::write(&config, "after").unwrap();
+        git.add(&git_dir, &home, &rel, AddMode::Paths).unwrap();
+        recorder.finish(&paths).unwrap();
+        assert_eq!(git.index_entries(&git_dir, &home, &rel).unwrap().len(), 1);
+
+        fs::write(&config, "edited by hand").unwrap();
+        assert!(undo_last(&paths, &git, &git_dir, &home, false).is_err());
+        fs::write(&config, "after").unwrap();
+
+        // A later `git add` under the same path must not be discarded silently.
+        fs::write(home.join("app.toml"), "v2").unwrap();
+        git.add(&git_dir, &home, &rel, AddMode::Paths).unwrap();
+        let err = undo_last(&paths, &git, &git_dir, &home, false).unwrap_err();
+        assert!(err.to_string().contains("staged after"), "{err}");
+        assert_eq!(read_operations(&paths).unwrap().len(), 1);
+
+        let outcome = undo_last(&paths, &git, &git_dir, &home, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.operation.command, "track app.toml");
+        assert!(outcome.lost.is_empty());
+        assert_eq!(fs::read_to_string(&config).unwrap(), "before");
+        assert!(git.index_entries(&git_dir, &home, &rel).unwrap().is_empty());
+        assert!(read_operations(&paths).unwrap().is_empty());
+        assert!(undo_last(&paths, &git, &git_dir, &home, false)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn journal_keeps_the_newest_operations() {
+        let temp = TempDir::new().unwrap();
+        let paths =
+            Paths::new_with_overrides(Some(temp.path()), Some(&temp.path().join("xdg"))).unwrap();
+        for i in 0..MAX_OPERATIONS + 3 {
+            let operation = Operation {
+                timestamp: i as u64,
+                command: format!("track {i}"),
+                files: Vec::new(),
+                pathspecs: Vec::new(),
+                index_before: Vec::new(),
+                index_after: None,
+                history_purged: false,
+            };
+            append_operation(&paths, &operation).unwrap();
+        }
+        let operations = read_operations(&paths).unwrap();
+        assert_eq!(operations.len(), MAX_OPERATIONS);
+        assert_eq!(operations[0].command, "track 3");
+    }
+}
@@ -0,0 +1,122 @@
+//! Stable, serializable results for `--format json`.
+//!
+//! Every command prints one [`Output`] envelope. Commands whose result is already
+//! a core type reuse it: `plan deploy` a [`crate::DeployPlan`], `deploy` and
+//! `rollback` a [`crate::GenerationEntry`], `secret audit` a
+//! [`crate::SecretAuditReport`].
+//!
+//! `schema_version` is bumped only when a field is removed, renamed or changes
+//! type; new fields may appear in any release without a bump, so consumers should
+//! ignore keys they do not know.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::git::{LogEntry, RemoteInfo};
+use crate::{RepairReport, VerifyReport};
+
+/// Version of the JSON schema described in `docs/cli.md`.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Output<T> {
+    pub schema_version: u32,
+    pub command: String,
+    pub result: T,
+}
+
+impl<T: Serialize> Output<T> {
+    pub fn new(command: impl Into<String>, result: T) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            command: command.into(),
+            result,
+        }
+    }
+}
+
+/// One managed path with pending changes, as `git status --porcelain` reports it.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusEntry {
+    pub index: String,
+    pub worktree: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub clean: bool,
+    pub entries: Vec<StatusEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogReport {
+    pub commits: Vec<LogEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteListReport {
+    pub remotes: Vec<RemoteInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupEntry {
+    /// Directory name under `state_dir/backups`.
+    pub name: String,
+    /// Unix time parsed from the name; absent for pre-restore backups.
+    pub timestamp: Option<u64>,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupListReport {
+    /// Newest first.
+    pub backups: Vec<BackupEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretStatusEntry {
+    pub path: String,
+    pub status: String,
+    pub last_encrypted_at: Option<String>,
+    pub recipients: Vec<String>,
+    pub commit: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretStatusReport {
+    pub enabled: bool,
+    pub secrets: Vec<SecretStatusEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyResult {
+    /// Present only with `--fix` when drift was repaired.
+    pub repair: Option<RepairReport>,
+    pub report: VerifyReport,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_carries_schema_version_and_command() {
+        let output = Output::new(
+            "status",
+            StatusReport {
+                clean: false,
+                entries: vec![StatusEntry {
+                    index: "M".to_string(),
+                    worktree: ".".to_string(),
+                    path: ".config/app.toml".to_string(),
+                }],
+            },
+        );
+        let value = serde_json::to_value(&output).unwrap();
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        assert_eq!(value["command"], "status");
+        assert_eq!(value["result"]["entries"][0]["path"], ".config/app.toml");
+    }
+}
@@ -0,0 +1,216 @@
+use std::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::ptr::NonNull;
+use std::slice;
+
+use zeroize::Zeroize;
+
+const CHUNK_SIZE: usize = 8192;
+
+/// Decrypted (or about-to-be-encrypted) secret bytes.
+///
+/// The buffer is zeroed before it is freed, including when it has to grow, and
+/// on unix its pages are locked in memory and excluded from core dumps on a
+/// best-effort basis. Every buffer is its own page-aligned allocation, so
+/// locking and unlocking never touch pages shared with other data. It
+/// intentionally implements neither `Debug` nor `Clone`.
+pub struct Plaintext {
+    ptr: NonNull<u8>,
+    len: usize,
+    /// Allocated size, a whole number of pages.
+    capacity: usize,
+    locked: bool,
+}
+
+// SAFETY: the buffer is uniquely owned, like a `Vec<u8>`.
+unsafe impl Send for Plaintext {}
+// SAFETY: shared access only reads the buffer.
+unsafe impl Sync for Plaintext {}
+
+impl Plaintext {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let layout = page_layout(capacity);
+        // SAFETY: the layout has a non-zero size.
+        let ptr = unsafe { alloc_zeroed(layout) };
+        let Some(ptr) = NonNull::new(ptr) else {
+            handle_alloc_error(layout);
+        };
+        let locked = protect(ptr.as_ptr(), layout.size());
+        Self {
+            ptr,
+            len: 0,
+            capacity: layout.size(),
+            locked,
+        }
+    }
+
+    /// Read everything from `reader`, reserving `size_hint` bytes up front.
+    pub fn from_reader(mut reader: impl Read, size_hint: usize) -> io::Result<Self> {
+        let mut out = Self::with_capacity(size_hint);
+        let mut chunk = [0u8; CHUNK_SIZE];
+        let result = loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => break Ok(()),
+                Ok(n) => out.extend_from_slice(&chunk[..n]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => break Err(err),
+            }
+        };
+        chunk.zeroize();
+        result.map(|()| out)
+    }
+
+    pub fn read_file(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let size_hint = file.metadata().map(|m| m.len() as usize).unwrap_or(0);
+        Self::from_reader(file, size_hint)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: the first `len` bytes of the allocation are initialized.
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        let needed = self.len + data.len();
+        if needed > self.capacity {
+            // Grow into a fresh buffer so the old one is wiped when dropped.
+            let mut grown = Self::with_capacity(needed.max(self.capacity * 2));
+            grown.extend_from_slice(self.as_bytes());
+            std::mem::swap(self, &mut grown);
+        }
+        // SAFETY: `needed <= capacity`, and `data` cannot overlap a buffer
+        // borrowed mutably here.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr(),
+                self.ptr.as_ptr().add(self.len),
+                data.len(),
+            );
+        }
+        self.len = needed;
+    }
+}
+
+impl AsRef<[u8]> for Plaintext {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl PartialEq for Plaintext {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Drop for Plaintext {
+    fn drop(&mut self) {
+        // SAFETY: the allocation is `capacity` bytes and zero-initialized.
+        let buffer = unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.capacity) };
+        // Zeroes the full capacity, not just the initialized length.
+        buffer.zeroize();
+        if self.locked {
+            unprotect(self.ptr.as_ptr(), self.capacity);
+        }
+        // SAFETY: allocated in `with_capacity` with the same layout.
+        unsafe { dealloc(self.ptr.as_ptr(), page_layout(self.capacity)) };
+    }
+}
+
+fn page_size() -> usize {
+    #[cfg(unix)]
+    {
+        // SAFETY: sysconf has no preconditions.
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if page > 0 {
+            return page as usize;
+        }
+    }
+    4096
+}
+
+/// Whole pages, page-aligned, holding at least `capacity` bytes.
+fn page_layout(capacity: usize) -> Layout {
+    let page = page_size();
+    let size = capacity.max(1).div_ceil(page) * page;
+    Layout::from_size_align(size, page).expect("plaintext buffer size overflows")
+}
+
+#[cfg(unix)]
+fn protect(ptr: *mut u8, len: usize) -> bool {
+    // SAFETY: `ptr..ptr+len` is exactly the pages of one live allocation; both
+    // calls only change paging attributes and failures are tolerated.
+    unsafe {
+        #[cfg(target_os = "linux")]
+        libc::madvise(ptr.cast(), len, libc::MADV_DONTDUMP);
+        libc::mlock(ptr.cast(), len) == 0
+    }
+}
+
+/// Undo `protect` before the pages go back to the allocator.
+#[cfg(unix)]
+fn unprotect(ptr: *mut u8, len: usize) {
+    // SAFETY: same range that was passed to `protect`.
+    unsafe {
+        libc::munlock(ptr.cast(), len);
+        #[cfg(target_os = "linux")]
+        libc::madvise(ptr.cast(), len, libc::MADV_DODUMP);
+    }
+}
+
+#[cfg(not(unix))]
+fn protect(_ptr: *mut u8, _len: usize) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+fn unprotect(_ptr: *mut u8, _len: usize) {}
+
+#[cfg(test)]
+mod tests {
+    use super::{page_size, Plaintext};
+    use std::io::Cursor;
+
+    #[test]
+    fn from_reader_grows_past_hint() {
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        let plaintext = Plaintext::from_reader(Cursor::new(&data), 16).expect("read");
+        assert_eq!(plaintext.as_bytes(), &data[..]);
+        assert_eq!(plaintext.len(), data.len());
+    }
+
+    #[test]
+    fn read_file_round_trips() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let path = temp.path().join("secret");
+        std::fs::write(&path, b"hunter2").expect("write");
+        let plaintext = Plaintext::read_file(&path).expect("read");
+        assert_eq!(plaintext.as_bytes(), b"hunter2");
+        assert!(!plaintext.is_empty());
+    }
+
+    #[test]
+    fn buffers_own_whole_pages() {
+        let mut a = Plaintext::with_capacity(10);
+        let b = Plaintext::with_capacity(10);
+        let page = page_size();
+        for buffer in [&a, &b] {
+            assert_eq!(buffer.as_bytes().as_ptr() as usize % page, 0);
+        }
+        a.extend_from_slice(&vec![7u8; page + 1]);
+        assert_eq!(a.as_bytes().as_ptr() as usize % page, 0);
+        assert_eq!(a.len(), page + 1);
+        assert!(b.is_empty());
+    }
+}
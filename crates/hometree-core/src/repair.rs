@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::deploy::{
+    apply_secrets, apply_target, backup_current, backup_secrets, collect_target_paths,
+    create_backup_dir, generation_entry,
+};
+use crate::error::Result;
+use crate::generations::{append_generation, GenerationEntry};
+use crate::git::{GitBackend, TreeEntry};
+use crate::lock::acquire_lock;
+use crate::metadata::{apply_manifest, read_manifest_at};
+use crate::secrets::{AgeBackend, SecretsManager};
+use crate::{Config, ManagedSet, Paths, VerifyReport};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RepairOptions {
+    pub remove_unexpected: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RepairReport {
+    pub rev: String,
+    pub restored: Vec<String>,
+    pub mode_fixed: Vec<String>,
+    pub metadata_fixed: Vec<String>,
+    pub secrets_restored: Vec<String>,
+    pub removed: Vec<String>,
+    pub backup_dir: PathBuf,
+    pub generation: GenerationEntry,
+}
+
+/// Repair only the drift listed in `report`, leaving everything else untouched.
+///
+/// Files that are missing, modified or of the wrong type are restored from the
+/// verified revision, exec bits and recorded metadata are corrected, drifted secrets are decrypted
+/// again and, with `remove_unexpected`, unexpected files are deleted. Whatever
+/// gets overwritten or removed is backed up first.
+pub fn repair(
+    config: &Config,
+    paths: &Paths,
+    git: &impl GitBackend,
+    report: &VerifyReport,
+    options: RepairOptions,
+) -> Result<RepairReport> {
+    let _lock = acquire_lock(paths)?;
+    let home_dir = paths.home_dir();
+    let managed = ManagedSet::from_config(config, home_dir)?;
+    let secrets = SecretsManager::from_config(&config.secrets);
+    let secrets_ref = if secrets.enabled() {
+        Some(&secrets)
+    } else {
+        None
+    };
+    let target_entries = collect_target_paths(
+        &managed,
+        secrets_ref,
+        git,
+        &config.repo.git_dir,
+        &config.repo.work_tree,
+        &report.rev,
+    )?;
+
+    let restore: BTreeMap<PathBuf, TreeEntry> = report
+        .missing
+        .iter()
+        .chain(&report.modified)
+        .chain(&report.type_mismatch)
+        .filter_map(|rel| {
+            let rel = PathBuf::from(rel);
+            target_entries.get(&rel).map(|entry| (rel, entry.clone()))
+        })
+        .collect();
+    let mode_fix: Vec<(PathBuf, &TreeEntry)> = report
+        .mode_mismatch
+        .iter()
+        .map(PathBuf::from)
+        .filter(|rel| !restore.contains_key(rel))
+        .filter_map(|rel| target_entries.get(&rel).map(|entry| (rel, entry)))
+        .collect();
+    let metadata_fix: Vec<PathBuf> = report
+        .metadata_mismatch
+        .iter()
+        .map(PathBuf::from)
+        .filter(|rel| target_entries.contains_key(rel) && !restore.contains_key(rel))
+        .collect();
+    let remove: BTreeSet<PathBuf> = if options.remove_unexpected {
+        report.unexpected.iter().map(PathBuf::from).collect()
+    } else {
+        BTreeSet::new()
+    };
+
+    // Only secrets whose sidecar exists at the verified revision can be restored.
+    let drifted_secrets: BTreeSet<&String> = report
+        .secret_mismatch
+        .iter()
+        .chain(&report.secret_missing_plaintext)
+        .collect();
+    let mut secrets_config = config.secrets.clone();
+    secrets_config.rules.retain(|rule| {
+        drifted_secrets.contains(&rule.path)
+            && target_entries.contains_key(&secrets.ciphertext_path(rule))
+    });
+    let drifted = SecretsManager::from_config(&secrets_config);
+    let secrets_backend = if drifted.enabled() && !drifted.rules().is_empty() {
+        Some(AgeBackend::from_config(
+            &config.secrets,
+            &config.repo.work_tree,
+        )?)
+    } else {
+        None
+    };
+
+    let overwritten: BTreeSet<PathBuf> = restore
+        .keys()
+        .chain(mode_fix.iter().map(|(rel, _)| rel))
+        .chain(&metadata_fix)
+        .chain(&remove)
+        .filter(|rel| fs::symlink_metadata(home_dir.join(rel)).is_ok())
+        .cloned()
+        .collect();
+    let backup_dir = create_backup_dir(paths)?;
+    backup_current(&backup_dir, home_dir, &overwritten)?;
+    backup_secrets(&backup_dir, home_dir, &drifted, secrets_backend.as_ref())?;
+
+    apply_target(
+        home_dir,
+        git,
+        &config.repo.git_dir,
+        &config.repo.work_tree,
+        &report.rev,
+        &restore,
+    )?;
+
+    for (rel, entry) in &mode_fix {
+        set_exec_bit(&home_dir.join(rel), entry.mode == "100755")?;
+    }
+
+    if let Some(manifest) = read_manifest_at(config, git, &report.rev)? {
+        apply_manifest(
+            home_dir,
+            &manifest,
+            restore
+                .keys()
+                .chain(mode_fix.iter().map(|(rel, _)| rel))
+                .chain(&metadata_fix),
+        )?;
+    }
+
+    if secrets_backend.is_some() {
+        apply_secrets(
+            home_dir,
+            &drifted,
+            secrets_backend.as_ref(),
+            git,
+            &config.repo.git_dir,
+            &config.repo.work_tree,
+            &report.rev,
+        )?;
+    }
+
+    let mut removed = Vec::new();
+    for rel in &remove {
+        if fs::remove_file(home_dir.join(rel)).is_ok() {
+            removed.push(rel.to_string_lossy().to_string());
+        }
+    }
+
+    let generation = generation_entry(report.rev.clone(), Some("verify --fix".to_string()));
+    append_generation(paths.state_dir(), &generation)?;
+
+    Ok(RepairReport {
+        rev: report.rev.clone(),
+        restored: to_strings(restore.keys()),
+        mode_fixed: to_strings(mode_fix.iter().map(|(rel, _)| rel)),
+        metadata_fixed: to_strings(metadata_fix.iter()),
+        secrets_restored: drifted.rules().iter().map(|r| r.path.clone()).collect(),
+        removed,
+        backup_dir,
+        generation,
+    })
+}
+
+fn to_strings<'a>(paths: impl Iterator<Item = &'a PathBuf>) -> Vec<String> {
+    paths.map(|p| p.to_string_lossy().to_string()).collect()
+}
+
+#[cfg(unix)]
+fn set_exec_bit(path: &Path, exec: bool) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut perms = fs::metadata(path)?.permissions();
+    let mode = perms.mode();
+    perms.set_mode(if exec { mode | 0o111 } else { mode & !0o111 });
+    fs::set_permissions(path, perms)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_exec_bit(_path: &Path, _exec: bool) -> Result<()> {
+    Ok(())
+}
@@ -0,0 +1,417 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::error::{HometreeError, Result};
+use crate::git::GitBackend;
+use crate::secrets::SecretsManager;
+use crate::{Config, Paths};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Per-secret metadata recorded every time hometree writes a ciphertext sidecar.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretRecord {
+    pub ciphertext: String,
+    pub last_encrypted_at: String,
+    pub last_encrypted_epoch: u64,
+    pub recipients: Vec<String>,
+    pub ciphertext_sha256: String,
+    #[serde(default)]
+    pub commit: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecretState {
+    #[serde(default)]
+    pub secrets: BTreeMap<String, SecretRecord>,
+}
+
+impl SecretState {
+    pub fn record_encryption(
+        &mut self,
+        plaintext_rel: &Path,
+        ciphertext_rel: &Path,
+        ciphertext: &[u8],
+        recipients: &[String],
+    ) -> Result<()> {
+        let now = SystemTime::now();
+        let last_encrypted_at = OffsetDateTime::from(now)
+            .format(&Rfc3339)
+            .map_err(|err| HometreeError::Config(format!("invalid timestamp: {err}")))?;
+        let record = SecretRecord {
+            ciphertext: ciphertext_rel.to_string_lossy().to_string(),
+            last_encrypted_at,
+            last_encrypted_epoch: now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            recipients: sorted_fingerprints(recipients),
+            ciphertext_sha256: sha256_hex(ciphertext),
+            commit: None,
+        };
+        self.secrets
+            .insert(plaintext_rel.to_string_lossy().to_string(), record);
+        Ok(())
+    }
+
+    /// Whether a secret was last encrypted to a different recipient set than
+    /// `current_recipients`, or was never recorded at all.
+    pub fn needs_rekey(&self, plaintext_rel: &Path, current_recipients: &[String]) -> bool {
+        match self.secrets.get(plaintext_rel.to_string_lossy().as_ref()) {
+            Some(record) => record.recipients != sorted_fingerprints(current_recipients),
+            None => true,
+        }
+    }
+
+    /// Fill in the commit that introduced each recorded ciphertext version.
+    ///
+    /// A record is attributed to the newest commit touching its sidecar only when
+    /// the committed blob matches the recorded hash; otherwise the version is still
+    /// uncommitted and `commit` stays empty. Returns true when any record changed.
+    pub fn resolve_commits(&mut self, config: &Config, git: &impl GitBackend) -> bool {
+        let mut changed = false;
+        for record in self.secrets.values_mut() {
+            if record.commit.is_some() {
+                continue;
+            }
+            let ciphertext_rel = PathBuf::from(&record.ciphertext);
+            let commit = match git.last_commit_for_path(
+                &config.repo.git_dir,
+                &config.repo.work_tree,
+                &ciphertext_rel,
+            ) {
+                Ok(Some(commit)) => commit,
+                _ => continue,
+            };
+            let blob = match git.show_blob(
+                &config.repo.git_dir,
+                &config.repo.work_tree,
+                &commit,
+                &ciphertext_rel,
+            ) {
+                Ok(blob) => blob,
+                Err(_) => continue,
+            };
+            if sha256_hex(&blob) == record.ciphertext_sha256 {
+                record.commit = Some(commit);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+pub fn secret_state_path(paths: &Paths) -> PathBuf {
+    paths.state_dir().join("secrets.json")
+}
+
+pub fn read_secret_state(paths: &Paths) -> Result<SecretState> {
+    let path = secret_state_path(paths);
+    if !path.exists() {
+        return Ok(SecretState::default());
+    }
+    let contents = fs::read_to_string(path)?;
+    let state: SecretState = serde_json::from_str(&contents)?;
+    Ok(state)
+}
+
+pub fn write_secret_state(paths: &Paths, state: &SecretState) -> Result<()> {
+    fs::create_dir_all(paths.state_dir())?;
+    let path = secret_state_path(paths);
+    let contents = serde_json::to_string_pretty(state)?;
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Short, stable identifier for a recipient key that is safe to print and store.
+pub fn recipient_fingerprint(recipient: &str) -> String {
+    let digest = sha256_hex(recipient.trim().as_bytes());
+    format!("sha256:{}", &digest[..16])
+}
+
+pub fn sorted_fingerprints(recipients: &[String]) -> Vec<String> {
+    let set: BTreeSet<String> = recipients
+        .iter()
+        .filter(|r| !r.trim().is_empty())
+        .map(|r| recipient_fingerprint(r))
+        .collect();
+    set.into_iter().collect()
+}
+
+/// Count the X25519 recipient stanzas in a binary age header.
+///
+/// age does not reveal which keys a file was encrypted to, but the number of
+/// stanzas still catches sidecars written for a different recipient set. Other
+/// stanza types (including age's random "grease" stanzas) are not counted.
+pub fn ciphertext_stanza_count(ciphertext: &[u8]) -> Option<usize> {
+    let mut lines = ciphertext.split(|b| *b == b'\n');
+    if lines.next()? != b"age-encryption.org/v1" {
+        return None;
+    }
+    let mut count = 0;
+    for line in lines {
+        if line.starts_with(b"---") {
+            return Some(count);
+        }
+        if line.starts_with(b"-> X25519 ") {
+            count += 1;
+        }
+    }
+    None
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    let digest = Sha256::digest(data);
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditFinding {
+    /// No encryption has been recorded for this secret.
+    Unrecorded,
+    /// The sidecar is older than the configured maximum age.
+    Stale,
+    /// The sidecar was encrypted to a different recipient set than configured.
+    RecipientMismatch,
+    /// The sidecar on disk differs from the last recorded encryption.
+    CiphertextChanged,
+    /// The sidecar does not exist on disk.
+    MissingCiphertext,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretAuditEntry {
+    pub path: String,
+    pub ciphertext: String,
+    pub last_encrypted_at: Option<String>,
+    pub age_days: Option<u64>,
+    pub recipients: Vec<String>,
+    pub commit: Option<String>,
+    pub findings: Vec<AuditFinding>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretAuditReport {
+    pub stale_after_days: u64,
+    pub current_recipients: Vec<String>,
+    pub entries: Vec<SecretAuditEntry>,
+}
+
+impl SecretAuditReport {
+    pub fn is_clean(&self) -> bool {
+        self.entries.iter().all(|entry| entry.findings.is_empty())
+    }
+}
+
+/// Compare recorded secret metadata with the current config and sidecars on disk.
+pub fn audit_secrets(
+    config: &Config,
+    paths: &Paths,
+    state: &SecretState,
+    current_recipients: &[String],
+    stale_after_days: u64,
+    now: SystemTime,
+) -> Result<SecretAuditReport> {
+    let secrets = SecretsManager::from_config(&config.secrets);
+    let current = sorted_fingerprints(current_recipients);
+    let now_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let mut entries = Vec::new();
+
+    for rule in secrets.rules() {
+        let plaintext_rel = secrets.plaintext_path(rule);
+        let ciphertext_rel = secrets.ciphertext_path(rule);
+        let key = plaintext_rel.to_string_lossy().to_string();
+        let record = state.secrets.get(&key);
+        let mut findings = Vec::new();
+
+        let on_disk = fs::read(paths.home_dir().join(&ciphertext_rel)).ok();
+        match on_disk.as_deref() {
+            None => findings.push(AuditFinding::MissingCiphertext),
+            Some(bytes) => {
+                if let Some(count) = ciphertext_stanza_count(bytes) {
+                    if count != current.len() {
+                        findings.push(AuditFinding::RecipientMismatch);
+                    }
+                }
+                if let Some(record) = record {
+                    if sha256_hex(bytes) != record.ciphertext_sha256 {
+                        findings.push(AuditFinding::CiphertextChanged);
+                    }
+                }
+            }
+        }
+
+        let age_days =
+            record.map(|r| now_epoch.saturating_sub(r.last_encrypted_epoch) / SECONDS_PER_DAY);
+        match record {
+            None => findings.push(AuditFinding::Unrecorded),
+            Some(record) => {
+                if age_days.unwrap_or(0) >= stale_after_days {
+                    findings.push(AuditFinding::Stale);
+                }
+                if record.recipients != current
+                    && !findings.contains(&AuditFinding::RecipientMismatch)
+                {
+                    findings.push(AuditFinding::RecipientMismatch);
+                }
+            }
+        }
+
+        entries.push(SecretAuditEntry {
+            path: key,
+            ciphertext: ciphertext_rel.to_string_lossy().to_string(),
+            last_encrypted_at: record.map(|r| r.last_encrypted_at.clone()),
+            age_days,
+            recipients: record.map(|r| r.recipients.clone()).unwrap_or_default(),
+            commit: record.and_then(|r| r.commit.clone()),
+            findings,
+        });
+    }
+
+    Ok(SecretAuditReport {
+        stale_after_days,
+        current_recipients: current,
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SecretRule;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, Paths, Config) {
+        let temp = TempDir::new().expect("tempdir");
+        let home = temp.path().join("home");
+        let xdg = temp.path().join("xdg");
+        fs::create_dir_all(home.join(".config/app")).unwrap();
+        let paths = Paths::new_with_overrides(Some(&home), Some(&xdg)).expect("paths");
+        let mut config = Config::default_with_paths(&paths);
+        config.secrets.enabled = true;
+        config.secrets.rules.push(SecretRule {
+            path: ".config/app/secret.txt".to_string(),
+            ciphertext: None,
+            mode: None,
+        });
+        (temp, paths, config)
+    }
+
+    fn fake_ciphertext(stanzas: usize) -> Vec<u8> {
+        let mut out = b"age-encryption.org/v1\n".to_vec();
+        for _ in 0..stanzas {
+            out.extend_from_slice(b"-> X25519 abc\nbody\n");
+        }
+        out.extend_from_slice(b"--- mac\npayload");
+        out
+    }
+
+    #[test]
+    fn secret_state_round_trip() {
+        let (_temp, paths, _config) = setup();
+        let mut state = SecretState::default();
+        state
+            .record_encryption(
+                Path::new(".config/app/secret.txt"),
+                Path::new(".config/app/secret.txt.age"),
+                b"cipher",
+                &["age1b".to_string(), "age1a".to_string()],
+            )
+            .expect("record");
+        write_secret_state(&paths, &state).expect("write");
+        let loaded = read_secret_state(&paths).expect("read");
+        let record = loaded
+            .secrets
+            .get(".config/app/secret.txt")
+            .expect("record");
+        assert_eq!(record.ciphertext, ".config/app/secret.txt.age");
+        assert_eq!(record.recipients.len(), 2);
+        assert!(record.recipients.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(record.ciphertext_sha256, sha256_hex(b"cipher"));
+    }
+
+    #[test]
+    fn needs_rekey_tracks_recipient_set() {
+        let mut state = SecretState::default();
+        let plaintext = Path::new(".config/app/secret.txt");
+        let current = vec!["age1a".to_string(), "age1b".to_string()];
+        assert!(state.needs_rekey(plaintext, &current));
+        state
+            .record_encryption(
+                plaintext,
+                Path::new(".config/app/secret.txt.age"),
+                b"cipher",
+                &current,
+            )
+            .expect("record");
+        let reordered = vec!["age1b".to_string(), "age1a".to_string()];
+        assert!(!state.needs_rekey(plaintext, &reordered));
+        let grown = vec![
+            "age1a".to_string(),
+            "age1b".to_string(),
+            "age1c".to_string(),
+        ];
+        assert!(state.needs_rekey(plaintext, &grown));
+    }
+
+    #[test]
+    fn stanza_count_parses_binary_header() {
+        assert_eq!(ciphertext_stanza_count(&fake_ciphertext(2)), Some(2));
+        assert_eq!(ciphertext_stanza_count(b"not age"), None);
+    }
+
+    #[test]
+    fn audit_flags_stale_and_recipient_mismatch() {
+        let (_temp, paths, config) = setup();
+        let ciphertext = fake_ciphertext(1);
+        fs::write(
+            paths.home_dir().join(".config/app/secret.txt.age"),
+            &ciphertext,
+        )
+        .unwrap();
+        let mut state = SecretState::default();
+        state
+            .record_encryption(
+                Path::new(".config/app/secret.txt"),
+                Path::new(".config/app/secret.txt.age"),
+                &ciphertext,
+                &["age1old".to_string()],
+            )
+            .expect("record");
+
+        let now = SystemTime::now();
+        let report = audit_secrets(&config, &paths, &state, &["age1old".to_string()], 30, now)
+            .expect("audit");
+        assert!(report.is_clean());
+
+        let later = now + Duration::from_secs(31 * SECONDS_PER_DAY);
+        let report = audit_secrets(&config, &paths, &state, &["age1new".to_string()], 30, later)
+            .expect("audit");
+        let findings = &report.entries[0].findings;
+        assert!(findings.contains(&AuditFinding::Stale));
+        assert!(findings.contains(&AuditFinding::RecipientMismatch));
+    }
+
+    #[test]
+    fn audit_flags_unrecorded_and_missing() {
+        let (_temp, paths, config) = setup();
+        let report = audit_secrets(
+            &config,
+            &paths,
+            &SecretState::default(),
+            &["age1".to_string()],
+            30,
+            SystemTime::now(),
+        )
+        .expect("audit");
+        let findings = &report.entries[0].findings;
+        assert!(findings.contains(&AuditFinding::Unrecorded));
+        assert!(findings.contains(&AuditFinding::MissingCiphertext));
+    }
+}
@@ -1,15 +1,16 @@
 use std::fs;
-use std::io::{Read, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use age::{Decryptor, Encryptor};
 
 use crate::config::{BackupPolicy, SecretRule, SecretsConfig};
 use crate::error::{HometreeError, Result};
+use crate::plaintext::Plaintext;
 
 pub trait SecretsBackend {
     fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>>;
-    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>>;
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Plaintext>;
 }
 
 pub struct AgeBackend {
@@ -18,16 +19,12 @@ pub struct AgeBackend {
 }
 
 impl AgeBackend {
-    pub fn from_config(config: &SecretsConfig) -> Result<Self> {
+    pub fn from_config(config: &SecretsConfig, work_tree: &Path) -> Result<Self> {
         let mut recipients = Vec::new();
-        for recipient in &config.recipients {
-            let trimmed = recipient.trim();
-            if trimmed.is_empty() {
-                continue;
-            }
-            let parsed = trimmed
-                .parse::<age::x25519::Recipient>()
-                .map_err(|_| HometreeError::Config(format!("invalid age recipient: {trimmed}")))?;
+        for recipient in effective_recipients(config, work_tree)? {
+            let parsed = recipient.parse::<age::x25519::Recipient>().map_err(|_| {
+                HometreeError::Config(format!("invalid age recipient: {recipient}"))
+            })?;
             recipients.push(parsed);
         }
 
@@ -52,6 +49,11 @@ impl AgeBackend {
         })
     }
 
+    /// Recipient keys this backend encrypts to, in their canonical string form.
+    pub fn recipient_keys(&self) -> Vec<String> {
+        self.recipients.iter().map(|r| r.to_string()).collect()
+    }
+
     pub fn ensure_recipients(&self) -> Result<()> {
         if self.recipients.is_empty() {
             return Err(HometreeError::Config(
@@ -90,7 +92,7 @@ impl SecretsBackend for AgeBackend {
         Ok(out)
     }
 
-    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Plaintext> {
         self.ensure_identities()?;
         let decryptor = Decryptor::new(ciphertext)
             .map_err(|e| HometreeError::Config(format!("age decrypt failed: {e}")))?;
@@ -102,9 +104,9 @@ impl SecretsBackend for AgeBackend {
         let mut reader = decryptor
             .decrypt(self.identities.iter().map(|i| i as &dyn age::Identity))
             .map_err(|e| HometreeError::Config(format!("age decrypt failed: {e}")))?;
-        let mut out = Vec::new();
-        reader.read_to_end(&mut out)?;
-        Ok(out)
+        // The payload is never larger than the ciphertext, so this reservation
+        // avoids regrowing the buffer.
+        Ok(Plaintext::from_reader(&mut reader, ciphertext.len())?)
     }
 }
 
@@ -164,8 +166,108 @@ impl SecretsManager {
     }
 }
 
+/// Collect recipients from the inline list, `recipients_files` and every file in
+/// `recipients_dir`, in that order and without duplicates.
+pub fn effective_recipients(config: &SecretsConfig, work_tree: &Path) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    for recipient in &config.recipients {
+        push_recipient(&mut keys, recipient);
+    }
+
+    for path in &config.recipients_files {
+        let path = if path.is_absolute() {
+            path.clone()
+        } else {
+            work_tree.join(path)
+        };
+        read_recipients_file(&path, &mut keys)?;
+    }
+
+    if let Some(dir) = &config.recipients_dir {
+        let dir = work_tree.join(dir);
+        if dir.is_dir() {
+            let mut files = Vec::new();
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                if !hidden && entry.file_type()?.is_file() {
+                    files.push(entry.path());
+                }
+            }
+            files.sort();
+            for file in files {
+                read_recipients_file(&file, &mut keys)?;
+            }
+        }
+    }
+
+    Ok(keys)
+}
+
+fn read_recipients_file(path: &Path, keys: &mut Vec<String>) -> Result<()> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        HometreeError::Config(format!("failed to read recipients file {path:?}: {e}"))
+    })?;
+    for line in contents.lines() {
+        let key = line.split('#').next().unwrap_or_default();
+        push_recipient(keys, key);
+    }
+    Ok(())
+}
+
+fn push_recipient(keys: &mut Vec<String>, recipient: &str) {
+    let trimmed = recipient.trim();
+    if trimmed.is_empty() || keys.iter().any(|k| k == trimmed) {
+        return;
+    }
+    keys.push(trimmed.to_string());
+}
+
 pub fn add_suffix(path: &Path, suffix: &str) -> PathBuf {
     let mut s = path.to_string_lossy().to_string();
     s.push_str(suffix);
     PathBuf::from(s)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::effective_recipients;
+    use crate::config::SecretsConfig;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn effective_recipients_merge_files_and_dir() {
+        let temp = tempdir().expect("tempdir");
+        let work_tree = temp.path();
+        fs::write(
+            work_tree.join("recipients.txt"),
+            "# laptop\nage1aaa\n\nage1bbb\n",
+        )
+        .expect("write recipients file");
+        let dir = work_tree.join(".config/hometree/recipients");
+        fs::create_dir_all(&dir).expect("create dir");
+        fs::write(dir.join("desktop"), "age1ccc # trailing\nage1aaa\n").expect("write desktop");
+        fs::write(dir.join(".hidden"), "age1zzz\n").expect("write hidden");
+
+        let config = SecretsConfig {
+            recipients: vec!["age1inline".to_string()],
+            recipients_files: vec!["recipients.txt".into()],
+            recipients_dir: Some(".config/hometree/recipients".to_string()),
+            ..SecretsConfig::default()
+        };
+        let keys = effective_recipients(&config, work_tree).expect("recipients");
+        assert_eq!(keys, vec!["age1inline", "age1aaa", "age1bbb", "age1ccc"]);
+    }
+
+    #[test]
+    fn missing_recipients_dir_is_empty() {
+        let temp = tempdir().expect("tempdir");
+        let config = SecretsConfig {
+            recipients_dir: Some("keys".to_string()),
+            ..SecretsConfig::default()
+        };
+        let keys = effective_recipients(&config, temp.path()).expect("recipients");
+        assert!(keys.is_empty());
+    }
+}
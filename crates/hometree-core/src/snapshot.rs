@@ -0,0 +1,234 @@
+use std::str::FromStr;
+
+use time::{OffsetDateTime, UtcOffset};
+
+use crate::error::{HometreeError, Result};
+
+/// Template used for scheduled snapshots when `auto_message_template` is unset.
+pub const DEFAULT_AUTO_TEMPLATE: &str = "snapshot: {count} file(s) on {host} at {time}";
+
+/// Number of paths spelled out by `{files}` before the rest is summarized.
+const MAX_LISTED_FILES: usize = 10;
+
+/// Values substituted into an auto-snapshot message template.
+#[derive(Debug, Clone)]
+pub struct MessageContext<'a> {
+    pub files: &'a [String],
+    pub host: &'a str,
+    pub time: OffsetDateTime,
+}
+
+/// Expand `{files}`, `{count}`, `{host}` and `{time}` in `template`.
+pub fn render_message(template: &str, ctx: &MessageContext<'_>) -> String {
+    let format =
+        time::format_description::parse("[year]-[month]-[day] [hour]:[minute]:[second]").unwrap();
+    let time = ctx
+        .time
+        .format(&format)
+        .unwrap_or_else(|_| "unknown".to_string());
+    template
+        .replace("{files}", &summarize_files(ctx.files))
+        .replace("{count}", &ctx.files.len().to_string())
+        .replace("{host}", ctx.host)
+        .replace("{time}", &time)
+}
+
+fn summarize_files(files: &[String]) -> String {
+    if files.len() <= MAX_LISTED_FILES {
+        return files.join(", ");
+    }
+    format!(
+        "{} and {} more",
+        files[..MAX_LISTED_FILES].join(", "),
+        files.len() - MAX_LISTED_FILES
+    )
+}
+
+/// A five-field cron expression: minute, hour, day of month, month, day of week.
+///
+/// Fields accept `*`, numbers, ranges (`1-5`), steps (`*/15`, `0-30/10`) and
+/// comma-separated lists. Day of week runs from 0 (Sunday) to 6; 7 is also Sunday.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl FromStr for Schedule {
+    type Err = HometreeError;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(HometreeError::Config(format!(
+                "snapshot.schedule must have 5 fields: {expr}"
+            )));
+        }
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            days_restricted: fields[2] != "*",
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+}
+
+impl Schedule {
+    /// Whether the schedule fires during the minute containing `time`.
+    pub fn matches(&self, time: OffsetDateTime) -> bool {
+        let bit = |mask: u64, value: u8| mask & (1 << value) != 0;
+        let day = bit(self.days, time.day());
+        let weekday = bit(self.weekdays, time.weekday().number_days_from_sunday());
+        // Like cron, a restricted day-of-month and day-of-week match either way.
+        let day_ok = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        };
+        bit(self.minutes, time.minute())
+            && bit(self.hours, time.hour())
+            && bit(self.months, u8::from(time.month()))
+            && day_ok
+    }
+}
+
+fn parse_field(field: &str, min: u8, max: u8) -> Result<u64> {
+    let invalid = || HometreeError::Config(format!("invalid snapshot.schedule field: {field}"));
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u8>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (
+                a.parse::<u8>().map_err(|_| invalid())?,
+                b.parse::<u8>().map_err(|_| invalid())?,
+            )
+        } else {
+            let value = range.parse::<u8>().map_err(|_| invalid())?;
+            // `5/10` means "from 5 to the end, every 10".
+            (value, if part.contains('/') { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+/// Current time in the local timezone, falling back to UTC.
+pub fn local_now() -> OffsetDateTime {
+    let now = OffsetDateTime::now_utc();
+    now.to_offset(local_offset(now.unix_timestamp()).unwrap_or(UtcOffset::UTC))
+}
+
+#[cfg(unix)]
+fn local_offset(timestamp: i64) -> Option<UtcOffset> {
+    let t = timestamp as libc::time_t;
+    // SAFETY: localtime_r only writes into the provided tm struct.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::localtime_r(&t, &mut tm) };
+    if result.is_null() {
+        return None;
+    }
+    UtcOffset::from_whole_seconds(tm.tm_gmtoff as i32).ok()
+}
+
+#[cfg(not(unix))]
+fn local_offset(_timestamp: i64) -> Option<UtcOffset> {
+    None
+}
+
+/// Short host name used in snapshot messages.
+pub fn hostname() -> String {
+    if let Ok(name) = std::env::var("HOSTNAME") {
+        if !name.trim().is_empty() {
+            return name.trim().to_string();
+        }
+    }
+    system_hostname().unwrap_or_else(|| "localhost".to_string())
+}
+
+#[cfg(unix)]
+fn system_hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer length is passed along and gethostname NUL-terminates on success.
+    let rc = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if rc != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    let name = String::from_utf8_lossy(&buf[..len]).trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(not(unix))]
+fn system_hostname() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_message, MessageContext, Schedule};
+    use time::macros::datetime;
+
+    #[test]
+    fn schedule_parses_steps_ranges_and_lists() {
+        let schedule: Schedule = "*/15 9-17 * * 1-5".parse().expect("parse");
+        // 2024-03-04 is a Monday.
+        assert!(schedule.matches(datetime!(2024-03-04 09:30 UTC)));
+        assert!(!schedule.matches(datetime!(2024-03-04 09:31 UTC)));
+        assert!(!schedule.matches(datetime!(2024-03-04 18:00 UTC)));
+        assert!(!schedule.matches(datetime!(2024-03-03 10:00 UTC)));
+
+        let sunday: Schedule = "0 0 * * 7".parse().expect("parse");
+        assert!(sunday.matches(datetime!(2024-03-03 00:00 UTC)));
+
+        assert!("* * *".parse::<Schedule>().is_err());
+        assert!("60 * * * *".parse::<Schedule>().is_err());
+        assert!("*/0 * * * *".parse::<Schedule>().is_err());
+    }
+
+    #[test]
+    fn schedule_matches_either_day_field_when_both_restricted() {
+        let schedule: Schedule = "0 12 1 * 1".parse().expect("parse");
+        assert!(schedule.matches(datetime!(2024-03-01 12:00 UTC)));
+        assert!(schedule.matches(datetime!(2024-03-04 12:00 UTC)));
+        assert!(!schedule.matches(datetime!(2024-03-05 12:00 UTC)));
+    }
+
+    #[test]
+    fn message_template_expands_placeholders() {
+        let files: Vec<String> = (0..12).map(|i| format!("f{i}")).collect();
+        let ctx = MessageContext {
+            files: &files,
+            host: "box",
+            time: datetime!(2024-03-04 09:30:05 UTC),
+        };
+        let message = render_message("{count} on {host} at {time}: {files}", &ctx);
+        assert_eq!(
+            message,
+            "12 on box at 2024-03-04 09:30:05: f0, f1, f2, f3, f4, f5, f6, f7, f8, f9 and 2 more"
+        );
+    }
+}
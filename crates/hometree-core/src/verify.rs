@@ -7,6 +7,8 @@ use serde::Serialize;
 use crate::deploy::{collect_current_paths, collect_target_paths};
 use crate::error::Result;
 use crate::git::GitBackend;
+use crate::metadata::{read_manifest_at, MetadataManifest};
+use crate::plaintext::Plaintext;
 use crate::secrets::{AgeBackend, SecretsBackend, SecretsManager};
 use crate::{Config, ManagedSet, Paths};
 
@@ -33,6 +35,7 @@ pub struct VerifyReport {
     pub modified: Vec<String>,
     pub type_mismatch: Vec<String>,
     pub mode_mismatch: Vec<String>,
+    pub metadata_mismatch: Vec<String>,
     pub unexpected: Vec<String>,
     pub secret_missing_plaintext: Vec<String>,
     pub secret_missing_ciphertext: Vec<String>,
@@ -46,6 +49,7 @@ impl VerifyReport {
             && self.modified.is_empty()
             && self.type_mismatch.is_empty()
             && self.mode_mismatch.is_empty()
+            && self.metadata_mismatch.is_empty()
             && self.unexpected.is_empty()
             && self.secret_missing_plaintext.is_empty()
             && self.secret_missing_ciphertext.is_empty()
@@ -86,6 +90,7 @@ pub fn verify(
         modified: Vec::new(),
         type_mismatch: Vec::new(),
         mode_mismatch: Vec::new(),
+        metadata_mismatch: Vec::new(),
         unexpected: Vec::new(),
         secret_missing_plaintext: Vec::new(),
         secret_missing_ciphertext: Vec::new(),
@@ -107,6 +112,9 @@ pub fn verify(
     )?;
 
     if options.strict {
+        if let Some(manifest) = read_manifest_at(config, git, &rev)? {
+            verify_metadata(&manifest, &target_entries, paths.home_dir(), &mut report)?;
+        }
         let current_paths = collect_current_paths(
             &managed,
             secrets_ref,
@@ -191,6 +199,29 @@ fn verify_expected(
     Ok(())
 }
 
+fn verify_metadata(
+    manifest: &MetadataManifest,
+    target_entries: &BTreeMap<PathBuf, crate::git::TreeEntry>,
+    home_dir: &Path,
+    report: &mut VerifyReport,
+) -> Result<()> {
+    for rel in target_entries.keys() {
+        let rel_str = rel.to_string_lossy().to_string();
+        let Some(recorded) = manifest.entries.get(&rel_str) else {
+            continue;
+        };
+        let abs = home_dir.join(rel);
+        match fs::symlink_metadata(&abs) {
+            Ok(meta) if meta.file_type().is_file() => {}
+            _ => continue,
+        }
+        if !recorded.matches(&abs)? {
+            report.metadata_mismatch.push(rel_str);
+        }
+    }
+    Ok(())
+}
+
 #[cfg(unix)]
 fn exec_bit_mismatch(meta: &fs::Metadata, expected_exec: bool) -> bool {
     use std::os::unix::fs::PermissionsExt;
@@ -222,7 +253,10 @@ fn verify_secrets(
 
     let secrets = SecretsManager::from_config(&config.secrets);
     let backend = if matches!(mode, SecretsVerifyMode::Decrypt) {
-        Some(AgeBackend::from_config(&config.secrets)?)
+        Some(AgeBackend::from_config(
+            &config.secrets,
+            &config.repo.work_tree,
+        )?)
     } else {
         None
     };
@@ -252,7 +286,7 @@ fn verify_secrets(
             continue;
         }
 
-        let plaintext = fs::read(&plaintext_abs)?;
+        let plaintext = Plaintext::read_file(&plaintext_abs)?;
         let ciphertext = git.show_blob(git_dir, work_tree, rev, &ciphertext_rel)?;
         let decrypted = match backend.as_ref().unwrap().decrypt(&ciphertext) {
             Ok(data) => data,
@@ -0,0 +1,221 @@
+#![cfg(unix)]
+
+use hometree_core::ipc::{
+    negotiate, read_message, write_message, Client, DaemonEvent, DaemonStatus, EventRecord,
+    Request, Response, PROTOCOL_VERSION,
+};
+use std::io::{BufRead, BufReader, BufWriter};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::thread;
+use tempfile::TempDir;
+
+/// Status payload exactly as a v1 daemon sent it, before snapshot and push fields existed.
+const V1_STATUS: &str = r#"{"running_since":"2024-01-01T00:00:00Z","config_path":"/c","watch_roots":[".config"],"queue_size":0,"inhibited":false,"inhibit_reason":null,"last_flush_at":null,"last_error":null,"last_error_at":null,"total_flushes":3}"#;
+
+fn socket(temp: &TempDir) -> (PathBuf, UnixListener) {
+    let path = temp.path().join("daemon.sock");
+    let listener = UnixListener::bind(&path).unwrap();
+    (path, listener)
+}
+
+/// Serves like a v1 daemon: one request per connection, no `hello`.
+fn spawn_v1_daemon(listener: UnixListener, connections: usize) -> thread::JoinHandle<Vec<String>> {
+    thread::spawn(move || {
+        let mut seen = Vec::new();
+        for stream in listener.incoming().take(connections) {
+            let stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+            let cmd = value["cmd"].as_str().unwrap().to_string();
+            let response = match cmd.as_str() {
+                "ping" => r#"{"ok":true,"result":null,"error":null}"#.to_string(),
+                "status" => format!(r#"{{"ok":true,"result":{V1_STATUS},"error":null}}"#),
+                other => {
+                    format!(r#"{{"ok":false,"result":null,"error":"unknown command: {other}"}}"#)
+                }
+            };
+            seen.push(cmd);
+            let mut writer = BufWriter::new(stream);
+            std::io::Write::write_all(&mut writer, response.as_bytes()).unwrap();
+            std::io::Write::write_all(&mut writer, b"\n").unwrap();
+        }
+        seen
+    })
+}
+
+/// Serves like the current daemon for a single connection.
+fn spawn_v2_daemon(listener: UnixListener) -> thread::JoinHandle<Vec<Request>> {
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = BufWriter::new(stream);
+        let mut seen = Vec::new();
+        while let Some(request) = read_message::<Request>(&mut reader).unwrap() {
+            let response: Response = match &request {
+                Request::Hello { version } => {
+                    Response::ok(serde_json::to_value(negotiate(*version).unwrap()).unwrap())
+                }
+                Request::Status => Response::ok(serde_json::from_str(V1_STATUS).unwrap()),
+                Request::Subscribe => {
+                    write_message(&mut writer, &Response::ok(serde_json::Value::Null)).unwrap();
+                    let record = EventRecord {
+                        ts: "2024-01-01T00:00:00Z".to_string(),
+                        event: DaemonEvent::Flushed { staged: 1 },
+                    };
+                    write_message(&mut writer, &record).unwrap();
+                    seen.push(request);
+                    break;
+                }
+                _ => Response::ok(serde_json::Value::Null),
+            };
+            seen.push(request);
+            write_message(&mut writer, &response).unwrap();
+        }
+        seen
+    })
+}
+
+#[test]
+fn v1_request_lines_parse_as_current_requests() {
+    // The v1 client always serialized every field, using null for unused ones.
+    let cases = [
+        (
+            r#"{"cmd":"ping","ttl_ms":null,"reason":null}"#,
+            Request::Ping,
+        ),
+        (
+            r#"{"cmd":"status","ttl_ms":null,"reason":null}"#,
+            Request::Status,
+        ),
+        (
+            r#"{"cmd":"pause","ttl_ms":300000,"reason":"deploy"}"#,
+            Request::Pause {
+                ttl_ms: Some(300_000),
+                reason: Some("deploy".to_string()),
+            },
+        ),
+        (
+            r#"{"cmd":"subscribe","ttl_ms":null,"reason":null}"#,
+            Request::Subscribe,
+        ),
+    ];
+    for (line, expected) in cases {
+        assert_eq!(Request::parse(line), Ok(expected), "{line}");
+    }
+}
+
+#[test]
+fn current_requests_serialize_to_v1_compatible_lines() {
+    let line = serde_json::to_string(&Request::Pause {
+        ttl_ms: Some(1000),
+        reason: Some("x".to_string()),
+    })
+    .unwrap();
+    let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(value["cmd"], "pause");
+    assert_eq!(value["ttl_ms"], 1000);
+    assert_eq!(value["reason"], "x");
+}
+
+#[test]
+fn v1_status_payload_still_deserializes() {
+    let status: DaemonStatus = serde_json::from_str(V1_STATUS).unwrap();
+    assert_eq!(status.total_flushes, 3);
+    assert_eq!(status.total_snapshots, 0);
+    assert!(status.push_remote.is_none());
+}
+
+#[test]
+fn events_from_newer_daemons_parse_as_unknown() {
+    let record: EventRecord =
+        serde_json::from_str(r#"{"ts":"t","event":"future_thing","extra":1}"#).unwrap();
+    assert_eq!(record.event, DaemonEvent::Unknown);
+}
+
+#[test]
+fn client_gives_up_on_a_daemon_that_never_answers() {
+    let temp = TempDir::new().unwrap();
+    let (path, listener) = socket(&temp);
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        // Hold the connection open without answering.
+        thread::sleep(std::time::Duration::from_secs(3));
+        drop(stream);
+    });
+
+    let started = std::time::Instant::now();
+    let err = Client::connect(&path).err().expect("timeout");
+    assert!(err.to_string().contains("daemon not responding"), "{err}");
+    assert!(started.elapsed() < std::time::Duration::from_secs(3));
+    server.join().unwrap();
+}
+
+#[test]
+fn client_falls_back_to_v1_daemon() {
+    let temp = TempDir::new().unwrap();
+    let (path, listener) = socket(&temp);
+    // hello, then status and ping each on a fresh connection.
+    let server = spawn_v1_daemon(listener, 3);
+
+    let mut client = Client::connect(&path).unwrap();
+    assert_eq!(client.version(), 1);
+    assert_eq!(client.status().unwrap().total_flushes, 3);
+    client.command(&Request::Ping).unwrap();
+
+    assert_eq!(server.join().unwrap(), vec!["hello", "status", "ping"]);
+}
+
+#[test]
+fn client_negotiates_current_version_and_reuses_connection() {
+    let temp = TempDir::new().unwrap();
+    let (path, listener) = socket(&temp);
+    let server = spawn_v2_daemon(listener);
+
+    let mut client = Client::connect(&path).unwrap();
+    assert_eq!(client.version(), PROTOCOL_VERSION);
+    assert_eq!(client.status().unwrap().total_flushes, 3);
+    client.command(&Request::Flush).unwrap();
+    let mut events = client.subscribe().unwrap();
+    let record = events.next().unwrap().unwrap();
+    assert_eq!(record.event, DaemonEvent::Flushed { staged: 1 });
+    drop(events);
+
+    assert_eq!(
+        server.join().unwrap(),
+        vec![
+            Request::Hello {
+                version: PROTOCOL_VERSION
+            },
+            Request::Status,
+            Request::Flush,
+            Request::Subscribe,
+        ]
+    );
+}
+
+#[test]
+fn v1_client_talks_to_current_daemon_without_handshake() {
+    let temp = TempDir::new().unwrap();
+    let (path, listener) = socket(&temp);
+    let server = spawn_v2_daemon(listener);
+
+    let stream = UnixStream::connect(&path).unwrap();
+    let mut writer = BufWriter::new(stream.try_clone().unwrap());
+    std::io::Write::write_all(
+        &mut writer,
+        b"{\"cmd\":\"status\",\"ttl_ms\":null,\"reason\":null}\n",
+    )
+    .unwrap();
+    std::io::Write::flush(&mut writer).unwrap();
+    let mut reader = BufReader::new(stream);
+    let response: Response<DaemonStatus> = read_message(&mut reader).unwrap().unwrap();
+    assert!(response.ok);
+    assert_eq!(response.result.unwrap().total_flushes, 3);
+    drop(writer);
+    drop(reader);
+
+    assert_eq!(server.join().unwrap(), vec![Request::Status]);
+}
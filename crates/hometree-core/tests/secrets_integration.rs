@@ -25,9 +25,12 @@ fn make_secrets_config(temp: &TempDir) -> (SecretsConfig, PathBuf) {
         backend: "age".to_string(),
         sidecar_suffix: ".age".to_string(),
         recipients: vec![recipient],
+        recipients_files: Vec::new(),
+        recipients_dir: None,
         identity_files: vec![identity_path.clone()],
         rules: vec![rule],
         backup_policy: BackupPolicy::Encrypt,
+        stale_after_days: 365,
     };
 
     (config, identity_path)
@@ -38,14 +41,14 @@ fn age_backend_encrypts_and_decrypts_round_trip() {
     let temp = TempDir::new().unwrap();
     let (secrets_cfg, _identity_path) = make_secrets_config(&temp);
 
-    let backend = AgeBackend::from_config(&secrets_cfg).expect("backend from config");
+    let backend = AgeBackend::from_config(&secrets_cfg, temp.path()).expect("backend from config");
     let plaintext = b"super secret value";
 
     let ciphertext = backend.encrypt(plaintext).expect("encrypt");
     assert_ne!(ciphertext, plaintext);
 
     let decrypted = backend.decrypt(&ciphertext).expect("decrypt");
-    assert_eq!(decrypted, plaintext);
+    assert_eq!(decrypted.as_bytes(), plaintext);
 }
 
 #[test]